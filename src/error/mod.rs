@@ -8,6 +8,8 @@ pub enum ProtocolError {
     UnknownVariant,
     #[fail(display = "wrong variant has been given")]
     WrongVariant,
+    #[fail(display = "message is missing a parameter")]
+    MissingParameter,
     #[fail(display = "io error")]
     IOError(std::io::Error),
     #[fail(display = "could not convert from int")]
@@ -16,6 +18,8 @@ pub enum ProtocolError {
     Utf8Error(std::string::FromUtf8Error),
     #[fail(display = "failed to parse char as utf16")]
     CharError,
+    #[fail(display = "unknown TimeSpec: {}", _0)]
+    UnknownTimeSpec(i8),
  }
 
 // impl std::error::Error for ErrorKind {}
@@ -0,0 +1,459 @@
+//! Export of the messages of a buffer to log files
+//!
+//! The [`Exporter`] writes messages one by one, so an export can be streamed
+//! while the backlog is still being fetched. Messages have to be written from oldest to newest.
+//!
+//! All text formats strip mIRC format codes and insert their own day change separators,
+//! `DayChange` and `Markerline` messages generated by clients are skipped.
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use failure::Error;
+use time::{Date, OffsetDateTime, PrimitiveDateTime, UtcOffset};
+
+use crate::formatting::{self, MessageEvent};
+use crate::primitive::{BufferInfo, LocalZone, Message, MessageType};
+
+/// The file formats messages can be exported to
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Timestamped text as rendered by the quassel client
+    Plain,
+    /// The log format of irssi with its default theme
+    Irssi,
+    /// The tab separated log format of weechat
+    Weechat,
+    /// One JSON object with all fields of the message per line
+    JsonLines,
+}
+
+impl ExportFormat {
+    /// File extension used for log files of this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Plain | ExportFormat::Irssi | ExportFormat::Weechat => "log",
+            ExportFormat::JsonLines => "jsonl",
+        }
+    }
+}
+
+/// Writes the messages of a single buffer in an [`ExportFormat`]
+#[derive(Debug)]
+pub struct Exporter<W: Write> {
+    writer: W,
+    format: ExportFormat,
+    zone: LocalZone,
+    buffer: BufferInfo,
+    last_date: Option<Date>,
+}
+
+impl<W: Write> Exporter<W> {
+    /// Create an exporter for the messages of `buffer`
+    ///
+    /// Times are written in the `zone`, which also decides where days change.
+    pub fn new(writer: W, format: ExportFormat, buffer: &BufferInfo, zone: LocalZone) -> Self {
+        Self {
+            writer,
+            format,
+            zone,
+            buffer: buffer.clone(),
+            last_date: None,
+        }
+    }
+
+    /// Write a single message
+    pub fn write(&mut self, message: &Message) -> Result<(), Error> {
+        if self.format == ExportFormat::JsonLines {
+            return Ok(writeln!(self.writer, "{}", json(message))?);
+        }
+
+        if message
+            .msg_type
+            .intersects(MessageType::DAY_CHANGE | MessageType::MARKERLINE)
+        {
+            return Ok(());
+        }
+
+        let time = self.local_time(message.time());
+        match self.last_date {
+            None if self.format == ExportFormat::Irssi => writeln!(
+                self.writer,
+                "--- Log opened {}",
+                time.format("%a %b %d %H:%M:%S %Y")
+            )?,
+            Some(last_date) if last_date != time.date() => self.write_day_change(time.date())?,
+            _ => (),
+        }
+        self.last_date = Some(time.date());
+
+        let event = MessageEvent::from(message);
+        let line = match self.format {
+            ExportFormat::Plain => format!(
+                "[{}] {} {}",
+                time.format("%Y-%m-%d %H:%M:%S"),
+                event.sender_text(),
+                event
+            ),
+            ExportFormat::Irssi => format!("{} {}", time.format("%H:%M"), irssi(message, &event)),
+            ExportFormat::Weechat => {
+                let (prefix, text) = weechat(&event);
+                format!("{}\t{}\t{}", time.format("%Y-%m-%d %H:%M:%S"), prefix, text)
+            }
+            ExportFormat::JsonLines => unreachable!(),
+        };
+
+        writeln!(self.writer, "{}", formatting::strip(&line))?;
+        Ok(())
+    }
+
+    /// Write multiple messages, like a page of backlog
+    pub fn write_all(&mut self, messages: &[Message]) -> Result<(), Error> {
+        for message in messages {
+            self.write(message)?;
+        }
+
+        Ok(())
+    }
+
+    /// Finish the export and return the writer
+    pub fn finish(mut self) -> Result<W, Error> {
+        if self.format == ExportFormat::Irssi && self.last_date.is_some() {
+            let now = self.local_time(OffsetDateTime::now_utc());
+            writeln!(
+                self.writer,
+                "--- Log closed {}",
+                now.format("%a %b %d %H:%M:%S %Y")
+            )?;
+        }
+
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    /// The buffer the messages belong to
+    pub fn buffer(&self) -> &BufferInfo {
+        &self.buffer
+    }
+
+    fn write_day_change(&mut self, date: Date) -> Result<(), Error> {
+        match self.format {
+            ExportFormat::Plain => writeln!(self.writer, "{}", MessageEvent::DayChange { date })?,
+            ExportFormat::Irssi => writeln!(
+                self.writer,
+                "--- Day changed {}",
+                date.format("%a %b %d %Y")
+            )?,
+            ExportFormat::Weechat | ExportFormat::JsonLines => (),
+        }
+
+        Ok(())
+    }
+
+    fn local_time(&self, time: OffsetDateTime) -> OffsetDateTime {
+        let utc = time.to_offset(UtcOffset::UTC);
+        time.to_offset(
+            self.zone
+                .offset_at(PrimitiveDateTime::new(utc.date(), utc.time())),
+        )
+    }
+}
+
+/// Name of the log file of a buffer, unsafe characters in the name are replaced with `_`
+pub fn log_file_name(buffer: &BufferInfo, format: ExportFormat) -> String {
+    let name: String = buffer
+        .name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    format!("{}-{}.{}", buffer.network_id, name, format.extension())
+}
+
+/// Export the messages of a buffer to its log file in `dir`
+///
+/// Returns the path of the written file.
+pub fn export_buffer<P: AsRef<Path>>(
+    dir: P,
+    buffer: &BufferInfo,
+    messages: &[Message],
+    format: ExportFormat,
+    zone: LocalZone,
+) -> Result<PathBuf, Error> {
+    let path = dir.as_ref().join(log_file_name(buffer, format));
+
+    let mut exporter = Exporter::new(BufWriter::new(File::create(&path)?), format, buffer, zone);
+    exporter.write_all(messages)?;
+    exporter.finish()?;
+
+    Ok(path)
+}
+
+/// Format a message like the default theme of irssi
+fn irssi(message: &Message, event: &MessageEvent) -> String {
+    match event {
+        MessageEvent::Plain { nick, text } => {
+            format!("<{}{}> {}", mode_prefix(message), nick, text)
+        }
+        MessageEvent::Notice { nick, text } => format!("-{}- {}", nick, text),
+        MessageEvent::Action { nick, text } => format!(" * {} {}", nick, text),
+        MessageEvent::Nick { nick, new_nick } => {
+            format!("-!- {} is now known as {}", nick, new_nick)
+        }
+        MessageEvent::Mode {
+            nick: Some(nick),
+            modes,
+        } => format!("-!- mode/{} [{}] by {}", message.buffer.name, modes, nick),
+        MessageEvent::Join {
+            nick,
+            host,
+            channel,
+        } => format!("-!- {} [{}] has joined {}", nick, host, channel),
+        MessageEvent::Part {
+            nick,
+            host,
+            channel,
+            reason,
+        } => format!(
+            "-!- {} [{}] has left {} [{}]",
+            nick,
+            host,
+            channel,
+            reason.as_deref().unwrap_or_default()
+        ),
+        MessageEvent::Quit { nick, host, reason } => format!(
+            "-!- {} [{}] has quit [{}]",
+            nick,
+            host,
+            reason.as_deref().unwrap_or_default()
+        ),
+        MessageEvent::Kick {
+            nick,
+            kicked,
+            channel,
+            reason,
+        } => format!(
+            "-!- {} was kicked from {} by {} [{}]",
+            kicked,
+            channel,
+            nick,
+            reason.as_deref().unwrap_or_default()
+        ),
+        event => format!("-!- {}", event),
+    }
+}
+
+/// The prefix column and the text of a message in the weechat log format
+fn weechat(event: &MessageEvent) -> (String, String) {
+    match event {
+        MessageEvent::Plain { nick, text } => (nick.clone(), text.clone()),
+        MessageEvent::Notice { nick, text } => (s!("--"), format!("Notice({}): {}", nick, text)),
+        MessageEvent::Action { .. } => (s!(" *"), event.to_string()),
+        MessageEvent::Join { .. } | MessageEvent::NetsplitJoin(_) => (s!("-->"), event.to_string()),
+        MessageEvent::Part { .. }
+        | MessageEvent::Quit { .. }
+        | MessageEvent::Kick { .. }
+        | MessageEvent::Kill { .. }
+        | MessageEvent::NetsplitQuit(_) => (s!("<--"), event.to_string()),
+        MessageEvent::Error { .. } => (s!("=!="), event.to_string()),
+        event => (s!("--"), event.to_string()),
+    }
+}
+
+/// The highest channel mode of the sender, a space if it has none
+fn mode_prefix(message: &Message) -> char {
+    #[cfg(feature = "sender-prefixes")]
+    return message.sender_prefixes.chars().next().unwrap_or(' ');
+    #[cfg(not(feature = "sender-prefixes"))]
+    return {
+        let _ = message;
+        ' '
+    };
+}
+
+/// Format all fields of a message as a JSON object
+fn json(message: &Message) -> String {
+    let mut fields = vec![
+        format!("\"msg_id\":{}", message.msg_id),
+        format!("\"timestamp\":{}", message.timestamp),
+        format!(
+            "\"time\":{}",
+            json_string(&message.time().format("%Y-%m-%dT%H:%M:%SZ"))
+        ),
+        format!("\"msg_type\":{}", message.msg_type.bits()),
+        format!("\"flags\":{}", message.flags.bits()),
+        format!(
+            "\"buffer\":{{\"id\":{},\"network_id\":{},\"buffer_type\":{},\"name\":{}}}",
+            message.buffer.id,
+            message.buffer.network_id,
+            message.buffer.buffer_type as i16,
+            json_string(&message.buffer.name)
+        ),
+        format!("\"sender\":{}", json_string(&message.sender)),
+    ];
+
+    #[cfg(feature = "sender-prefixes")]
+    fields.push(format!(
+        "\"sender_prefixes\":{}",
+        json_string(&message.sender_prefixes)
+    ));
+
+    #[cfg(feature = "rich-messages")]
+    {
+        fields.push(format!("\"real_name\":{}", json_string(&message.real_name)));
+        fields.push(format!(
+            "\"avatar_url\":{}",
+            json_string(&message.avatar_url)
+        ));
+    }
+
+    fields.push(format!("\"content\":{}", json_string(&message.content)));
+
+    format!("{{{}}}", fields.join(","))
+}
+
+/// Quote and escape a string for JSON
+fn json_string(text: &str) -> String {
+    let mut res = String::with_capacity(text.len() + 2);
+    res.push('"');
+
+    for c in text.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c if c.is_control() => res.push_str(&format!("\\u{:04x}", c as u32)),
+            c => res.push(c),
+        }
+    }
+
+    res.push('"');
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::primitive::BufferType;
+
+    use super::*;
+
+    fn buffer() -> BufferInfo {
+        BufferInfo {
+            id: 1,
+            network_id: 2,
+            buffer_type: BufferType::Channel,
+            name: s!("#test"),
+        }
+    }
+
+    fn message(msg_id: i32, seconds: i64, msg_type: MessageType, content: &str) -> Message {
+        Message {
+            msg_type,
+            buffer: buffer(),
+            sender: s!("nick!~user@host"),
+            ..Message::test(msg_id, content)
+                .at(seconds)
+                .with_sender_prefixes("@")
+                .with_real_name("Real \"Name\"")
+        }
+    }
+
+    fn get_messages() -> Vec<Message> {
+        vec![
+            // Tuesday, January 5, 2021 23:30:00 UTC
+            message(1, 1609889400, MessageType::PLAIN, "\x02hello\x02"),
+            message(2, 1609889460, MessageType::ACTION, "waves"),
+            message(3, 1609889500, MessageType::DAY_CHANGE, ""),
+            message(4, 1609891200, MessageType::JOIN, ""),
+            message(5, 1609891260, MessageType::QUIT, "bye"),
+        ]
+    }
+
+    fn export(format: ExportFormat, zone: LocalZone) -> String {
+        let mut exporter = Exporter::new(Vec::new(), format, &buffer(), zone);
+        exporter.write_all(&get_messages()).unwrap();
+        String::from_utf8(exporter.writer).unwrap()
+    }
+
+    #[test]
+    fn export_plain() {
+        assert_eq!(
+            export(ExportFormat::Plain, LocalZone::Utc),
+            "[2021-01-05 23:30:00] <nick> hello\n\
+             [2021-01-05 23:31:00] -*- nick waves\n\
+             {Day changed to Wednesday, January 6, 2021}\n\
+             [2021-01-06 00:00:00] --> nick (~user@host) has joined #test\n\
+             [2021-01-06 00:01:00] <-- nick (~user@host) has quit (bye)\n"
+        );
+
+        // In UTC+1 all messages are on the same day
+        let zone = LocalZone::Fixed(UtcOffset::hours(1));
+        assert!(
+            export(ExportFormat::Plain, zone).starts_with("[2021-01-06 00:30:00] <nick> hello\n")
+        );
+        assert!(!export(ExportFormat::Plain, zone).contains("Day changed"));
+    }
+
+    #[cfg(feature = "sender-prefixes")]
+    #[test]
+    fn export_irssi() {
+        assert_eq!(
+            export(ExportFormat::Irssi, LocalZone::Utc),
+            "--- Log opened Tue Jan 05 23:30:00 2021\n\
+             23:30 <@nick> hello\n\
+             23:31  * nick waves\n\
+             --- Day changed Wed Jan 06 2021\n\
+             00:00 -!- nick [~user@host] has joined #test\n\
+             00:01 -!- nick [~user@host] has quit [bye]\n"
+        );
+    }
+
+    #[test]
+    fn export_weechat() {
+        assert_eq!(
+            export(ExportFormat::Weechat, LocalZone::Utc),
+            "2021-01-05 23:30:00\tnick\thello\n\
+             2021-01-05 23:31:00\t *\tnick waves\n\
+             2021-01-06 00:00:00\t-->\tnick (~user@host) has joined #test\n\
+             2021-01-06 00:01:00\t<--\tnick (~user@host) has quit (bye)\n"
+        );
+    }
+
+    #[cfg(feature = "all-quassel-features")]
+    #[test]
+    fn export_json_lines() {
+        let export = export(ExportFormat::JsonLines, LocalZone::Utc);
+        let lines: Vec<&str> = export.lines().collect();
+
+        assert_eq!(lines.len(), 5);
+        assert_eq!(
+            lines[0],
+            "{\"msg_id\":1,\"timestamp\":1609889400000,\"time\":\"2021-01-05T23:30:00Z\",\
+             \"msg_type\":1,\"flags\":0,\
+             \"buffer\":{\"id\":1,\"network_id\":2,\"buffer_type\":2,\"name\":\"#test\"},\
+             \"sender\":\"nick!~user@host\",\"sender_prefixes\":\"@\",\
+             \"real_name\":\"Real \\\"Name\\\"\",\"avatar_url\":\"\",\
+             \"content\":\"\\u0002hello\\u0002\"}"
+        );
+    }
+
+    #[test]
+    fn export_file_name() {
+        let mut buffer = buffer();
+        buffer.name = s!("a/b:c");
+
+        assert_eq!(
+            log_file_name(&buffer, ExportFormat::JsonLines),
+            "2-a_b_c.jsonl"
+        );
+    }
+}
@@ -0,0 +1,321 @@
+//! Matching of the rule expressions used by the ignore list and highlight rules
+//!
+//! Follows the semantics of quassels `ExpressionMatch` so rules behave the same
+//! as in the official client and core.
+
+use std::sync::{Arc, Mutex};
+
+use regex::{Regex, RegexBuilder};
+
+use crate::hostmask::wildcard_to_regex;
+
+/// How an expression is interpreted
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MatchMode {
+    /// Match a phrase surrounded by word boundaries
+    Phrase,
+    /// Match any of multiple phrases separated by newlines
+    MultiPhrase,
+    /// Match the whole text against a wildcard, `!` in front inverts the match
+    Wildcard,
+    /// Match the whole text against any of multiple wildcards separated by `;`,
+    /// wildcards starting with `!` invert the match
+    MultiWildcard,
+    /// Match a regular expression anywhere in the text, `!` in front inverts the match
+    RegEx,
+}
+
+/// A compiled expression as used by [`IgnoreListItem`] and [`HighlightRule`]
+///
+/// [`IgnoreListItem`]: crate::message::objects::IgnoreListItem
+/// [`HighlightRule`]: crate::message::objects::HighlightRule
+#[derive(Clone, Debug)]
+pub struct ExpressionMatch {
+    empty: bool,
+    valid: bool,
+    positive: Vec<Regex>,
+    inverted: Vec<Regex>,
+}
+
+impl ExpressionMatch {
+    pub fn new(expression: &str, mode: MatchMode, case_sensitive: bool) -> Self {
+        let mut res = Self {
+            empty: expression.trim().is_empty(),
+            valid: true,
+            positive: Vec::new(),
+            inverted: Vec::new(),
+        };
+
+        if res.empty {
+            return res;
+        }
+
+        let (positive, inverted) = match mode {
+            MatchMode::Phrase => (vec![phrase_to_regex(expression)], Vec::new()),
+            MatchMode::MultiPhrase => (
+                expression
+                    .split('\n')
+                    .map(str::trim)
+                    .filter(|phrase| !phrase.is_empty())
+                    .map(phrase_to_regex)
+                    .collect(),
+                Vec::new(),
+            ),
+            MatchMode::Wildcard => match split_inverted(expression) {
+                (true, wildcard) => (Vec::new(), vec![wildcard_to_regex(&wildcard)]),
+                (false, wildcard) => (vec![wildcard_to_regex(&wildcard)], Vec::new()),
+            },
+            MatchMode::MultiWildcard => {
+                let mut positive = Vec::new();
+                let mut inverted = Vec::new();
+
+                for wildcard in split_wildcards(expression) {
+                    match split_inverted(&wildcard) {
+                        (true, wildcard) => inverted.push(wildcard_to_regex(&wildcard)),
+                        (false, wildcard) => positive.push(wildcard_to_regex(&wildcard)),
+                    }
+                }
+
+                (positive, inverted)
+            }
+            MatchMode::RegEx => match split_inverted(expression) {
+                (true, regex) => (Vec::new(), vec![regex]),
+                (false, regex) => (vec![regex], Vec::new()),
+            },
+        };
+
+        let build = |pattern: &String| {
+            RegexBuilder::new(pattern)
+                .case_insensitive(!case_sensitive)
+                .build()
+        };
+
+        match (
+            positive.iter().map(build).collect::<Result<Vec<_>, _>>(),
+            inverted.iter().map(build).collect::<Result<Vec<_>, _>>(),
+        ) {
+            (Ok(positive), Ok(inverted)) => {
+                res.positive = positive;
+                res.inverted = inverted;
+            }
+            _ => res.valid = false,
+        }
+
+        res
+    }
+
+    /// Match the text against the expression
+    ///
+    /// `match_empty` is returned if the expression is empty, invalid expressions never match.
+    pub fn matches(&self, text: &str, match_empty: bool) -> bool {
+        if self.empty {
+            return match_empty;
+        }
+
+        if !self.valid {
+            return false;
+        }
+
+        if self.inverted.iter().any(|regex| regex.is_match(text)) {
+            return false;
+        }
+
+        self.positive.is_empty() && !self.inverted.is_empty()
+            || self.positive.iter().any(|regex| regex.is_match(text))
+    }
+
+    /// The expression is empty and matches according to `match_empty`
+    pub fn is_empty(&self) -> bool {
+        self.empty
+    }
+
+    /// The expression compiled successfully
+    pub fn is_valid(&self) -> bool {
+        self.valid
+    }
+}
+
+/// An [`ExpressionMatch`] that is only compiled again when its expression changes
+///
+/// Caches are ignored when comparing, so they can be part of synced objects.
+#[derive(Debug, Default)]
+pub struct CachedMatch {
+    cache: Mutex<Option<CacheEntry>>,
+}
+
+/// The expression, mode and case sensitivity the cached [`ExpressionMatch`] was compiled from
+type CacheEntry = (String, MatchMode, bool, Arc<ExpressionMatch>);
+
+impl CachedMatch {
+    /// Get the compiled expression, compiling it if it differs from the cached one
+    pub fn get(
+        &self,
+        expression: &str,
+        mode: MatchMode,
+        case_sensitive: bool,
+    ) -> Arc<ExpressionMatch> {
+        let mut cache = self.cache.lock().unwrap();
+
+        match &*cache {
+            Some((cached, cached_mode, cached_case, matcher))
+                if cached == expression
+                    && *cached_mode == mode
+                    && *cached_case == case_sensitive =>
+            {
+                matcher.clone()
+            }
+            _ => {
+                let matcher = Arc::new(ExpressionMatch::new(expression, mode, case_sensitive));
+                *cache = Some((expression.to_owned(), mode, case_sensitive, matcher.clone()));
+                matcher
+            }
+        }
+    }
+}
+
+impl Clone for CachedMatch {
+    fn clone(&self) -> Self {
+        Self {
+            cache: Mutex::new(self.cache.lock().unwrap().clone()),
+        }
+    }
+}
+
+impl PartialEq for CachedMatch {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+/// Convert a phrase to a regular expression matching it between word boundaries
+fn phrase_to_regex(phrase: &str) -> String {
+    format!(r"(?:^|\W){}(?:\W|$)", regex::escape(phrase))
+}
+
+/// Split off a leading `!` that marks an inverted expression, `\!` escapes a literal `!`
+fn split_inverted(expression: &str) -> (bool, String) {
+    if let Some(expression) = expression.strip_prefix('!') {
+        (true, expression.to_string())
+    } else if let Some(expression) = expression.strip_prefix(r"\!") {
+        (false, format!("!{}", expression))
+    } else {
+        (false, expression.to_string())
+    }
+}
+
+/// Split a list of wildcards on `;`, `\;` escapes a literal `;`
+///
+/// Wildcards are trimmed and empty wildcards are skipped.
+fn split_wildcards(expression: &str) -> Vec<String> {
+    let mut res = Vec::new();
+    let mut current = String::new();
+    let mut chars = expression.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(';') => current.push(';'),
+                Some(next) => {
+                    current.push('\\');
+                    current.push(next);
+                }
+                None => current.push('\\'),
+            },
+            ';' => res.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    res.push(current);
+
+    res.into_iter()
+        .map(|wildcard| wildcard.trim().to_string())
+        .filter(|wildcard| !wildcard.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expression_phrase() {
+        let matcher = ExpressionMatch::new("test", MatchMode::Phrase, false);
+
+        assert!(matcher.matches("a Test message", false));
+        assert!(matcher.matches("test: hi", false));
+        assert!(!matcher.matches("testing", false));
+
+        let matcher = ExpressionMatch::new("one\ntwo words", MatchMode::MultiPhrase, true);
+        assert!(matcher.matches("two words!", false));
+        assert!(matcher.matches("one", false));
+        assert!(!matcher.matches("One", false));
+    }
+
+    #[test]
+    fn expression_wildcard() {
+        let matcher = ExpressionMatch::new("*!*@*.example.com", MatchMode::Wildcard, false);
+        assert!(matcher.matches("nick!user@host.EXAMPLE.com", false));
+        assert!(!matcher.matches("nick!user@example.org", false));
+
+        let matcher = ExpressionMatch::new("!#quassel*", MatchMode::Wildcard, false);
+        assert!(!matcher.matches("#quassel-test", false));
+        assert!(matcher.matches("#other", false));
+
+        let matcher = ExpressionMatch::new(r"\!bang", MatchMode::Wildcard, false);
+        assert!(matcher.matches("!bang", false));
+    }
+
+    #[test]
+    fn expression_multi_wildcard() {
+        let matcher = ExpressionMatch::new("#a*; #b ;!#ab", MatchMode::MultiWildcard, false);
+        assert!(matcher.matches("#abc", false));
+        assert!(matcher.matches("#b", false));
+        assert!(!matcher.matches("#ab", false));
+        assert!(!matcher.matches("#c", false));
+
+        let matcher = ExpressionMatch::new("!#a;!#b", MatchMode::MultiWildcard, false);
+        assert!(matcher.matches("#c", false));
+        assert!(!matcher.matches("#b", false));
+
+        let matcher = ExpressionMatch::new(r"a\;b", MatchMode::MultiWildcard, false);
+        assert!(matcher.matches("a;b", false));
+    }
+
+    #[test]
+    fn expression_regex() {
+        let matcher = ExpressionMatch::new(r"^\d+$", MatchMode::RegEx, false);
+        assert!(matcher.matches("1234", false));
+        assert!(!matcher.matches("12a", false));
+
+        let matcher = ExpressionMatch::new("!bot", MatchMode::RegEx, true);
+        assert!(matcher.matches("user", false));
+        assert!(!matcher.matches("somebot", false));
+
+        let matcher = ExpressionMatch::new("(unclosed", MatchMode::RegEx, false);
+        assert!(!matcher.is_valid());
+        assert!(!matcher.matches("(unclosed", true));
+    }
+
+    #[test]
+    fn expression_empty() {
+        let matcher = ExpressionMatch::new(" ", MatchMode::MultiWildcard, false);
+        assert!(matcher.is_empty());
+        assert!(matcher.matches("anything", true));
+        assert!(!matcher.matches("anything", false));
+    }
+
+    #[test]
+    fn expression_cached() {
+        let cache = CachedMatch::default();
+
+        let first = cache.get("test", MatchMode::Phrase, false);
+        assert!(Arc::ptr_eq(
+            &first,
+            &cache.get("test", MatchMode::Phrase, false)
+        ));
+
+        let changed = cache.get("test", MatchMode::Phrase, true);
+        assert!(!Arc::ptr_eq(&first, &changed));
+        assert!(!changed.matches("Test", false));
+    }
+}
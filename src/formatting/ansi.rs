@@ -0,0 +1,114 @@
+use super::{Color, Span, Style};
+
+/// Render the spans with ANSI escape sequences for display in a terminal
+///
+/// Colors are emitted as 24-bit truecolor sequences, monospace is ignored.
+/// Control characters in the text are removed, see [`escape_ansi`].
+pub fn ansi(spans: &[Span]) -> String {
+    let mut res = String::new();
+    let mut styled = false;
+
+    for span in spans {
+        if styled {
+            res.push_str("\x1b[0m");
+            styled = false;
+        }
+
+        if !span.style.is_plain() {
+            let codes = sgr(&span.style);
+            if !codes.is_empty() {
+                res.push_str(&format!("\x1b[{}m", codes.join(";")));
+                styled = true;
+            }
+        }
+
+        res.push_str(&escape_ansi(&span.text));
+    }
+
+    if styled {
+        res.push_str("\x1b[0m");
+    }
+
+    res
+}
+
+/// Remove control characters from text so it can not inject escape sequences into a terminal
+///
+/// The mIRC formatting codes are already parsed into spans, tabs are kept.
+pub fn escape_ansi(text: &str) -> String {
+    text.chars()
+        .filter(|c| *c == '\t' || !c.is_control())
+        .collect()
+}
+
+fn sgr(style: &Style) -> Vec<String> {
+    let mut codes: Vec<String> = Vec::new();
+
+    if style.bold {
+        codes.push(s!("1"));
+    }
+
+    if style.italic {
+        codes.push(s!("3"));
+    }
+
+    if style.underline {
+        codes.push(s!("4"));
+    }
+
+    if style.reverse {
+        codes.push(s!("7"));
+    }
+
+    if style.strikethrough {
+        codes.push(s!("9"));
+    }
+
+    if let Some(color) = style.foreground {
+        codes.push(truecolor(38, color));
+    }
+
+    if let Some(color) = style.background {
+        codes.push(truecolor(48, color));
+    }
+
+    codes
+}
+
+fn truecolor(code: u8, color: Color) -> String {
+    let (r, g, b) = color.rgb();
+    format!("{};2;{};{};{}", code, r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatting::parse;
+
+    use super::*;
+
+    #[test]
+    fn ansi_plain() {
+        assert_eq!(ansi(&parse("just text")), "just text");
+        assert_eq!(ansi(&parse("\x11mono\x11")), "mono");
+    }
+
+    #[test]
+    fn ansi_escape() {
+        assert_eq!(
+            ansi(&parse("clear\x1b[2J screen\x07\u{9b}2J\tdone")),
+            "clear[2J screen2J\tdone"
+        );
+        assert_eq!(
+            ansi(&parse("\x02bold\x1b]0;title\x07")),
+            "\x1b[1mbold]0;title\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn ansi_styles() {
+        assert_eq!(
+            ansi(&parse("a \x02\x1dbold\x0f \x0304,01red")),
+            "a \x1b[1;3mbold\x1b[0m \x1b[38;2;255;0;0;48;2;0;0;0mred\x1b[0m"
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use super::{Color, Span, Style};
+
+/// Default colors used for reversed text without explicit colors
+const DEFAULT_FOREGROUND: Color = Color::Palette(1);
+const DEFAULT_BACKGROUND: Color = Color::Palette(0);
+
+/// Render the spans as HTML
+///
+/// Text is escaped and every styled span is wrapped in a `<span>` with inline css.
+pub fn html(spans: &[Span]) -> String {
+    let mut res = String::new();
+
+    for span in spans {
+        let text = escape_html(&span.text);
+
+        if span.style.is_plain() {
+            res.push_str(&text);
+        } else {
+            res.push_str(&format!(
+                "<span style=\"{}\">{}</span>",
+                css(&span.style),
+                text
+            ));
+        }
+    }
+
+    res
+}
+
+/// Escape text for use in HTML
+pub fn escape_html(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            '\'' => res.push_str("&#39;"),
+            c => res.push(c),
+        }
+    }
+
+    res
+}
+
+fn css(style: &Style) -> String {
+    let mut rules: Vec<String> = Vec::new();
+
+    if style.bold {
+        rules.push(s!("font-weight: bold"));
+    }
+
+    if style.italic {
+        rules.push(s!("font-style: italic"));
+    }
+
+    match (style.underline, style.strikethrough) {
+        (true, true) => rules.push(s!("text-decoration: underline line-through")),
+        (true, false) => rules.push(s!("text-decoration: underline")),
+        (false, true) => rules.push(s!("text-decoration: line-through")),
+        (false, false) => (),
+    }
+
+    if style.monospace {
+        rules.push(s!("font-family: monospace"));
+    }
+
+    let (foreground, background) = if style.reverse {
+        (
+            Some(style.background.unwrap_or(DEFAULT_BACKGROUND)),
+            Some(style.foreground.unwrap_or(DEFAULT_FOREGROUND)),
+        )
+    } else {
+        (style.foreground, style.background)
+    };
+
+    if let Some(color) = foreground {
+        rules.push(format!("color: {}", color.hex()));
+    }
+
+    if let Some(color) = background {
+        rules.push(format!("background-color: {}", color.hex()));
+    }
+
+    rules.join("; ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatting::parse;
+
+    use super::*;
+
+    #[test]
+    fn html_escape() {
+        assert_eq!(
+            html(&parse("<b>\"tom\" & 'jerry'</b>")),
+            "&lt;b&gt;&quot;tom&quot; &amp; &#39;jerry&#39;&lt;/b&gt;"
+        );
+    }
+
+    #[test]
+    fn html_styles() {
+        assert_eq!(
+            html(&parse("a \x02\x1fbold\x0f \x0304,01red\x03 \x16rev")),
+            "a <span style=\"font-weight: bold; text-decoration: underline\">bold</span> \
+             <span style=\"color: #ff0000; background-color: #000000\">red</span> \
+             <span style=\"color: #ffffff; background-color: #000000\">rev</span>"
+        );
+    }
+}
@@ -0,0 +1,521 @@
+//! Parsing and rendering of mIRC format codes as contained in [`Message::content`]
+//!
+//! [`parse`] turns the content into a list of [`Span`]s of text that share the same [`Style`],
+//! which can then be rendered with [`plain`], [`html`] or [`ansi`], or encoded back to
+//! mIRC format codes with [`encode`].
+//!
+//! [`Message::content`]: crate::primitive::Message::content
+
+mod ansi;
+mod html;
+mod render;
+
+pub use ansi::*;
+pub use html::*;
+pub use render::*;
+
+/// Toggles bold text
+pub const BOLD: char = '\x02';
+/// Sets the foreground and background color as a 99 color palette index
+pub const COLOR: char = '\x03';
+/// Sets the foreground and background color as a hex RGB value
+pub const HEX_COLOR: char = '\x04';
+/// Resets all formatting
+pub const RESET: char = '\x0f';
+/// Toggles monospaced text
+pub const MONOSPACE: char = '\x11';
+/// Toggles swapping of the foreground and background color
+pub const REVERSE: char = '\x16';
+/// Toggles italic text
+pub const ITALIC: char = '\x1d';
+/// Toggles strikethrough text
+pub const STRIKETHROUGH: char = '\x1e';
+/// Toggles underlined text
+pub const UNDERLINE: char = '\x1f';
+
+/// RGB values of the 99 color palette
+///
+/// Colors 0 to 15 are the classic mIRC colors, 16 to 98 the extended colors.
+/// Color 99 is the default color of the client and therefore not part of the palette.
+#[rustfmt::skip]
+pub const PALETTE: [(u8, u8, u8); 99] = [
+    (0xff, 0xff, 0xff), (0x00, 0x00, 0x00), (0x00, 0x00, 0x7f), (0x00, 0x93, 0x00),
+    (0xff, 0x00, 0x00), (0x7f, 0x00, 0x00), (0x9c, 0x00, 0x9c), (0xfc, 0x7f, 0x00),
+    (0xff, 0xff, 0x00), (0x00, 0xfc, 0x00), (0x00, 0x93, 0x93), (0x00, 0xff, 0xff),
+    (0x00, 0x00, 0xfc), (0xff, 0x00, 0xff), (0x7f, 0x7f, 0x7f), (0xd2, 0xd2, 0xd2),
+    (0x47, 0x00, 0x00), (0x47, 0x21, 0x00), (0x47, 0x47, 0x00), (0x32, 0x47, 0x00),
+    (0x00, 0x47, 0x00), (0x00, 0x47, 0x2c), (0x00, 0x47, 0x47), (0x00, 0x27, 0x47),
+    (0x00, 0x00, 0x47), (0x2e, 0x00, 0x47), (0x47, 0x00, 0x47), (0x47, 0x00, 0x2a),
+    (0x74, 0x00, 0x00), (0x74, 0x3a, 0x00), (0x74, 0x74, 0x00), (0x51, 0x74, 0x00),
+    (0x00, 0x74, 0x00), (0x00, 0x74, 0x49), (0x00, 0x74, 0x74), (0x00, 0x40, 0x74),
+    (0x00, 0x00, 0x74), (0x4b, 0x00, 0x74), (0x74, 0x00, 0x74), (0x74, 0x00, 0x45),
+    (0xb5, 0x00, 0x00), (0xb5, 0x63, 0x00), (0xb5, 0xb5, 0x00), (0x7d, 0xb5, 0x00),
+    (0x00, 0xb5, 0x00), (0x00, 0xb5, 0x71), (0x00, 0xb5, 0xb5), (0x00, 0x63, 0xb5),
+    (0x00, 0x00, 0xb5), (0x75, 0x00, 0xb5), (0xb5, 0x00, 0xb5), (0xb5, 0x00, 0x6b),
+    (0xff, 0x00, 0x00), (0xff, 0x8c, 0x00), (0xff, 0xff, 0x00), (0xb2, 0xff, 0x00),
+    (0x00, 0xff, 0x00), (0x00, 0xff, 0xa0), (0x00, 0xff, 0xff), (0x00, 0x8c, 0xff),
+    (0x00, 0x00, 0xff), (0xa5, 0x00, 0xff), (0xff, 0x00, 0xff), (0xff, 0x00, 0x98),
+    (0xff, 0x59, 0x59), (0xff, 0xb4, 0x59), (0xff, 0xff, 0x71), (0xcf, 0xff, 0x60),
+    (0x6f, 0xff, 0x6f), (0x65, 0xff, 0xc9), (0x6d, 0xff, 0xff), (0x59, 0xb4, 0xff),
+    (0x59, 0x59, 0xff), (0xc4, 0x59, 0xff), (0xff, 0x66, 0xff), (0xff, 0x59, 0xbc),
+    (0xff, 0x9c, 0x9c), (0xff, 0xd3, 0x9c), (0xff, 0xff, 0x9c), (0xe2, 0xff, 0x9c),
+    (0x9c, 0xff, 0x9c), (0x9c, 0xff, 0xdb), (0x9c, 0xff, 0xff), (0x9c, 0xd3, 0xff),
+    (0x9c, 0x9c, 0xff), (0xdc, 0x9c, 0xff), (0xff, 0x9c, 0xff), (0xff, 0x94, 0xd3),
+    (0x00, 0x00, 0x00), (0x13, 0x13, 0x13), (0x28, 0x28, 0x28), (0x36, 0x36, 0x36),
+    (0x4d, 0x4d, 0x4d), (0x65, 0x65, 0x65), (0x81, 0x81, 0x81), (0x9f, 0x9f, 0x9f),
+    (0xbc, 0xbc, 0xbc), (0xe2, 0xe2, 0xe2), (0xff, 0xff, 0xff),
+];
+
+/// A foreground or background color
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Color {
+    /// Index into the 99 color [`PALETTE`], set with `\x03`
+    Palette(u8),
+    /// RGB value, set with `\x04`
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    /// RGB value of the color
+    pub fn rgb(&self) -> (u8, u8, u8) {
+        match self {
+            Color::Palette(i) => PALETTE[*i as usize % PALETTE.len()],
+            Color::Rgb(r, g, b) => (*r, *g, *b),
+        }
+    }
+
+    /// Color as a css hex color like `#ff0000`
+    pub fn hex(&self) -> String {
+        let (r, g, b) = self.rgb();
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    }
+}
+
+/// The formatting applied to a [`Span`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Style {
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikethrough: bool,
+    pub monospace: bool,
+    pub reverse: bool,
+    pub foreground: Option<Color>,
+    pub background: Option<Color>,
+}
+
+impl Style {
+    /// Returns true if no formatting is applied
+    pub fn is_plain(&self) -> bool {
+        *self == Style::default()
+    }
+}
+
+/// A piece of text with a single [`Style`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub style: Style,
+}
+
+/// Parse a string containing mIRC format codes into a list of styled [`Span`]s
+///
+/// Empty spans are omitted and consecutive text with the same style is merged into one span.
+pub fn parse(content: &str) -> Vec<Span> {
+    let mut spans: Vec<Span> = Vec::new();
+    let mut style = Style::default();
+    let mut text = String::new();
+
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let mut next = style.clone();
+
+        match c {
+            BOLD => next.bold = !next.bold,
+            ITALIC => next.italic = !next.italic,
+            UNDERLINE => next.underline = !next.underline,
+            STRIKETHROUGH => next.strikethrough = !next.strikethrough,
+            MONOSPACE => next.monospace = !next.monospace,
+            REVERSE => next.reverse = !next.reverse,
+            RESET => next = Style::default(),
+            COLOR => match take_digits(&mut chars) {
+                Some(fg) => {
+                    next.foreground = palette_color(fg);
+
+                    if let Some(bg) = take_separated(&mut chars, take_digits) {
+                        next.background = palette_color(bg);
+                    }
+                }
+                None => {
+                    next.foreground = None;
+                    next.background = None;
+                }
+            },
+            HEX_COLOR => match take_hex(&mut chars) {
+                Some(fg) => {
+                    next.foreground = Some(fg);
+
+                    if let Some(bg) = take_separated(&mut chars, take_hex) {
+                        next.background = Some(bg);
+                    }
+                }
+                None => {
+                    next.foreground = None;
+                    next.background = None;
+                }
+            },
+            c => {
+                text.push(c);
+                continue;
+            }
+        }
+
+        if next != style {
+            push_span(&mut spans, std::mem::take(&mut text), style);
+            style = next;
+        }
+    }
+
+    push_span(&mut spans, text, style);
+
+    spans
+}
+
+fn push_span(spans: &mut Vec<Span>, text: String, style: Style) {
+    if text.is_empty() {
+        return;
+    }
+
+    match spans.last_mut() {
+        Some(last) if last.style == style => last.text.push_str(&text),
+        _ => spans.push(Span { text, style }),
+    }
+}
+
+type Chars<'a> = std::iter::Peekable<std::str::Chars<'a>>;
+
+/// Color 99 is the default color and removes the color
+fn palette_color(i: u8) -> Option<Color> {
+    match i {
+        99 => None,
+        i => Some(Color::Palette(i)),
+    }
+}
+
+/// Take up to two digits
+fn take_digits(chars: &mut Chars) -> Option<u8> {
+    let mut res: Option<u8> = None;
+
+    for _ in 0..2 {
+        match chars.peek().and_then(|c| c.to_digit(10)) {
+            Some(digit) => {
+                chars.next();
+                res = Some(res.unwrap_or(0) * 10 + digit as u8);
+            }
+            None => break,
+        }
+    }
+
+    res
+}
+
+/// Take exactly six hex digits
+fn take_hex(chars: &mut Chars) -> Option<Color> {
+    let lookahead: String = chars.clone().take(6).collect();
+
+    if lookahead.len() != 6 || !lookahead.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    chars.nth(5);
+
+    let value = u32::from_str_radix(&lookahead, 16).ok()?;
+    Some(Color::Rgb(
+        (value >> 16) as u8,
+        (value >> 8) as u8,
+        value as u8,
+    ))
+}
+
+/// Take a `,` followed by a value, only consumes the `,` if the value can be taken
+fn take_separated<T>(chars: &mut Chars, take: fn(&mut Chars) -> Option<T>) -> Option<T> {
+    if chars.peek() != Some(&',') {
+        return None;
+    }
+
+    let mut lookahead = chars.clone();
+    lookahead.next();
+
+    let res = take(&mut lookahead)?;
+    *chars = lookahead;
+
+    Some(res)
+}
+
+/// Render the spans as plain text without any formatting
+pub fn plain(spans: &[Span]) -> String {
+    spans.iter().map(|span| span.text.as_str()).collect()
+}
+
+/// Remove all mIRC format codes from a string
+pub fn strip(content: &str) -> String {
+    plain(&parse(content))
+}
+
+/// Encode the spans back into a string with mIRC format codes
+pub fn encode(spans: &[Span]) -> String {
+    let mut res = String::new();
+    let mut current = Style::default();
+
+    for span in spans {
+        let style = &span.style;
+
+        if style.is_plain() && !current.is_plain() {
+            res.push(RESET);
+            current = Style::default();
+        }
+
+        let toggles = [
+            (current.bold, style.bold, BOLD),
+            (current.italic, style.italic, ITALIC),
+            (current.underline, style.underline, UNDERLINE),
+            (current.strikethrough, style.strikethrough, STRIKETHROUGH),
+            (current.monospace, style.monospace, MONOSPACE),
+            (current.reverse, style.reverse, REVERSE),
+        ];
+
+        for (old, new, code) in toggles {
+            if old != new {
+                res.push(code);
+            }
+        }
+
+        let foreground_changed = current.foreground != style.foreground;
+        // A text starting with `,` would be read as the background of a color code
+        let background_changed = current.background != style.background
+            || (foreground_changed && span.text.starts_with(','));
+
+        if background_changed {
+            encode_colors(&mut res, style, &span.text);
+        } else if foreground_changed {
+            encode_foreground(&mut res, style.foreground);
+        }
+
+        res.push_str(&span.text);
+        current = style.clone();
+    }
+
+    res
+}
+
+/// Encode only the foreground color, leaving the background untouched
+fn encode_foreground(res: &mut String, color: Option<Color>) {
+    match color {
+        Some(Color::Rgb(..)) => res.push_str(&format!("{}{}", HEX_COLOR, hex_code(color))),
+        color => res.push_str(&format!("{}{:02}", COLOR, palette_code(color))),
+    }
+}
+
+/// Encode both foreground and background color
+///
+/// Mixing palette and hex colors needs a trailing foreground code, which would take
+/// a `,` at the start of the following text as background. In that case the code
+/// is terminated with an empty bold toggle.
+fn encode_colors(res: &mut String, style: &Style, text: &str) {
+    let (fg, bg) = (style.foreground, style.background);
+
+    match (fg, bg) {
+        (Some(Color::Rgb(..)), Some(Color::Rgb(..))) => {
+            res.push_str(&format!("{}{},{}", HEX_COLOR, hex_code(fg), hex_code(bg)))
+        }
+        (_, Some(Color::Rgb(..))) => {
+            res.push_str(&format!("{}{},{}", HEX_COLOR, hex_code(fg), hex_code(bg)));
+            encode_foreground(res, fg);
+            terminate_code(res, text);
+        }
+        (Some(Color::Rgb(..)), _) => {
+            res.push_str(&format!("{}99,{:02}", COLOR, palette_code(bg)));
+            encode_foreground(res, fg);
+            terminate_code(res, text);
+        }
+        _ => res.push_str(&format!(
+            "{}{:02},{:02}",
+            COLOR,
+            palette_code(fg),
+            palette_code(bg)
+        )),
+    }
+}
+
+/// Separate a foreground code from text starting with `,`
+fn terminate_code(res: &mut String, text: &str) {
+    if text.starts_with(',') {
+        res.push(BOLD);
+        res.push(BOLD);
+    }
+}
+
+/// Palette index of a color, 99 for the default color
+fn palette_code(color: Option<Color>) -> u8 {
+    match color {
+        Some(Color::Palette(i)) => i,
+        _ => 99,
+    }
+}
+
+/// Hex code of a color, black for the default color
+fn hex_code(color: Option<Color>) -> String {
+    let (r, g, b) = color.map(|color| color.rgb()).unwrap_or((0, 0, 0));
+    format!("{:02X}{:02X}{:02X}", r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(text: &str, style: Style) -> Span {
+        Span {
+            text: s!(text),
+            style,
+        }
+    }
+
+    #[test]
+    fn parse_toggles() {
+        assert_eq!(
+            parse("a\x02b\x1dc\x02d\x0fe"),
+            vec![
+                span("a", Style::default()),
+                span(
+                    "b",
+                    Style {
+                        bold: true,
+                        ..Default::default()
+                    }
+                ),
+                span(
+                    "c",
+                    Style {
+                        bold: true,
+                        italic: true,
+                        ..Default::default()
+                    }
+                ),
+                span(
+                    "d",
+                    Style {
+                        italic: true,
+                        ..Default::default()
+                    }
+                ),
+                span("e", Style::default()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_colors() {
+        assert_eq!(
+            parse("\x034red\x03,5comma\x0304,12both\x03none"),
+            vec![
+                span(
+                    "red",
+                    Style {
+                        foreground: Some(Color::Palette(4)),
+                        ..Default::default()
+                    }
+                ),
+                span(",5comma", Style::default(),),
+                span(
+                    "both",
+                    Style {
+                        foreground: Some(Color::Palette(4)),
+                        background: Some(Color::Palette(12)),
+                        ..Default::default()
+                    }
+                ),
+                span("none", Style::default()),
+            ]
+        );
+
+        assert_eq!(
+            parse("\x03123"),
+            vec![span(
+                "3",
+                Style {
+                    foreground: Some(Color::Palette(12)),
+                    ..Default::default()
+                }
+            )]
+        );
+
+        assert_eq!(
+            parse("\x04FF8000,000000hex\x0499,99x"),
+            vec![
+                span(
+                    "hex",
+                    Style {
+                        foreground: Some(Color::Rgb(0xff, 0x80, 0x00)),
+                        background: Some(Color::Rgb(0x00, 0x00, 0x00)),
+                        ..Default::default()
+                    }
+                ),
+                span("99,99x", Style::default()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_default_color() {
+        assert_eq!(
+            parse("\x0304,02a\x0399b"),
+            vec![
+                span(
+                    "a",
+                    Style {
+                        foreground: Some(Color::Palette(4)),
+                        background: Some(Color::Palette(2)),
+                        ..Default::default()
+                    }
+                ),
+                span(
+                    "b",
+                    Style {
+                        background: Some(Color::Palette(2)),
+                        ..Default::default()
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn strip_codes() {
+        assert_eq!(
+            strip("\x02bold\x02 \x0304,12color\x03 \x1funder\x0f \x04ff0000hex"),
+            "bold color under hex"
+        );
+    }
+
+    #[test]
+    fn encode_roundtrip() {
+        let inputs = [
+            "plain",
+            "\x02bold\x02 \x1ditalic\x1d \x1eline\x1e \x11mono\x11 \x16rev",
+            "\x0304red\x0312,01blue on black\x03 plain",
+            "\x0304,02a\x0399,02b",
+            "\x04FF8000orange\x0f\x02bold",
+            "\x0310123",
+            "\x04FF8000,00FF00a\x0304b\x03c\x0302,05d\x04000000e",
+            "\x0304,02a\x0399,,b",
+            "\x04FF8000,00FF00\x0304\x02\x02,12",
+            "\x0399,02\x04FF8000\x02\x02,00FF00",
+        ];
+
+        for input in inputs {
+            let spans = parse(input);
+            assert_eq!(parse(&encode(&spans)), spans, "input: {:?}", input);
+        }
+    }
+}
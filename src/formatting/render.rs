@@ -0,0 +1,457 @@
+use std::fmt;
+
+use time::{Date, OffsetDateTime};
+
+use crate::primitive::{LocalZone, Message, MessageType};
+
+/// Maximum amount of nicks listed in a netsplit message before they are summarized
+pub const MAX_NETSPLIT_NICKS: usize = 15;
+
+/// Separator used by the core to join users and servers in the content of netsplit messages
+const NETSPLIT_SEPARATOR: &str = "#:#";
+
+/// A [`Message`] with its sender and content parsed according to its [`MessageType`]
+///
+/// The [`Display`](fmt::Display) implementation renders the default english text
+/// as shown by the quassel client, mIRC format codes in the content are kept.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MessageEvent {
+    Plain {
+        nick: String,
+        text: String,
+    },
+    Notice {
+        nick: String,
+        text: String,
+    },
+    Action {
+        nick: String,
+        text: String,
+    },
+    Nick {
+        nick: String,
+        new_nick: String,
+    },
+    /// Mode change, without a nick for user modes set by the server
+    Mode {
+        nick: Option<String>,
+        modes: String,
+    },
+    Join {
+        nick: String,
+        host: String,
+        channel: String,
+    },
+    Part {
+        nick: String,
+        host: String,
+        channel: String,
+        reason: Option<String>,
+    },
+    Quit {
+        nick: String,
+        host: String,
+        reason: Option<String>,
+    },
+    Kick {
+        nick: String,
+        kicked: String,
+        channel: String,
+        reason: Option<String>,
+    },
+    Kill {
+        text: String,
+    },
+    Server {
+        text: String,
+    },
+    Info {
+        text: String,
+    },
+    Error {
+        text: String,
+    },
+    /// The day changed, the date is in the local zone as configured with [`LocalZone`]
+    DayChange {
+        date: Date,
+    },
+    Topic {
+        text: String,
+    },
+    NetsplitJoin(Netsplit),
+    NetsplitQuit(Netsplit),
+    Invite {
+        text: String,
+    },
+    Markerline,
+    /// Message of an unknown or combined type
+    Other {
+        msg_type: MessageType,
+        text: String,
+    },
+}
+
+/// The users and servers affected by a netsplit
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Netsplit {
+    /// Nicks of the users that joined or quit
+    pub nicks: Vec<String>,
+    /// The two servers that got split
+    pub servers: (String, String),
+}
+
+impl Netsplit {
+    /// Parse the content of a netsplit message in the format `users#:#servers`
+    pub fn parse(content: &str) -> Self {
+        let mut users: Vec<&str> = content.split(NETSPLIT_SEPARATOR).collect();
+        let servers = users.pop().unwrap_or_default();
+
+        let mut servers = servers.splitn(2, ' ');
+        let servers = (
+            servers.next().unwrap_or_default().to_string(),
+            servers.next().unwrap_or_default().to_string(),
+        );
+
+        Self {
+            nicks: users
+                .into_iter()
+                .map(|user| nick_from_mask(user).to_string())
+                .collect(),
+            servers,
+        }
+    }
+
+    fn nicks_text(&self) -> String {
+        if self.nicks.len() <= MAX_NETSPLIT_NICKS {
+            self.nicks.join(", ")
+        } else {
+            format!(
+                "{} ({} more)",
+                self.nicks[..MAX_NETSPLIT_NICKS].join(", "),
+                self.nicks.len() - MAX_NETSPLIT_NICKS
+            )
+        }
+    }
+}
+
+impl MessageEvent {
+    /// Parse a message, resolving dates in `zone` instead of the global [`LocalZone`]
+    pub fn from_message_in(msg: &Message, zone: LocalZone) -> Self {
+        let nick = nick_from_mask(&msg.sender).to_string();
+        let host = host_from_mask(&msg.sender).to_string();
+        let text = msg.content.clone();
+
+        match msg.msg_type {
+            MessageType::PLAIN => MessageEvent::Plain { nick, text },
+            MessageType::NOTICE => MessageEvent::Notice { nick, text },
+            MessageType::ACTION => MessageEvent::Action { nick, text },
+            MessageType::NICK => MessageEvent::Nick {
+                nick,
+                new_nick: text,
+            },
+            MessageType::MODE => MessageEvent::Mode {
+                nick: if nick.is_empty() { None } else { Some(nick) },
+                modes: text,
+            },
+            MessageType::JOIN => MessageEvent::Join {
+                nick,
+                host,
+                channel: msg.buffer.name.clone(),
+            },
+            MessageType::PART => MessageEvent::Part {
+                nick,
+                host,
+                channel: msg.buffer.name.clone(),
+                reason: non_empty(text),
+            },
+            MessageType::QUIT => MessageEvent::Quit {
+                nick,
+                host,
+                reason: non_empty(text),
+            },
+            MessageType::KICK => {
+                let mut content = text.splitn(2, ' ');
+
+                MessageEvent::Kick {
+                    nick,
+                    kicked: content.next().unwrap_or_default().to_string(),
+                    channel: msg.buffer.name.clone(),
+                    reason: content.next().map(str::to_string).and_then(non_empty),
+                }
+            }
+            MessageType::KILL => MessageEvent::Kill { text },
+            MessageType::SERVER => MessageEvent::Server { text },
+            MessageType::INFO => MessageEvent::Info { text },
+            MessageType::ERROR => MessageEvent::Error { text },
+            MessageType::DAY_CHANGE => MessageEvent::DayChange {
+                date: local_date(msg.time(), zone),
+            },
+            MessageType::TOPIC => MessageEvent::Topic { text },
+            MessageType::NETSPLIT_JOIN => MessageEvent::NetsplitJoin(Netsplit::parse(&text)),
+            MessageType::NETSPLIT_QUIT => MessageEvent::NetsplitQuit(Netsplit::parse(&text)),
+            MessageType::INVITE => MessageEvent::Invite { text },
+            MessageType::MARKERLINE => MessageEvent::Markerline,
+            msg_type => MessageEvent::Other { msg_type, text },
+        }
+    }
+
+    /// The text shown in the sender column of the quassel client
+    pub fn sender_text(&self) -> String {
+        match self {
+            MessageEvent::Plain { nick, .. } => format!("<{}>", nick),
+            MessageEvent::Notice { nick, .. } => format!("[{}]", nick),
+            MessageEvent::Action { .. } => s!("-*-"),
+            MessageEvent::Nick { .. } => s!("<->"),
+            MessageEvent::Mode { .. } => s!("***"),
+            MessageEvent::Join { .. } => s!("-->"),
+            MessageEvent::Part { .. } => s!("<--"),
+            MessageEvent::Quit { .. } => s!("<--"),
+            MessageEvent::Kick { .. } => s!("<-*"),
+            MessageEvent::Kill { .. } => s!("<--"),
+            MessageEvent::Server { .. } => s!("*"),
+            MessageEvent::Info { .. } => s!("*"),
+            MessageEvent::Error { .. } => s!("*"),
+            MessageEvent::DayChange { .. } => s!("-"),
+            MessageEvent::Topic { .. } => s!("*"),
+            MessageEvent::NetsplitJoin(_) => s!("=>"),
+            MessageEvent::NetsplitQuit(_) => s!("<="),
+            MessageEvent::Invite { .. } => s!("->"),
+            MessageEvent::Markerline => s!(""),
+            MessageEvent::Other { .. } => s!("*"),
+        }
+    }
+}
+
+impl From<&Message> for MessageEvent {
+    fn from(msg: &Message) -> Self {
+        Self::from_message_in(msg, LocalZone::current())
+    }
+}
+
+impl fmt::Display for MessageEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MessageEvent::Plain { text, .. } => write!(f, "{}", text),
+            MessageEvent::Notice { text, .. } => write!(f, "{}", text),
+            MessageEvent::Action { nick, text } => write!(f, "{} {}", nick, text),
+            MessageEvent::Nick { nick, new_nick } => {
+                if nick == new_nick {
+                    write!(f, "You are now known as {}", new_nick)
+                } else {
+                    write!(f, "{} is now known as {}", nick, new_nick)
+                }
+            }
+            MessageEvent::Mode { nick, modes } => match nick {
+                Some(nick) => write!(f, "Mode {} by {}", modes, nick),
+                None => write!(f, "User mode: {}", modes),
+            },
+            MessageEvent::Join {
+                nick,
+                host,
+                channel,
+            } => write!(f, "{} ({}) has joined {}", nick, host, channel),
+            MessageEvent::Part {
+                nick,
+                host,
+                channel,
+                reason,
+            } => {
+                write!(f, "{} ({}) has left {}", nick, host, channel)?;
+                write_reason(f, reason)
+            }
+            MessageEvent::Quit { nick, host, reason } => {
+                write!(f, "{} ({}) has quit", nick, host)?;
+                write_reason(f, reason)
+            }
+            MessageEvent::Kick {
+                nick,
+                kicked,
+                channel,
+                reason,
+            } => {
+                write!(f, "{} has kicked {} from {}", nick, kicked, channel)?;
+                write_reason(f, reason)
+            }
+            MessageEvent::Kill { text } => write!(f, "{}", text),
+            MessageEvent::Server { text } => write!(f, "{}", text),
+            MessageEvent::Info { text } => write!(f, "{}", text),
+            MessageEvent::Error { text } => write!(f, "{}", text),
+            MessageEvent::DayChange { date } => {
+                write!(f, "{{Day changed to {}}}", date.format("%A, %B %-d, %Y"))
+            }
+            MessageEvent::Topic { text } => write!(f, "{}", text),
+            MessageEvent::NetsplitJoin(netsplit) => write!(
+                f,
+                "Netsplit between {} and {} ended. Users joined: {}",
+                netsplit.servers.0,
+                netsplit.servers.1,
+                netsplit.nicks_text()
+            ),
+            MessageEvent::NetsplitQuit(netsplit) => write!(
+                f,
+                "Netsplit between {} and {}. Users quit: {}",
+                netsplit.servers.0,
+                netsplit.servers.1,
+                netsplit.nicks_text()
+            ),
+            MessageEvent::Invite { text } => write!(f, "{}", text),
+            MessageEvent::Markerline => Ok(()),
+            MessageEvent::Other { text, .. } => write!(f, "{}", text),
+        }
+    }
+}
+
+/// Render the message as the default english text shown by the quassel client
+pub fn render(msg: &Message) -> String {
+    MessageEvent::from(msg).to_string()
+}
+
+/// Same as [`render`], but resolves dates in `zone` instead of the global [`LocalZone`]
+pub fn render_in(msg: &Message, zone: LocalZone) -> String {
+    MessageEvent::from_message_in(msg, zone).to_string()
+}
+
+/// Get the nick of a `nick!user@host` mask
+fn nick_from_mask(mask: &str) -> &str {
+    mask.split('!').next().unwrap_or_default()
+}
+
+/// Get the `user@host` part of a `nick!user@host` mask
+fn host_from_mask(mask: &str) -> &str {
+    mask.split_once('!').map(|(_, host)| host).unwrap_or_default()
+}
+
+fn non_empty(text: String) -> Option<String> {
+    if text.is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+fn write_reason(f: &mut fmt::Formatter<'_>, reason: &Option<String>) -> fmt::Result {
+    match reason {
+        Some(reason) => write!(f, " ({})", reason),
+        None => Ok(()),
+    }
+}
+
+fn local_date(time: OffsetDateTime, zone: LocalZone) -> Date {
+    let utc = time.to_offset(time::UtcOffset::UTC);
+    let offset = zone.offset_at(time::PrimitiveDateTime::new(utc.date(), utc.time()));
+
+    time.to_offset(offset).date()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(msg_type: MessageType, sender: &str, content: &str) -> Message {
+        Message {
+            msg_type,
+            sender: sender.to_string(),
+            ..Message::test(1, content).at(1609848000)
+        }
+    }
+
+    #[test]
+    fn render_join_part_quit() {
+        assert_eq!(
+            render(&message(MessageType::JOIN, "nick!user@host", "#test")),
+            "nick (user@host) has joined #test"
+        );
+        assert_eq!(
+            render(&message(MessageType::PART, "nick!user@host", "")),
+            "nick (user@host) has left #test"
+        );
+        assert_eq!(
+            render(&message(
+                MessageType::QUIT,
+                "nick!user@host",
+                "Ping timeout"
+            )),
+            "nick (user@host) has quit (Ping timeout)"
+        );
+    }
+
+    #[test]
+    fn render_kick() {
+        let msg = message(MessageType::KICK, "op!user@host", "victim go away");
+
+        assert_eq!(
+            MessageEvent::from(&msg),
+            MessageEvent::Kick {
+                nick: s!("op"),
+                kicked: s!("victim"),
+                channel: s!("#test"),
+                reason: Some(s!("go away")),
+            }
+        );
+        assert_eq!(render(&msg), "op has kicked victim from #test (go away)");
+    }
+
+    #[test]
+    fn render_nick_mode() {
+        assert_eq!(
+            render(&message(MessageType::NICK, "old!user@host", "new")),
+            "old is now known as new"
+        );
+        assert_eq!(
+            render(&message(MessageType::NICK, "new!user@host", "new")),
+            "You are now known as new"
+        );
+        assert_eq!(
+            render(&message(MessageType::MODE, "op!user@host", "+o nick")),
+            "Mode +o nick by op"
+        );
+        assert_eq!(
+            render(&message(MessageType::MODE, "", "+i")),
+            "User mode: +i"
+        );
+    }
+
+    #[test]
+    fn render_netsplit() {
+        let msg = message(
+            MessageType::NETSPLIT_QUIT,
+            "",
+            "a!u@h#:#b!u@h#:#irc.one.net irc.two.net",
+        );
+
+        assert_eq!(
+            MessageEvent::from(&msg),
+            MessageEvent::NetsplitQuit(Netsplit {
+                nicks: vec![s!("a"), s!("b")],
+                servers: (s!("irc.one.net"), s!("irc.two.net")),
+            })
+        );
+        assert_eq!(
+            render(&msg),
+            "Netsplit between irc.one.net and irc.two.net. Users quit: a, b"
+        );
+
+        let users: Vec<String> = (0..17).map(|i| format!("n{}!u@h", i)).collect();
+        let netsplit = Netsplit::parse(&format!("{}#:#one two", users.join("#:#")));
+        assert_eq!(netsplit.nicks.len(), 17);
+        assert_eq!(
+            MessageEvent::NetsplitJoin(netsplit).to_string(),
+            "Netsplit between one and two ended. Users joined: \
+             n0, n1, n2, n3, n4, n5, n6, n7, n8, n9, n10, n11, n12, n13, n14 (2 more)"
+        );
+    }
+
+    #[test]
+    fn render_day_change() {
+        let msg = message(MessageType::DAY_CHANGE, "", "");
+
+        assert_eq!(
+            render_in(&msg, LocalZone::Utc),
+            "{Day changed to Tuesday, January 5, 2021}"
+        );
+        assert_eq!(
+            render_in(&msg, LocalZone::Fixed(time::UtcOffset::hours(-13))),
+            "{Day changed to Monday, January 4, 2021}"
+        );
+    }
+}
@@ -0,0 +1,249 @@
+//! Parsing, formatting and matching of IRC hostmasks in the form of `nick!user@host`
+
+use std::fmt;
+
+use regex::{Regex, RegexBuilder};
+
+/// A hostmask of an IRC user in the form of `nick!user@host`
+///
+/// Missing parts are represented by an empty string,
+/// a sender without `!` and `@` like a server name is parsed as just a nick.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Hostmask {
+    pub nick: String,
+    pub user: String,
+    pub host: String,
+}
+
+/// The styles in which a [`Hostmask::ban_mask`] can be built
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BanMaskType {
+    /// `nick!*@*`
+    Nick,
+    /// `*!user@host`
+    UserHost,
+    /// `*!*@host`
+    Host,
+    /// `*!user@*.domain`
+    UserDomain,
+    /// `*!*@*.domain`
+    Domain,
+    /// `nick!user@host`
+    Full,
+}
+
+impl Hostmask {
+    pub fn new(nick: &str, user: &str, host: &str) -> Self {
+        Self {
+            nick: nick.to_string(),
+            user: user.to_string(),
+            host: host.to_string(),
+        }
+    }
+
+    /// Parse a `nick!user@host` mask
+    pub fn parse(mask: &str) -> Self {
+        let (nick, rest) = match mask.split_once('!') {
+            Some((nick, rest)) => (nick, Some(rest)),
+            None => match mask.split_once('@') {
+                Some((nick, host)) => return Self::new(nick, "", host),
+                None => (mask, None),
+            },
+        };
+
+        match rest {
+            Some(rest) => match rest.rsplit_once('@') {
+                Some((user, host)) => Self::new(nick, user, host),
+                None => Self::new(nick, rest, ""),
+            },
+            None => Self::new(nick, "", ""),
+        }
+    }
+
+    /// Build a mask for banning this user in the given style
+    ///
+    /// A `~` in front of the user, which marks a missing ident response,
+    /// is replaced by a wildcard so the ban still matches if identd comes back.
+    pub fn ban_mask(&self, ban_type: BanMaskType) -> String {
+        let user = match self.user.strip_prefix('~') {
+            Some(user) => format!("*{}", user),
+            None => self.user.clone(),
+        };
+
+        match ban_type {
+            BanMaskType::Nick => format!("{}!*@*", self.nick),
+            BanMaskType::UserHost => format!("*!{}@{}", user, self.host),
+            BanMaskType::Host => format!("*!*@{}", self.host),
+            BanMaskType::UserDomain => format!("*!{}@{}", user, domain_mask(&self.host)),
+            BanMaskType::Domain => format!("*!*@{}", domain_mask(&self.host)),
+            BanMaskType::Full => format!("{}!{}@{}", self.nick, user, self.host),
+        }
+    }
+
+    /// Match the hostmask against a wildcard mask like `*!*@*.example.com`
+    ///
+    /// `*` matches any amount of characters, `?` matches exactly one character
+    /// and both can be escaped with a `\`. Matching is case insensitive.
+    pub fn matches(&self, mask: &str) -> bool {
+        wildcard_regex(mask)
+            .map(|regex| self.matches_regex(&regex))
+            .unwrap_or(false)
+    }
+
+    /// Match the formatted hostmask against a regular expression
+    pub fn matches_regex(&self, regex: &Regex) -> bool {
+        regex.is_match(&self.to_string())
+    }
+}
+
+impl From<&str> for Hostmask {
+    fn from(mask: &str) -> Self {
+        Self::parse(mask)
+    }
+}
+
+impl fmt::Display for Hostmask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.user.is_empty() && self.host.is_empty() {
+            write!(f, "{}", self.nick)
+        } else {
+            write!(f, "{}!{}@{}", self.nick, self.user, self.host)
+        }
+    }
+}
+
+/// Convert a wildcard pattern to an anchored regular expression
+///
+/// Follows the wildcard semantics of quassel: `*` and `?` are wildcards,
+/// `\*`, `\?` and `\\` match the literal character and everything else is matched literally.
+pub fn wildcard_to_regex(pattern: &str) -> String {
+    let mut res = String::from("^");
+    let mut chars = pattern.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.peek() {
+                Some(&next) if next == '\\' || next == '*' || next == '?' => {
+                    chars.next();
+                    res.push_str(&regex::escape(&next.to_string()));
+                }
+                _ => res.push_str(r"\\"),
+            },
+            '*' => res.push_str(".*"),
+            '?' => res.push('.'),
+            c => res.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+
+    res.push('$');
+    res
+}
+
+/// Build a case insensitive [`Regex`] from a wildcard pattern as described in [`wildcard_to_regex`]
+pub fn wildcard_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(&wildcard_to_regex(pattern))
+        .case_insensitive(true)
+        .build()
+}
+
+/// Match text against a wildcard pattern as described in [`wildcard_to_regex`]
+pub fn wildcard_match(pattern: &str, text: &str) -> bool {
+    wildcard_regex(pattern)
+        .map(|regex| regex.is_match(text))
+        .unwrap_or(false)
+}
+
+/// Replace the most specific part of a host with a wildcard
+///
+/// For IPv4 addresses the last octet is replaced, `1.2.3.4` becomes `1.2.3.*`,
+/// for hostnames the first label, `a.example.com` becomes `*.example.com`.
+/// Hosts with less than three parts and IPv6 addresses are kept as is.
+fn domain_mask(host: &str) -> String {
+    let parts: Vec<&str> = host.split('.').collect();
+
+    if parts.len() < 3 {
+        host.to_string()
+    } else if parts.iter().all(|part| part.parse::<u8>().is_ok()) {
+        format!("{}.*", parts[..parts.len() - 1].join("."))
+    } else {
+        format!("*.{}", parts[1..].join("."))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hostmask_parse() {
+        assert_eq!(
+            Hostmask::parse("nick!~user@host.example.com"),
+            Hostmask::new("nick", "~user", "host.example.com")
+        );
+        assert_eq!(
+            Hostmask::parse("irc.example.com"),
+            Hostmask::new("irc.example.com", "", "")
+        );
+        assert_eq!(
+            Hostmask::parse("nick!user@cloak@host"),
+            Hostmask::new("nick", "user@cloak", "host")
+        );
+        assert_eq!(
+            Hostmask::parse("nick@host"),
+            Hostmask::new("nick", "", "host")
+        );
+    }
+
+    #[test]
+    fn hostmask_format() {
+        for mask in &["nick!~user@host.example.com", "irc.example.com"] {
+            assert_eq!(Hostmask::parse(mask).to_string(), *mask);
+        }
+    }
+
+    #[test]
+    fn hostmask_ban_mask() {
+        let mask = Hostmask::parse("nick!~user@a.host.example.com");
+
+        assert_eq!(mask.ban_mask(BanMaskType::Nick), "nick!*@*");
+        assert_eq!(
+            mask.ban_mask(BanMaskType::UserHost),
+            "*!*user@a.host.example.com"
+        );
+        assert_eq!(mask.ban_mask(BanMaskType::Host), "*!*@a.host.example.com");
+        assert_eq!(
+            mask.ban_mask(BanMaskType::UserDomain),
+            "*!*user@*.host.example.com"
+        );
+        assert_eq!(mask.ban_mask(BanMaskType::Domain), "*!*@*.host.example.com");
+
+        let mask = Hostmask::parse("nick!user@192.168.1.20");
+        assert_eq!(mask.ban_mask(BanMaskType::Domain), "*!*@192.168.1.*");
+    }
+
+    #[test]
+    fn hostmask_matches() {
+        let mask = Hostmask::parse("Nick!~user@host.example.com");
+
+        assert!(mask.matches("*!*@*.example.com"));
+        assert!(mask.matches("nick!*"));
+        assert!(mask.matches("n?ck!?user@*"));
+        assert!(!mask.matches("*!*@example.com"));
+        assert!(mask.matches(&mask.ban_mask(BanMaskType::UserDomain)));
+
+        assert!(mask.matches_regex(&Regex::new(r"^Nick!.*\.com$").unwrap()));
+    }
+
+    #[test]
+    fn wildcard_escapes() {
+        assert_eq!(
+            wildcard_to_regex(r"a*b?c\*d\?e\\f\g"),
+            r"^a.*b.c\*d\?e\\f\\g$"
+        );
+
+        assert!(wildcard_match(r"what\?", "what?"));
+        assert!(!wildcard_match(r"what\?", "whats"));
+        assert!(wildcard_match("a.c", "A.C"));
+        assert!(!wildcard_match("a.c", "abc"));
+    }
+}
@@ -20,6 +20,20 @@ pub mod primitive;
 
 pub mod session;
 
+pub mod formatting;
+
+pub mod hostmask;
+
+pub mod expression;
+
+pub mod search;
+
+pub mod export;
+
+#[cfg(feature = "message-store")]
+#[cfg_attr(docsrs, doc(cfg(feature = "message-store")))]
+pub mod store;
+
 #[allow(dead_code)]
 /// Error Types
 pub mod error;
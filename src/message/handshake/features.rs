@@ -61,6 +61,10 @@ impl Feature {
         features.push("SenderPrefixes".to_string());
         #[cfg(feature = "authenticators")]
         features.push("Authenticators".to_string());
+        #[cfg(feature = "remote-disconnect")]
+        features.push("RemoteDisconnect".to_string());
+        #[cfg(feature = "synced-coreinfo")]
+        features.push("SyncedCoreInfo".to_string());
 
         return features;
     }
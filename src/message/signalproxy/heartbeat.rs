@@ -12,7 +12,7 @@ impl Serialize for HeartBeat {
         let mut res = VariantList::new();
 
         res.push(Variant::i32(MessageType::HeartBeat as i32));
-        res.push(Variant::DateTime(self.timestamp.clone()));
+        res.push(Variant::DateTime(self.timestamp));
 
         res.serialize()
     }
@@ -43,7 +43,7 @@ impl Serialize for HeartBeatReply {
         let mut res = VariantList::new();
 
         res.push(Variant::i32(MessageType::HeartBeatReply as i32));
-        res.push(Variant::DateTime(self.timestamp.clone()));
+        res.push(Variant::DateTime(self.timestamp));
 
         res.serialize()
     }
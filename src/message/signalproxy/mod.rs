@@ -27,16 +27,62 @@ use once_cell::sync::OnceCell;
 
 pub static SYNC_PROXY: OnceCell<SyncProxy> = OnceCell::new();
 
+/// Receivers of the SyncProxy used in tests, kept so sending never fails
+#[cfg(test)]
+static TEST_RECEIVERS: OnceCell<(
+    crossbeam_channel::Receiver<SyncMessage>,
+    crossbeam_channel::Receiver<RpcCall>,
+)> = OnceCell::new();
+
+/// Initialize the global SYNC_PROXY with unbounded channels for use in tests
+#[cfg(test)]
+pub(crate) fn init_test_sync_proxy() {
+    TEST_RECEIVERS.get_or_init(|| {
+        let (sync_tx, sync_rx) = crossbeam_channel::unbounded();
+        let (rpc_tx, rpc_rx) = crossbeam_channel::unbounded();
+        let (init_tx, init_rx) = crossbeam_channel::unbounded();
+
+        SYNC_PROXY
+            .set(SyncProxy {
+                sync_channel: sync_tx,
+                rpc_channel: rpc_tx,
+                init_channel: init_tx,
+                init_requests: init_rx,
+            })
+            .unwrap();
+
+        (sync_rx, rpc_rx)
+    });
+}
+
+/// Take the InitRequests sent through the test SYNC_PROXY so far
+///
+/// Tests run in parallel, so this can include requests sent by other tests.
+#[cfg(test)]
+pub(crate) fn test_init_requests() -> Vec<InitRequest> {
+    init_test_sync_proxy();
+    SYNC_PROXY
+        .get()
+        .unwrap()
+        .init_requests()
+        .try_iter()
+        .collect()
+}
+
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct SyncProxy {
     sync_channel: crossbeam_channel::Sender<SyncMessage>,
     rpc_channel: crossbeam_channel::Sender<RpcCall>,
+    init_channel: crossbeam_channel::Sender<InitRequest>,
+    init_requests: crossbeam_channel::Receiver<InitRequest>,
 }
 
-/// SyncProxy sends sync and rpc messages
+/// SyncProxy sends sync, rpc and init request messages
 impl SyncProxy {
     /// Initialize the global SYNC_PROXY object and return receiver ends for the SyncMessage and RpcCall channels
+    ///
+    /// The InitRequests objects send are available from [`SyncProxy::init_requests`].
     pub fn init(
         cap: usize,
     ) -> (
@@ -45,17 +91,28 @@ impl SyncProxy {
     ) {
         let (sync_tx, sync_rx) = crossbeam_channel::bounded(cap);
         let (rpc_tx, rpc_rx) = crossbeam_channel::bounded(cap);
+        let (init_tx, init_rx) = crossbeam_channel::unbounded();
 
         SYNC_PROXY
             .set(SyncProxy {
                 sync_channel: sync_tx,
                 rpc_channel: rpc_tx,
+                init_channel: init_tx,
+                init_requests: init_rx,
             })
             .unwrap();
 
         (sync_rx, rpc_rx)
     }
 
+    /// Receiver end for the InitRequests objects send, like the BufferViewManager
+    /// requesting the data of its configs
+    ///
+    /// The channel is unbounded so sending never blocks if the requests are not read.
+    pub fn init_requests(&self) -> crossbeam_channel::Receiver<InitRequest> {
+        self.init_requests.clone()
+    }
+
     /// Send a SyncMessage
     fn sync(
         &self,
@@ -76,7 +133,21 @@ impl SyncProxy {
     }
 
     /// Send an RpcCall
-    fn rpc(&self, _function: &str, _params: VariantList) {}
+    fn rpc(&self, rpc: RpcCall) {
+        debug!("submitting {:#?}", rpc);
+        self.rpc_channel.send(rpc).unwrap();
+    }
+
+    /// Request the InitData of an object
+    pub(crate) fn init_request(&self, class_name: Class, object_name: &str) {
+        let msg = InitRequest {
+            class_name: class_name.as_str().to_owned(),
+            object_name: object_name.to_owned(),
+        };
+
+        debug!("submitting {:#?}", msg);
+        self.init_channel.send(msg).unwrap();
+    }
 }
 
 /// A base Syncable Object
@@ -100,11 +171,19 @@ pub trait Syncable {
     }
 
     /// Send a RpcCall
-    fn send_rpc(&self, function: &str, params: VariantList) {
+    fn send_rpc(&self, rpc: RpcCall) {
+        crate::message::signalproxy::SYNC_PROXY
+            .get()
+            .unwrap()
+            .rpc(rpc);
+    }
+
+    /// Send an InitRequest for another object, like one announced by this object
+    fn send_init_request(&self, class_name: Class, object_name: &str) {
         crate::message::signalproxy::SYNC_PROXY
             .get()
             .unwrap()
-            .rpc(function, params);
+            .init_request(class_name, object_name);
     }
 
     fn init(&mut self, data: Self)
@@ -10,9 +10,22 @@ use crate::message::StatefulSyncableServer;
 
 use crate::message::Syncable;
 
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::message::objects::Network;
+use crate::primitive::BufferInfo;
 #[allow(unused_imports)]
 use crate::primitive::VariantMap;
 
+/// Maximum depth of aliases expanding to other aliases before expansion stops
+pub const MAX_ALIAS_DEPTH: usize = 16;
+
+/// Parameter ranges like `$1..3` or `$2..`
+static PARAM_RANGE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\$(\d+)\.\.(\d*)").unwrap());
+/// Separator of the commands in an expansion
+static COMMAND_SEPARATOR: Lazy<Regex> = Lazy::new(|| Regex::new("; ?").unwrap());
+
 /// AliasManager
 /// keeps a list of all registered aliases
 /// syncable
@@ -23,6 +36,91 @@ pub struct AliasManager {
 }
 
 impl AliasManager {
+    /// Get an alias by its name, alias names are case insensitive
+    pub fn alias(&self, name: &str) -> Option<&Alias> {
+        self.aliases
+            .iter()
+            .find(|alias| alias.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Process user input into the list of commands to send with `sendInput`
+    ///
+    /// Input that is not a command is turned into a `/SAY` command,
+    /// `//text` and `/ text` send the text literally.
+    /// Commands that match an alias are expanded, aliases expanding to other aliases
+    /// are followed up to [`MAX_ALIAS_DEPTH`] levels deep.
+    pub fn expand(&self, input: &str, buffer: &BufferInfo, network: &Network) -> Vec<String> {
+        let mut commands = Vec::new();
+
+        if input.is_empty() {
+            return commands;
+        }
+
+        self.process(input, buffer, network, 0, &mut commands);
+        commands
+    }
+
+    fn process(
+        &self,
+        input: &str,
+        buffer: &BufferInfo,
+        network: &Network,
+        depth: usize,
+        commands: &mut Vec<String>,
+    ) {
+        let first_space = input.find(' ');
+        let second_slash = input
+            .get(1..)
+            .and_then(|rest| rest.find('/'))
+            .map(|pos| pos + 1);
+
+        // A second slash before the first space means this is a path like /proc/cpuinfo
+        if !input.starts_with('/')
+            || first_space == Some(1)
+            || second_slash.is_some_and(|slash| first_space.is_none_or(|space| slash < space))
+        {
+            let text = if input.starts_with("//") {
+                &input[1..]
+            } else if let Some(text) = input.strip_prefix("/ ") {
+                text
+            } else {
+                input
+            };
+
+            commands.push(format!("/SAY {}", text));
+            return;
+        }
+
+        let (command, params) = input.split_once(' ').unwrap_or((input, ""));
+
+        match self.alias(&command[1..]) {
+            Some(_) if depth >= MAX_ALIAS_DEPTH => {
+                log::warn!("alias recursion limit reached, not expanding {}", command);
+                commands.push(input.to_string());
+            }
+            Some(alias) => {
+                let mut expanded =
+                    expand_alias(&alias.expansion, params, buffer, network).into_iter();
+
+                while let Some(command) = expanded.next() {
+                    // Everything following /wait is passed to the core as a single command
+                    if command.trim().to_lowercase().starts_with("/wait ") {
+                        commands.push(
+                            std::iter::once(command)
+                                .chain(expanded)
+                                .collect::<Vec<_>>()
+                                .join("; "),
+                        );
+                        break;
+                    }
+
+                    self.process(&command, buffer, network, depth + 1, commands);
+                }
+            }
+            None => commands.push(input.to_string()),
+        }
+    }
+
     pub fn add_alias(&mut self, alias: Alias) {
         #[cfg(feature = "server")]
         sync!("addAlias", [alias.to_network_map()]);
@@ -33,6 +131,75 @@ impl AliasManager {
     }
 }
 
+/// Expand the variables of an alias expansion with the given parameters
+///
+/// Returns one command for each of the `;` separated commands in the expansion.
+fn expand_alias(
+    expansion: &str,
+    params: &str,
+    buffer: &BufferInfo,
+    network: &Network,
+) -> Vec<String> {
+    let param_list: Vec<&str> = params.split(' ').collect();
+
+    COMMAND_SEPARATOR
+        .split(expansion)
+        .map(|command| {
+            let mut command = PARAM_RANGE
+                .replace_all(command, |caps: &regex::Captures| {
+                    let start: usize = caps[1].parse().unwrap_or(0);
+                    let end: usize = caps[2].parse().unwrap_or(param_list.len());
+
+                    if start == 0 || end < start {
+                        String::new()
+                    } else {
+                        param_list
+                            .iter()
+                            .skip(start - 1)
+                            .take(end - start + 1)
+                            .copied()
+                            .collect::<Vec<&str>>()
+                            .join(" ")
+                    }
+                })
+                .to_string();
+
+            // In reverse so $10 is not replaced by $1
+            for (i, param) in param_list.iter().enumerate().rev() {
+                let user = network.irc_user(param);
+                let lookup = |field: fn(&super::IrcUser) -> &String| {
+                    user.map(field)
+                        .filter(|value| !value.is_empty())
+                        .map_or("*", String::as_str)
+                };
+
+                // Only verified idents, unverified ones are prefixed with ~
+                let identd = match lookup(|user| &user.user) {
+                    ident if ident.starts_with('~') => "*",
+                    ident => ident,
+                };
+
+                let i = i + 1;
+                command = command
+                    .replace(&format!("${}:account", i), lookup(|user| &user.account))
+                    .replace(&format!("${}:hostname", i), lookup(|user| &user.host))
+                    .replace(&format!("${}:identd", i), identd)
+                    .replace(&format!("${}:ident", i), lookup(|user| &user.user))
+                    .replace(&format!("${}", i), param);
+            }
+
+            command
+                .replace("$0", params)
+                .replace("$channelname", &buffer.name)
+                .replace("$channel", &buffer.name)
+                .replace("$buffer", &buffer.name)
+                .replace("$currentnick", &network.my_nick)
+                .replace("$nick", &network.my_nick)
+                .replace("$network", &network.network_info.network_name)
+        })
+        .collect()
+}
+
 #[cfg(feature = "client")]
 impl StatefulSyncableClient for AliasManager {}
 
@@ -127,4 +294,122 @@ mod tests {
     fn aliasmanager_from_network() {
         assert_eq!(AliasManager::from_network_list(&mut get_dest()), get_src())
     }
+
+    fn get_network() -> Network {
+        let user = crate::message::objects::IrcUser {
+            user: s!("~audron"),
+            host: s!("audron.example.com"),
+            nick: s!("audron"),
+            real_name: s!(""),
+            account: s!("audron"),
+            away: false,
+            away_message: s!(""),
+            idle_time: crate::primitive::DateTime::null(),
+            login_time: crate::primitive::DateTime::null(),
+            server: s!(""),
+            irc_operator: s!(""),
+            last_away_message_time: crate::primitive::DateTime::null(),
+            whois_service_reply: s!(""),
+            suser_host: s!(""),
+            encrypted: false,
+            channels: vec![],
+            user_modes: s!(""),
+        };
+
+        let mut network = Network {
+            my_nick: s!("me"),
+            ..Default::default()
+        };
+        network.network_info.network_name = s!("libera");
+        network.irc_users.insert(s!("audron"), user);
+        network
+    }
+
+    fn get_buffer() -> BufferInfo {
+        BufferInfo {
+            id: 1,
+            network_id: 1,
+            buffer_type: crate::primitive::BufferType::Channel,
+            name: s!("#test"),
+        }
+    }
+
+    fn expand(aliases: &AliasManager, input: &str) -> Vec<String> {
+        aliases.expand(input, &get_buffer(), &get_network())
+    }
+
+    #[test]
+    fn aliasmanager_expand_input() {
+        let aliases = get_src();
+
+        assert_eq!(expand(&aliases, "hello"), vec![s!("/SAY hello")]);
+        assert_eq!(expand(&aliases, "//join"), vec![s!("/SAY /join")]);
+        assert_eq!(expand(&aliases, "/ /join"), vec![s!("/SAY /join")]);
+        assert_eq!(
+            expand(&aliases, "/proc/cpuinfo"),
+            vec![s!("/SAY /proc/cpuinfo")]
+        );
+        assert_eq!(expand(&aliases, "/part bye"), vec![s!("/part bye")]);
+        assert_eq!(expand(&aliases, "/J #quassel"), vec![s!("/join #quassel")]);
+        assert!(expand(&aliases, "").is_empty());
+    }
+
+    #[test]
+    fn aliasmanager_expand_variables() {
+        let aliases = AliasManager {
+            aliases: vec![
+                Alias {
+                    name: s!("info"),
+                    expansion: s!("/say $nick in $channel on $network: $2 $1..; /say $3.. $9"),
+                },
+                Alias {
+                    name: s!("ban"),
+                    expansion: s!(
+                        "/mode $channel +b *!$1:ident@$1:hostname; /say $1:account $1:identd $2:hostname"
+                    ),
+                },
+            ],
+        };
+
+        assert_eq!(
+            expand(&aliases, "/info a b c d"),
+            vec![
+                s!("/say me in #test on libera: b a b c d"),
+                s!("/say c d $9")
+            ]
+        );
+        assert_eq!(
+            expand(&aliases, "/ban Audron nobody"),
+            vec![
+                s!("/mode #test +b *!~audron@audron.example.com"),
+                s!("/say audron * *")
+            ]
+        );
+    }
+
+    #[test]
+    fn aliasmanager_expand_recursion() {
+        let aliases = AliasManager {
+            aliases: vec![
+                Alias {
+                    name: s!("a"),
+                    expansion: s!("/b $0; /wait 2; /msg x $0; /msg y"),
+                },
+                Alias {
+                    name: s!("b"),
+                    expansion: s!("/msg b $0"),
+                },
+                Alias {
+                    name: s!("loop"),
+                    expansion: s!("/loop"),
+                },
+            ],
+        };
+
+        assert_eq!(
+            expand(&aliases, "/a hi"),
+            vec![s!("/msg b hi"), s!("/wait 2; /msg x hi; /msg y")]
+        );
+        assert_eq!(expand(&aliases, "/loop"), vec![s!("/loop")]);
+    }
 }
@@ -48,114 +48,693 @@
 //     receiveBacklogAllFiltered(first: MsgId, last: MsgId, limit: Int, additional: Int, type: Int, flags: Int, messages: QVariantList)
 // }
 
-use crate::primitive::VariantList;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Condvar, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use libquassel_derive::{NetworkList, NetworkMap};
+
+use crate::message::{objects::BacklogStore, Class, Syncable};
+use crate::primitive::{BufferId, Message, MessageFlags, MessageType, MsgId, Variant, VariantList};
 
 /// Receive and Request Backlog
 /// All "request" functions are Client to Server and all "receive" functions are Server to Client
-#[derive(Clone, Debug, std::cmp::PartialEq, Default)]
-pub struct BacklogManager {}
+///
+/// Every request returns a [`PendingBacklog`] that resolves with the messages
+/// once the matching `receiveBacklog*` call arrived.
+///
+/// On the server requests are answered from the [`BacklogStore`] set with [`BacklogManager::with_store`].
+#[derive(Clone, Debug, Default, NetworkList, NetworkMap)]
+pub struct BacklogManager {
+    #[network(skip, default)]
+    pending: Vec<(BacklogRequest, PendingBacklog)>,
+    #[network(skip, default)]
+    store: Option<Arc<dyn BacklogStore>>,
+}
+
+/// The parameters of a backlog request as echoed back by the core in the response
+#[derive(Clone, Debug, PartialEq)]
+pub enum BacklogRequest {
+    Buffer {
+        buffer_id: BufferId,
+        first: MsgId,
+        last: MsgId,
+        limit: i32,
+        additional: i32,
+    },
+    BufferFiltered {
+        buffer_id: BufferId,
+        first: MsgId,
+        last: MsgId,
+        limit: i32,
+        additional: i32,
+        msg_type: MessageType,
+        flags: MessageFlags,
+    },
+    All {
+        first: MsgId,
+        last: MsgId,
+        limit: i32,
+        additional: i32,
+    },
+    AllFiltered {
+        first: MsgId,
+        last: MsgId,
+        limit: i32,
+        additional: i32,
+        msg_type: MessageType,
+        flags: MessageFlags,
+    },
+}
+
+impl BacklogRequest {
+    /// The name of the request slot
+    pub fn request_slot(&self) -> &'static str {
+        match self {
+            BacklogRequest::Buffer { .. } => "requestBacklog",
+            BacklogRequest::BufferFiltered { .. } => "requestBacklogFiltered",
+            BacklogRequest::All { .. } => "requestBacklogAll",
+            BacklogRequest::AllFiltered { .. } => "requestBacklogAllFiltered",
+        }
+    }
+
+    /// The name of the slot the core responds with
+    pub fn receive_slot(&self) -> &'static str {
+        match self {
+            BacklogRequest::Buffer { .. } => "receiveBacklog",
+            BacklogRequest::BufferFiltered { .. } => "receiveBacklogFiltered",
+            BacklogRequest::All { .. } => "receiveBacklogAll",
+            BacklogRequest::AllFiltered { .. } => "receiveBacklogAllFiltered",
+        }
+    }
+
+    /// The parameters of the request as sent in the SyncMessage
+    pub fn to_params(&self) -> VariantList {
+        match self.clone() {
+            BacklogRequest::Buffer {
+                buffer_id,
+                first,
+                last,
+                limit,
+                additional,
+            } => vec![
+                buffer_id.into(),
+                first.into(),
+                last.into(),
+                limit.into(),
+                additional.into(),
+            ],
+            BacklogRequest::BufferFiltered {
+                buffer_id,
+                first,
+                last,
+                limit,
+                additional,
+                msg_type,
+                flags,
+            } => vec![
+                buffer_id.into(),
+                first.into(),
+                last.into(),
+                limit.into(),
+                additional.into(),
+                msg_type.bits().into(),
+                (flags.bits() as i32).into(),
+            ],
+            BacklogRequest::All {
+                first,
+                last,
+                limit,
+                additional,
+            } => vec![first.into(), last.into(), limit.into(), additional.into()],
+            BacklogRequest::AllFiltered {
+                first,
+                last,
+                limit,
+                additional,
+                msg_type,
+                flags,
+            } => vec![
+                first.into(),
+                last.into(),
+                limit.into(),
+                additional.into(),
+                msg_type.bits().into(),
+                (flags.bits() as i32).into(),
+            ],
+        }
+    }
+
+    /// The parameters of the response to the request with the given messages
+    pub fn response_params(&self, messages: Vec<Message>) -> VariantList {
+        let mut params = self.to_params();
+        params.push(Variant::VariantList(
+            messages.into_iter().map(Variant::Message).collect(),
+        ));
+
+        params
+    }
+
+    /// Parse the request from the parameters of a request or receive slot
+    ///
+    /// Parameters that belong to the request are removed from `params`,
+    /// for a receive slot only the messages remain.
+    pub fn from_params(slot: &str, params: &mut VariantList) -> Option<Self> {
+        let mut param = || -> Option<Variant> {
+            if params.is_empty() {
+                None
+            } else {
+                Some(params.remove(0))
+            }
+        };
+
+        let filter = |msg_type: i32, flags: i32| {
+            (
+                MessageType::from_bits_truncate(msg_type),
+                MessageFlags::from_bits_truncate(flags as u8),
+            )
+        };
+
+        match slot {
+            "requestBacklog" | "receiveBacklog" => Some(BacklogRequest::Buffer {
+                buffer_id: param()?.try_into().ok()?,
+                first: param()?.try_into().ok()?,
+                last: param()?.try_into().ok()?,
+                limit: param()?.try_into().ok()?,
+                additional: param()?.try_into().ok()?,
+            }),
+            "requestBacklogFiltered" | "receiveBacklogFiltered" => {
+                let buffer_id = param()?.try_into().ok()?;
+                let first = param()?.try_into().ok()?;
+                let last = param()?.try_into().ok()?;
+                let limit = param()?.try_into().ok()?;
+                let additional = param()?.try_into().ok()?;
+                let (msg_type, flags) =
+                    filter(param()?.try_into().ok()?, param()?.try_into().ok()?);
+
+                Some(BacklogRequest::BufferFiltered {
+                    buffer_id,
+                    first,
+                    last,
+                    limit,
+                    additional,
+                    msg_type,
+                    flags,
+                })
+            }
+            "requestBacklogAll" | "receiveBacklogAll" => Some(BacklogRequest::All {
+                first: param()?.try_into().ok()?,
+                last: param()?.try_into().ok()?,
+                limit: param()?.try_into().ok()?,
+                additional: param()?.try_into().ok()?,
+            }),
+            "requestBacklogAllFiltered" | "receiveBacklogAllFiltered" => {
+                let first = param()?.try_into().ok()?;
+                let last = param()?.try_into().ok()?;
+                let limit = param()?.try_into().ok()?;
+                let additional = param()?.try_into().ok()?;
+                let (msg_type, flags) =
+                    filter(param()?.try_into().ok()?, param()?.try_into().ok()?);
+
+                Some(BacklogRequest::AllFiltered {
+                    first,
+                    last,
+                    limit,
+                    additional,
+                    msg_type,
+                    flags,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// The buffer of the request, `None` for requests of all buffers
+    pub fn buffer_id(&self) -> Option<BufferId> {
+        match self {
+            BacklogRequest::Buffer { buffer_id, .. }
+            | BacklogRequest::BufferFiltered { buffer_id, .. } => Some(*buffer_id),
+            BacklogRequest::All { .. } | BacklogRequest::AllFiltered { .. } => None,
+        }
+    }
+
+    /// The `first`, `last`, `limit` and `additional` parameters of the request
+    pub fn range(&self) -> (MsgId, MsgId, i32, i32) {
+        match *self {
+            BacklogRequest::Buffer {
+                first,
+                last,
+                limit,
+                additional,
+                ..
+            }
+            | BacklogRequest::BufferFiltered {
+                first,
+                last,
+                limit,
+                additional,
+                ..
+            }
+            | BacklogRequest::All {
+                first,
+                last,
+                limit,
+                additional,
+            }
+            | BacklogRequest::AllFiltered {
+                first,
+                last,
+                limit,
+                additional,
+                ..
+            } => (first, last, limit, additional),
+        }
+    }
+
+    /// The message type and flags of a filtered request
+    pub fn filter(&self) -> Option<(MessageType, MessageFlags)> {
+        match *self {
+            BacklogRequest::BufferFiltered {
+                msg_type, flags, ..
+            }
+            | BacklogRequest::AllFiltered {
+                msg_type, flags, ..
+            } => Some((msg_type, flags)),
+            BacklogRequest::Buffer { .. } | BacklogRequest::All { .. } => None,
+        }
+    }
+}
 
 impl BacklogManager {
+    /// Create a BacklogManager that answers backlog requests from `store`
+    pub fn with_store(store: Arc<dyn BacklogStore>) -> Self {
+        Self {
+            store: Some(store),
+            ..Default::default()
+        }
+    }
+
+    pub fn set_store(&mut self, store: Arc<dyn BacklogStore>) {
+        self.store = Some(store);
+    }
+
+    pub fn store(&self) -> Option<&Arc<dyn BacklogStore>> {
+        self.store.as_ref()
+    }
+
+    /// Send a backlog request and register it to be matched with the response
+    pub fn request(&mut self, request: BacklogRequest) -> PendingBacklog {
+        let pending = PendingBacklog::default();
+        self.pending.push((request.clone(), pending.clone()));
+
+        self.send_sync(request.request_slot(), request.to_params());
+
+        pending
+    }
+
     /// Loads backlog for `bufferId`, starting at message `first`, up to `last`
     /// (plus `additional` more messages after `last`) but at most `limit`
     /// messages total.
-    fn requestBacklog(
-        self: Self,
-        buffer_id: u32,
-        first: u32,
-        last: u32,
-        limit: u32,
-        additional: u32,
-    ) {
-        unimplemented!()
+    pub fn request_backlog(
+        &mut self,
+        buffer_id: BufferId,
+        first: MsgId,
+        last: MsgId,
+        limit: i32,
+        additional: i32,
+    ) -> PendingBacklog {
+        self.request(BacklogRequest::Buffer {
+            buffer_id,
+            first,
+            last,
+            limit,
+            additional,
+        })
     }
 
     /// Same as `requestBacklog`, but only messages of a certain message `type`
     /// with certain `flags` set.
-    fn requestBacklogFiltered(
-        self: Self,
-        buffer_id: u32,
-        first: u32,
-        last: u32,
-        limit: u32,
-        additional: u32,
-        msgtype: u32,
-        flags: u32,
-    ) {
-        unimplemented!()
+    #[allow(clippy::too_many_arguments)]
+    pub fn request_backlog_filtered(
+        &mut self,
+        buffer_id: BufferId,
+        first: MsgId,
+        last: MsgId,
+        limit: i32,
+        additional: i32,
+        msg_type: MessageType,
+        flags: MessageFlags,
+    ) -> PendingBacklog {
+        self.request(BacklogRequest::BufferFiltered {
+            buffer_id,
+            first,
+            last,
+            limit,
+            additional,
+            msg_type,
+            flags,
+        })
     }
 
     /// Same as `requestBacklog`, but applied to all buffers.
-    fn requestBacklogAll(self: Self, first: u32, last: u32, limit: u32, additional: u32) {
-        unimplemented!()
+    pub fn request_backlog_all(
+        &mut self,
+        first: MsgId,
+        last: MsgId,
+        limit: i32,
+        additional: i32,
+    ) -> PendingBacklog {
+        self.request(BacklogRequest::All {
+            first,
+            last,
+            limit,
+            additional,
+        })
     }
 
     /// Same as `requestBacklogFiltered`, but applied to all buffers.
-    fn requestBacklogAllFiltered(
-        self: Self,
-        first: u32,
-        last: u32,
-        limit: u32,
-        additional: u32,
-        msgtype: u32,
-        flags: u32,
-    ) {
-        unimplemented!()
-    }
-
-    /// The response to `requestBacklog`, with the messages encoded as Variants
-    /// in the `messages` parameter.
-    fn receiveBacklog(
-        self: Self,
-        buffer_id: u32,
-        first: u32,
-        last: u32,
-        limit: u32,
-        additional: u32,
-        messages: VariantList,
-    ) {
-        unimplemented!()
-    }
-
-    /// The response to `requestBacklogFiltered`, with the messages encoded as
-    /// Variants in the `messages` parameter.
-    fn receiveBacklogFiltered(
-        self: Self,
-        buffer_id: u32,
-        first: u32,
-        last: u32,
-        limit: u32,
-        additional: u32,
-        msgtype: u32,
-        flags: u32,
-        messages: VariantList,
-    ) {
-        unimplemented!()
-    }
-
-    /// Same as `receiveBacklog`, but applied to all buffers.
-    fn receiveBacklogAll(
-        self: Self,
-        first: u32,
-        last: u32,
-        limit: u32,
-        additional: u32,
-        messages: VariantList,
-    ) {
-        unimplemented!()
-    }
-
-    /// Same as `receiveBacklogFiltered`, but applied to all buffers.
-    fn receiveBacklogAllFiltered(
-        self: Self,
-        first: u32,
-        last: u32,
-        limit: u32,
-        additional: u32,
-        msgtype: u32,
-        flags: u32,
-        messages: VariantList,
-    ) {
-        unimplemented!()
+    pub fn request_backlog_all_filtered(
+        &mut self,
+        first: MsgId,
+        last: MsgId,
+        limit: i32,
+        additional: i32,
+        msg_type: MessageType,
+        flags: MessageFlags,
+    ) -> PendingBacklog {
+        self.request(BacklogRequest::AllFiltered {
+            first,
+            last,
+            limit,
+            additional,
+            msg_type,
+            flags,
+        })
+    }
+
+    /// The response to a request, resolves the oldest pending request with the same parameters.
+    ///
+    /// Returns false if no matching request was pending.
+    pub fn receive_backlog(&mut self, request: &BacklogRequest, messages: Vec<Message>) -> bool {
+        match self
+            .pending
+            .iter()
+            .position(|(pending, _)| pending == request)
+        {
+            Some(position) => {
+                let (_, pending) = self.pending.remove(position);
+                pending.resolve(messages);
+                true
+            }
+            None => {
+                log::warn!("received backlog without matching request: {:?}", request);
+                false
+            }
+        }
+    }
+
+    /// Number of requests still waiting for a response
+    pub fn pending_requests(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// The messages from the store that answer a request
+    ///
+    /// Without a store, or if fetching fails, no messages are returned
+    /// so the request on the client still resolves.
+    pub fn fetch(&self, request: &BacklogRequest) -> Vec<Message> {
+        match &self.store {
+            Some(store) => store.fetch_request(request).unwrap_or_else(|err| {
+                log::warn!("could not fetch backlog for {:?}: {}", request, err);
+                Vec::new()
+            }),
+            None => {
+                log::warn!("no backlog store to answer {:?}", request);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Answer a request with the messages from the store
+    ///
+    /// The `server` feature does not build yet, so this is untested apart from [`Self::fetch`].
+    #[cfg(feature = "server")]
+    pub fn answer(&self, request: &BacklogRequest) {
+        self.send_backlog(request, self.fetch(request));
+    }
+
+    /// Send the response to a request with the given messages
+    #[cfg(feature = "server")]
+    pub fn send_backlog(&self, request: &BacklogRequest, messages: Vec<Message>) {
+        self.send_sync(request.receive_slot(), request.response_params(messages));
+    }
+}
+
+#[cfg(feature = "client")]
+impl crate::message::StatefulSyncableClient for BacklogManager {
+    fn sync_custom(&mut self, mut msg: crate::message::SyncMessage)
+    where
+        Self: Sized,
+    {
+        match msg.slot_name.as_str() {
+            "receiveBacklog"
+            | "receiveBacklogFiltered"
+            | "receiveBacklogAll"
+            | "receiveBacklogAllFiltered" => {
+                match BacklogRequest::from_params(&msg.slot_name, &mut msg.params) {
+                    Some(request) => {
+                        let messages: VariantList = get_param!(msg);
+                        let messages = messages
+                            .into_iter()
+                            .filter_map(|message| match message {
+                                Variant::Message(message) => Some(message),
+                                _ => None,
+                            })
+                            .collect();
+
+                        self.receive_backlog(&request, messages);
+                    }
+                    None => log::warn!("could not parse {} parameters", msg.slot_name),
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+impl crate::message::StatefulSyncableServer for BacklogManager {
+    fn sync_custom(&mut self, mut msg: crate::message::SyncMessage)
+    where
+        Self: Sized,
+    {
+        match msg.slot_name.as_str() {
+            "requestBacklog"
+            | "requestBacklogFiltered"
+            | "requestBacklogAll"
+            | "requestBacklogAllFiltered" => {
+                match BacklogRequest::from_params(&msg.slot_name, &mut msg.params) {
+                    Some(request) => self.answer(&request),
+                    None => log::warn!("could not parse {} parameters", msg.slot_name),
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+impl Syncable for BacklogManager {
+    const CLASS: Class = Class::BacklogManager;
+}
+
+/// Messages of a backlog request that will be available once the core responded
+///
+/// Can be waited on blocking with [`PendingBacklog::wait`] or awaited as a [`Future`].
+#[derive(Clone, Debug, Default)]
+pub struct PendingBacklog {
+    state: Arc<(Mutex<PendingState>, Condvar)>,
+}
+
+#[derive(Debug, Default)]
+struct PendingState {
+    messages: Option<Vec<Message>>,
+    waker: Option<Waker>,
+}
+
+impl PendingBacklog {
+    fn resolve(&self, messages: Vec<Message>) {
+        let (state, condvar) = &*self.state;
+        let mut state = state.lock().unwrap();
+
+        state.messages = Some(messages);
+        if let Some(waker) = state.waker.take() {
+            waker.wake()
+        }
+
+        condvar.notify_all();
+    }
+
+    /// Take the messages if the response already arrived
+    pub fn try_take(&self) -> Option<Vec<Message>> {
+        self.state.0.lock().unwrap().messages.take()
+    }
+
+    /// Block until the response arrived and take the messages
+    pub fn wait(&self) -> Vec<Message> {
+        let (state, condvar) = &*self.state;
+        let mut state = condvar
+            .wait_while(state.lock().unwrap(), |state| state.messages.is_none())
+            .unwrap();
+
+        state.messages.take().unwrap()
+    }
+}
+
+impl Future for PendingBacklog {
+    type Output = Vec<Message>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.0.lock().unwrap();
+
+        match state.messages.take() {
+            Some(messages) => Poll::Ready(messages),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(msg_id: i32) -> Message {
+        Message {
+            flags: MessageFlags::BACKLOG,
+            ..Message::test(msg_id, "backlog")
+        }
+    }
+
+    #[test]
+    fn backlogrequest_params_roundtrip() {
+        let requests = vec![
+            BacklogRequest::Buffer {
+                buffer_id: BufferId(1),
+                first: MsgId(-1),
+                last: MsgId(100),
+                limit: 50,
+                additional: 0,
+            },
+            BacklogRequest::BufferFiltered {
+                buffer_id: BufferId(1),
+                first: MsgId(-1),
+                last: MsgId(-1),
+                limit: 50,
+                additional: 0,
+                msg_type: MessageType::PLAIN | MessageType::ACTION,
+                flags: MessageFlags::HIGHLIGHT,
+            },
+            BacklogRequest::All {
+                first: MsgId(10),
+                last: MsgId(-1),
+                limit: 500,
+                additional: 5,
+            },
+            BacklogRequest::AllFiltered {
+                first: MsgId(10),
+                last: MsgId(-1),
+                limit: 500,
+                additional: 5,
+                msg_type: MessageType::NOTICE,
+                flags: MessageFlags::NONE,
+            },
+        ];
+
+        for request in requests {
+            let mut params = request.to_params();
+            assert_eq!(
+                BacklogRequest::from_params(request.request_slot(), &mut params),
+                Some(request.clone())
+            );
+            assert!(params.is_empty());
+        }
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn backlogmanager_receive() {
+        use crate::message::{StatefulSyncableClient, SyncMessage};
+
+        crate::message::signalproxy::init_test_sync_proxy();
+
+        let mut manager = BacklogManager::default();
+        let first = manager.request_backlog(BufferId(1), MsgId(-1), MsgId(-1), 2, 0);
+        let second = manager.request_backlog(BufferId(2), MsgId(-1), MsgId(-1), 2, 0);
+        assert_eq!(manager.pending_requests(), 2);
+
+        let mut params = vec![
+            Variant::i32(2),
+            Variant::i64(-1),
+            Variant::i64(-1),
+            Variant::i32(2),
+            Variant::i32(0),
+        ];
+        params.push(Variant::VariantList(vec![
+            Variant::Message(message(2)),
+            Variant::Message(message(1)),
+        ]));
+
+        manager.sync(SyncMessage {
+            class_name: Class::BacklogManager,
+            object_name: s!(""),
+            slot_name: s!("receiveBacklog"),
+            params,
+        });
+
+        assert_eq!(first.try_take(), None);
+        assert_eq!(second.wait(), vec![message(2), message(1)]);
+        assert_eq!(manager.pending_requests(), 1);
+    }
+
+    #[test]
+    fn backlogmanager_answer() {
+        use crate::message::objects::MemoryBacklogStore;
+
+        let store = MemoryBacklogStore::with_messages((1..=5).map(message).collect());
+        let manager = BacklogManager::with_store(Arc::new(store));
+
+        let request = BacklogRequest::Buffer {
+            buffer_id: BufferId(1),
+            first: MsgId(2),
+            last: MsgId(5),
+            limit: 5,
+            additional: 1,
+        };
+        let mut params = request.to_params();
+        let parsed = BacklogRequest::from_params("requestBacklog", &mut params).unwrap();
+        assert_eq!(parsed, request);
+        assert!(params.is_empty());
+
+        let mut response = parsed.response_params(manager.fetch(&parsed));
+        assert_eq!(
+            BacklogRequest::from_params(request.receive_slot(), &mut response),
+            Some(request.clone())
+        );
+        assert_eq!(
+            response,
+            vec![Variant::VariantList(vec![
+                Variant::Message(message(4)),
+                Variant::Message(message(3)),
+                Variant::Message(message(2)),
+                Variant::Message(message(1)),
+            ])]
+        );
+
+        assert!(BacklogManager::default().fetch(&request).is_empty());
     }
 }
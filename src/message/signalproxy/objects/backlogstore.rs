@@ -0,0 +1,403 @@
+use std::{
+    fmt::Debug,
+    fs::{File, OpenOptions},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, RwLock},
+};
+
+use failure::Error;
+
+use crate::util::{read_records, record};
+use crate::{deserialize::*, serialize::*};
+
+use crate::message::objects::BacklogRequest;
+use crate::primitive::{BufferId, Message, MessageFlags, MessageType, MsgId};
+
+/// Storage of messages used by the server side [`BacklogManager`] to answer backlog requests
+///
+/// [`BacklogManager`]: crate::message::objects::BacklogManager
+pub trait BacklogStore: Debug + Send + Sync {
+    /// Store a message, assigning it the next free message id
+    ///
+    /// Returns the id the message was stored with.
+    fn store(&self, message: Message) -> Result<MsgId, Error>;
+
+    /// Fetch messages of a buffer, or of all buffers if `buffer_id` is `None`
+    ///
+    /// Returns at most `limit` messages with `first <= id < last`, newest first.
+    /// A `first` or `last` of -1 leaves that side of the range open
+    /// and a negative `limit` returns all messages in the range.
+    fn fetch(
+        &self,
+        buffer_id: Option<BufferId>,
+        first: MsgId,
+        last: MsgId,
+        limit: i32,
+        filter: Option<(MessageType, MessageFlags)>,
+    ) -> Result<Vec<Message>, Error>;
+
+    /// Fetch the messages answering a backlog request
+    ///
+    /// Like the quassel core, `additional` older messages are appended
+    /// if they continue seamlessly from the requested range. That is the case if `first` is open,
+    /// the oldest fetched message is `first` or the range had less than `limit` messages,
+    /// otherwise the messages between `first` and the oldest fetched one would be missing.
+    fn fetch_request(&self, request: &BacklogRequest) -> Result<Vec<Message>, Error> {
+        let (first, last, limit, additional) = request.range();
+        let (buffer_id, filter) = (request.buffer_id(), request.filter());
+
+        let mut messages = self.fetch(buffer_id, first, last, limit, filter)?;
+
+        if additional > 0 && limit != 0 {
+            let oldest = messages.last().map(|message| MsgId::from(message.msg_id));
+            let exhausted = usize::try_from(limit).map_or(true, |limit| messages.len() < limit);
+
+            if first == MsgId(-1) || exhausted || oldest == Some(first) {
+                let last = match oldest {
+                    _ if first != MsgId(-1) => first,
+                    Some(oldest) => oldest,
+                    None => last,
+                };
+
+                messages.extend(self.fetch(buffer_id, MsgId(-1), last, additional, filter)?);
+            }
+        }
+
+        Ok(messages)
+    }
+}
+
+/// Check if a message matches the message type and flags of a filtered request
+///
+/// The message type has to be one of the requested types and,
+/// unless no flags were requested, it has to carry one of the requested flags.
+pub fn filter_matches(message: &Message, filter: Option<(MessageType, MessageFlags)>) -> bool {
+    match filter {
+        Some((msg_type, flags)) => {
+            message.msg_type.intersects(msg_type)
+                && (flags.is_empty() || message.flags.intersects(flags))
+        }
+        None => true,
+    }
+}
+
+/// A [`BacklogStore`] keeping all messages in memory
+#[derive(Debug, Default)]
+pub struct MemoryBacklogStore {
+    messages: RwLock<Vec<Message>>,
+}
+
+impl MemoryBacklogStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a store from existing messages, keeping their ids
+    pub fn with_messages(mut messages: Vec<Message>) -> Self {
+        messages.sort_by_key(|message| message.msg_id);
+
+        Self {
+            messages: RwLock::new(messages),
+        }
+    }
+
+    /// Number of stored messages
+    pub fn len(&self) -> usize {
+        self.messages.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl BacklogStore for MemoryBacklogStore {
+    fn store(&self, mut message: Message) -> Result<MsgId, Error> {
+        let mut messages = self.messages.write().unwrap();
+
+        message.msg_id = messages.last().map_or(1, |last| last.msg_id + 1);
+        messages.push(message);
+
+        Ok(MsgId::from(messages.last().unwrap().msg_id))
+    }
+
+    fn fetch(
+        &self,
+        buffer_id: Option<BufferId>,
+        first: MsgId,
+        last: MsgId,
+        limit: i32,
+        filter: Option<(MessageType, MessageFlags)>,
+    ) -> Result<Vec<Message>, Error> {
+        let limit = usize::try_from(limit).unwrap_or(usize::MAX);
+
+        Ok(self
+            .messages
+            .read()
+            .unwrap()
+            .iter()
+            .rev()
+            .filter(|message| {
+                let id = MsgId::from(message.msg_id);
+
+                buffer_id.is_none_or(|buffer_id| message.buffer.id == buffer_id.0)
+                    && (first == MsgId(-1) || id >= first)
+                    && (last == MsgId(-1) || id < last)
+                    && filter_matches(message, filter)
+            })
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+}
+
+/// A [`BacklogStore`] persisting messages to a file
+///
+/// All messages are kept in memory and new messages are appended to the file,
+/// each as a big endian u32 length followed by the serialized message.
+/// Like the client side `MessageStore`, a truncated message at the end of the file is dropped.
+/// Intended for test cores, the file is only valid for the message features it was written with.
+#[derive(Debug)]
+pub struct FileBacklogStore {
+    path: PathBuf,
+    file: Mutex<File>,
+    messages: MemoryBacklogStore,
+}
+
+impl FileBacklogStore {
+    /// Open the store at `path`, loading existing messages or creating the file
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)?;
+
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+
+        let (records, len) = read_records(&bytes)?;
+        let messages = records
+            .into_iter()
+            .map(|record| Ok(Message::parse(record)?.1))
+            .collect::<Result<Vec<Message>, Error>>()?;
+
+        // Cut off a truncated record so new records are appended after the last valid one
+        if bytes.len() > len {
+            file.set_len(len as u64)?;
+        }
+
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+            messages: MemoryBacklogStore::with_messages(messages),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl BacklogStore for FileBacklogStore {
+    fn store(&self, message: Message) -> Result<MsgId, Error> {
+        let mut file = self.file.lock().unwrap();
+        let id = self.messages.store(message)?;
+
+        // The stored message carries the assigned id
+        let message = self
+            .messages
+            .fetch(None, id, MsgId(id.0 + 1), 1, None)?
+            .remove(0);
+        file.write_all(&record(message.serialize()?)?)?;
+        file.flush()?;
+
+        Ok(id)
+    }
+
+    fn fetch(
+        &self,
+        buffer_id: Option<BufferId>,
+        first: MsgId,
+        last: MsgId,
+        limit: i32,
+        filter: Option<(MessageType, MessageFlags)>,
+    ) -> Result<Vec<Message>, Error> {
+        self.messages.fetch(buffer_id, first, last, limit, filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(buffer_id: i32, msg_type: MessageType, content: &str) -> Message {
+        let mut message = Message {
+            msg_type,
+            ..Message::test(0, content)
+        };
+        message.buffer.id = buffer_id;
+
+        message
+    }
+
+    fn ids(messages: Vec<Message>) -> Vec<i64> {
+        messages
+            .into_iter()
+            .map(|message| MsgId::from(message.msg_id).0)
+            .collect()
+    }
+
+    fn get_store() -> MemoryBacklogStore {
+        let store = MemoryBacklogStore::new();
+
+        for i in 1..=10 {
+            let msg_type = if i % 3 == 0 {
+                MessageType::NOTICE
+            } else {
+                MessageType::PLAIN
+            };
+
+            assert_eq!(
+                store.store(message(i % 2, msg_type, "hi")).unwrap(),
+                MsgId(i as i64)
+            );
+        }
+
+        store
+    }
+
+    #[test]
+    fn memorybacklogstore_fetch() {
+        let store = get_store();
+        let all = MsgId(-1);
+
+        assert_eq!(
+            ids(store.fetch(Some(BufferId(1)), all, all, 3, None).unwrap()),
+            vec![9, 7, 5]
+        );
+        assert_eq!(
+            ids(store
+                .fetch(Some(BufferId(0)), MsgId(4), MsgId(8), -1, None)
+                .unwrap()),
+            vec![6, 4]
+        );
+        assert_eq!(
+            ids(store.fetch(None, MsgId(8), all, -1, None).unwrap()),
+            vec![10, 9, 8]
+        );
+        assert_eq!(
+            ids(store
+                .fetch(
+                    None,
+                    all,
+                    all,
+                    -1,
+                    Some((MessageType::NOTICE, MessageFlags::NONE))
+                )
+                .unwrap()),
+            vec![9, 6, 3]
+        );
+        assert!(store
+            .fetch(
+                None,
+                all,
+                all,
+                -1,
+                Some((MessageType::NOTICE, MessageFlags::HIGHLIGHT))
+            )
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn memorybacklogstore_additional() {
+        let store = get_store();
+
+        let request = BacklogRequest::Buffer {
+            buffer_id: BufferId(1),
+            first: MsgId(-1),
+            last: MsgId(-1),
+            limit: 2,
+            additional: 2,
+        };
+        assert_eq!(
+            ids(store.fetch_request(&request).unwrap()),
+            vec![9, 7, 5, 3]
+        );
+
+        let request = BacklogRequest::All {
+            first: MsgId(6),
+            last: MsgId(8),
+            limit: 5,
+            additional: 1,
+        };
+        assert_eq!(ids(store.fetch_request(&request).unwrap()), vec![7, 6, 5]);
+
+        // The oldest fetched message is first, the additional messages continue the range
+        let request = BacklogRequest::All {
+            first: MsgId(6),
+            last: MsgId(8),
+            limit: 2,
+            additional: 2,
+        };
+        assert_eq!(
+            ids(store.fetch_request(&request).unwrap()),
+            vec![7, 6, 5, 4]
+        );
+
+        // Only the newest messages of the range fit the limit, appending older
+        // messages would leave a hole between first and the oldest fetched message
+        let request = BacklogRequest::All {
+            first: MsgId(2),
+            last: MsgId(8),
+            limit: 2,
+            additional: 2,
+        };
+        assert_eq!(ids(store.fetch_request(&request).unwrap()), vec![7, 6]);
+    }
+
+    #[test]
+    fn filebacklogstore_persist() {
+        let path =
+            std::env::temp_dir().join(format!("libquassel-backlog-{}.bin", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        {
+            let store = FileBacklogStore::open(&path).unwrap();
+            store
+                .store(message(1, MessageType::PLAIN, "first"))
+                .unwrap();
+            store
+                .store(message(2, MessageType::ACTION, "second"))
+                .unwrap();
+        }
+
+        // Simulate a crash while writing a message
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[0, 0, 1, 0, 42]).unwrap();
+
+        let store = FileBacklogStore::open(&path).unwrap();
+        assert_eq!(
+            store
+                .store(message(1, MessageType::PLAIN, "third"))
+                .unwrap(),
+            MsgId(3)
+        );
+
+        let messages = store
+            .fetch(Some(BufferId(1)), MsgId(-1), MsgId(-1), -1, None)
+            .unwrap();
+        assert_eq!(
+            messages
+                .iter()
+                .map(|message| message.content.as_str())
+                .collect::<Vec<_>>(),
+            vec!["third", "first"]
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
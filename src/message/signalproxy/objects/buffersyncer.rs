@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use crate::{
     message::{Syncable, Class},
-    primitive::MessageType,
+    primitive::{Message, MessageType},
 };
 
 use libquassel_derive::{sync, NetworkList, NetworkMap};
@@ -125,6 +125,32 @@ impl BufferSyncer {
         sync!("setHighlightCount", [id, msg_id]);
     }
 
+    /// Merge a received message into the activity and highlight count of its buffer
+    ///
+    /// Messages sent by ourselves and already seen backlog do not count as activity.
+    pub fn merge_message_activity(&mut self, msg: &Message) {
+        let id = msg.buffer.id;
+
+        if msg.is_self() {
+            return;
+        }
+
+        // MsgId is only an i64 with the long-message-id feature
+        #[allow(clippy::unnecessary_cast)]
+        let msg_id = msg.msg_id as i64;
+        let last_seen = self.last_seen_msg.get(&id).copied().unwrap_or_default();
+
+        if msg.is_backlog() && msg_id <= last_seen {
+            return;
+        }
+
+        *self.activities.entry(id).or_insert(MessageType::NONE) |= msg.msg_type;
+
+        if msg.is_highlight() {
+            *self.highlight_counts.entry(id).or_default() += 1;
+        }
+    }
+
     pub fn set_marker_line(&mut self, id: i32, msg_id: i64) {
         *self.marker_line.entry(id).or_default() = msg_id;
 
@@ -286,6 +312,50 @@ mod tests {
     //     assert_eq!(get_runtime().to_network(), get_network())
     // }
 
+    #[test]
+    #[cfg(feature = "all-quassel-features")]
+    fn buffersyncer_merge_message_activity() {
+        use crate::primitive::{BufferInfo, BufferType, MessageFlags};
+
+        let mut buffer_syncer = get_runtime();
+        let mut msg = Message {
+            msg_id: 2186,
+            timestamp: 0,
+            msg_type: MessageType::PLAIN,
+            flags: MessageFlags::HIGHLIGHT,
+            buffer: BufferInfo {
+                id: 1,
+                network_id: 1,
+                buffer_type: BufferType::Channel,
+                name: s!("#test"),
+            },
+            sender: s!("test"),
+            sender_prefixes: s!(""),
+            real_name: s!(""),
+            avatar_url: s!(""),
+            content: s!("test"),
+        };
+
+        buffer_syncer.merge_message_activity(&msg);
+        assert_eq!(buffer_syncer.activities[&1], MessageType::PLAIN);
+        assert_eq!(buffer_syncer.highlight_counts[&1], 1);
+
+        // Already seen backlog and our own messages are ignored
+        msg.msg_type = MessageType::JOIN;
+        msg.msg_id = 2180;
+        msg.flags = MessageFlags::BACKLOG;
+        buffer_syncer.merge_message_activity(&msg);
+        msg.msg_id = 2187;
+        msg.flags = MessageFlags::SELF;
+        buffer_syncer.merge_message_activity(&msg);
+        assert_eq!(buffer_syncer.activities[&1], MessageType::PLAIN);
+
+        msg.flags = MessageFlags::BACKLOG;
+        buffer_syncer.merge_message_activity(&msg);
+        assert_eq!(buffer_syncer.activities[&1], MessageType::PLAIN | MessageType::JOIN);
+        assert_eq!(buffer_syncer.highlight_counts[&1], 1);
+    }
+
     #[test]
     fn buffersyncer_from_network() {
         assert_eq!(
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use libquassel_derive::sync;
 use libquassel_derive::{NetworkList, NetworkMap};
 
@@ -7,7 +9,9 @@ use crate::message::StatefulSyncableClient;
 use crate::message::StatefulSyncableServer;
 use crate::message::{Class, Syncable};
 
-use crate::primitive::VariantList;
+use crate::primitive::{BufferInfo, BufferType, MessageType, VariantList};
+
+use super::{BufferSyncer, Network};
 
 #[derive(Debug, Default, Clone, PartialEq, NetworkList, NetworkMap)]
 pub struct BufferViewConfig {
@@ -55,6 +59,7 @@ impl BufferViewConfig {
 
     pub fn add_buffer(&mut self, id: i32, pos: usize) {
         if !self.buffers.contains(&id) {
+            let pos = pos.min(self.buffers.len());
             self.buffers.insert(pos, id)
         }
 
@@ -79,8 +84,13 @@ impl BufferViewConfig {
     }
 
     pub fn move_buffer(&mut self, id: i32, pos: usize) {
-        let old_pos = self.buffers.iter().position(|&x| x == id).unwrap();
+        let old_pos = match self.buffers.iter().position(|&x| x == id) {
+            Some(old_pos) => old_pos,
+            None => return,
+        };
         self.buffers.remove(old_pos);
+
+        let pos = pos.min(self.buffers.len());
         self.buffers.insert(pos, id);
 
         #[cfg(feature = "server")]
@@ -132,6 +142,129 @@ impl BufferViewConfig {
         #[cfg(feature = "server")]
         sync!("removeBufferPermanently", [id]);
     }
+
+    /// Get the buffers shown by this view, in the order they are shown
+    ///
+    /// Takes all known buffers like the ones from the `SessionInit`, the activities
+    /// of the `BufferSyncer` and the networks for their connection states.
+    /// Buffers the view does not know about yet are shown if
+    /// `add_new_buffers_automatically` is set, temporarily removed buffers
+    /// are shown again once they have new messages.
+    pub fn visible_buffers<'a>(
+        &self,
+        buffers: &'a [BufferInfo],
+        buffer_syncer: &BufferSyncer,
+        networks: &HashMap<i32, Network>,
+    ) -> Vec<&'a BufferInfo> {
+        let mut visible: Vec<&BufferInfo> = self
+            .buffers
+            .iter()
+            .filter_map(|id| buffers.iter().find(|buffer| buffer.id == *id))
+            .collect();
+
+        if self.add_new_buffers_automatically {
+            visible.extend(buffers.iter().filter(|buffer| {
+                !self.buffers.contains(&buffer.id)
+                    && !self.removed_buffers.contains(&buffer.id)
+                    && !self.temporarily_removed_buffers.contains(&buffer.id)
+            }));
+        }
+
+        visible.extend(
+            self.temporarily_removed_buffers
+                .iter()
+                .filter(|id| activity_level(**id, buffer_syncer) >= 2)
+                .filter_map(|id| buffers.iter().find(|buffer| buffer.id == *id)),
+        );
+
+        visible.retain(|buffer| self.accepts_buffer(buffer, buffer_syncer, networks));
+
+        if self.sort_alphabetically {
+            visible.sort_by_cached_key(|buffer| {
+                (
+                    buffer.network_id,
+                    buffer.buffer_type != BufferType::Status,
+                    buffer.name.to_lowercase(),
+                )
+            });
+        }
+
+        visible
+    }
+
+    fn accepts_buffer(
+        &self,
+        buffer: &BufferInfo,
+        buffer_syncer: &BufferSyncer,
+        networks: &HashMap<i32, Network>,
+    ) -> bool {
+        if self.network_id != 0 && self.network_id != buffer.network_id {
+            return false;
+        }
+
+        if self.allowed_buffer_types & buffer.buffer_type as i32 == 0 {
+            return false;
+        }
+
+        let network = networks.get(&buffer.network_id);
+        let connected = network.is_some_and(|network| network.is_connected);
+
+        if self.hide_inactive_networks && !connected {
+            return false;
+        }
+
+        if self.hide_inactive_buffers && !is_buffer_active(buffer, network) {
+            return false;
+        }
+
+        activity_level(buffer.id, buffer_syncer) >= self.minimum_activity
+    }
+}
+
+/// Status buffers are active while connected, channels while joined and
+/// queries while the user is known
+fn is_buffer_active(buffer: &BufferInfo, network: Option<&Network>) -> bool {
+    let network = match network {
+        Some(network) if network.is_connected => network,
+        _ => return buffer.buffer_type == BufferType::Group,
+    };
+
+    match buffer.buffer_type {
+        BufferType::Status | BufferType::Group => true,
+        BufferType::Channel => network
+            .irc_channels
+            .keys()
+            .any(|channel| channel.eq_ignore_ascii_case(&buffer.name)),
+        BufferType::Query => network.irc_user(&buffer.name).is_some(),
+    }
+}
+
+/// The activity level of a buffer as used by `minimum_activity`
+///
+/// Quassel uses 0 for no activity, 1 for other activity like joins,
+/// 2 for new messages and 4 for highlights.
+fn activity_level(id: i32, buffer_syncer: &BufferSyncer) -> i32 {
+    let activity = buffer_syncer
+        .activities
+        .get(&id)
+        .copied()
+        .unwrap_or(MessageType::NONE);
+
+    if buffer_syncer
+        .highlight_counts
+        .get(&id)
+        .copied()
+        .unwrap_or(0)
+        > 0
+    {
+        4
+    } else if activity.intersects(MessageType::PLAIN | MessageType::NOTICE | MessageType::ACTION) {
+        2
+    } else if !activity.is_empty() {
+        1
+    } else {
+        0
+    }
 }
 
 #[cfg(feature = "client")]
@@ -154,9 +287,43 @@ impl StatefulSyncableClient for BufferViewConfig {
             "removeBufferPermanently" => {
                 self.remove_buffer_permanently(msg.params.remove(0).try_into().unwrap())
             }
+            "setAddNewBuffersAutomatically" => {
+                self.add_new_buffers_automatically = msg.params.remove(0).try_into().unwrap()
+            }
+            "setAllowedBufferTypes" => {
+                self.allowed_buffer_types = msg.params.remove(0).try_into().unwrap()
+            }
+            "setBufferViewName" => self.buffer_view_name = msg.params.remove(0).into(),
+            "setDisableDecoration" => {
+                self.disable_decoration = msg.params.remove(0).try_into().unwrap()
+            }
+            "setHideInactiveBuffers" => {
+                self.hide_inactive_buffers = msg.params.remove(0).try_into().unwrap()
+            }
+            "setHideInactiveNetworks" => {
+                self.hide_inactive_networks = msg.params.remove(0).try_into().unwrap()
+            }
+            "setMinimumActivity" => {
+                self.minimum_activity = msg.params.remove(0).try_into().unwrap()
+            }
+            "setNetworkId" => self.network_id = msg.params.remove(0).try_into().unwrap(),
+            "setShowSearch" => self.show_search = msg.params.remove(0).try_into().unwrap(),
+            "setSortAlphabetically" => {
+                self.sort_alphabetically = msg.params.remove(0).try_into().unwrap()
+            }
             _ => (),
         }
     }
+
+    /// The buffer view id is not part of the map so keep it
+    fn update(&mut self, mut param: <Self as crate::message::NetworkMap>::Item)
+    where
+        Self: Sized,
+    {
+        let buffer_view_id = self.buffer_view_id;
+        *self = <Self as crate::message::NetworkMap>::from_network_map(&mut param);
+        self.buffer_view_id = buffer_view_id;
+    }
 }
 
 #[cfg(feature = "server")]
@@ -333,5 +500,145 @@ mod tests {
             },
             buffer_view_config
         );
+
+        // Move unknown buffer, no change
+        let mut buffer_view_config = bufferviewconfig_sample();
+        buffer_view_config.move_buffer(10, 1);
+        assert_eq!(bufferviewconfig_sample(), buffer_view_config);
+
+        // Move buffer past the end
+        let mut buffer_view_config = bufferviewconfig_sample();
+        buffer_view_config.move_buffer(1, 10);
+        assert_eq!(
+            BufferViewConfig {
+                buffers: vec![2, 3, 1],
+                removed_buffers: vec![4, 5],
+                temporarily_removed_buffers: vec![6, 7],
+                ..Default::default()
+            },
+            buffer_view_config
+        );
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn bufferviewconfig_sync() {
+        use crate::message::SyncMessage;
+        use crate::primitive::Variant;
+
+        let sync = |slot_name: &str, params: VariantList| SyncMessage {
+            class_name: Class::BufferViewConfig,
+            object_name: s!("1"),
+            slot_name: slot_name.to_owned(),
+            params,
+        };
+
+        let mut buffer_view_config = BufferViewConfig {
+            buffer_view_id: 1,
+            ..bufferviewconfig_sample()
+        };
+
+        buffer_view_config.sync(sync(
+            "setBufferViewName",
+            vec![Variant::String(s!("All Chats"))],
+        ));
+        buffer_view_config.sync(sync("setHideInactiveBuffers", vec![Variant::bool(true)]));
+        buffer_view_config.sync(sync("addBuffer", vec![Variant::i32(8), Variant::i32(20)]));
+        buffer_view_config.sync(sync("moveBuffer", vec![Variant::i32(8), Variant::i32(0)]));
+        buffer_view_config.sync(sync("removeBuffer", vec![Variant::i32(2)]));
+        buffer_view_config.sync(sync("removeBufferPermanently", vec![Variant::i32(3)]));
+
+        assert_eq!(
+            BufferViewConfig {
+                buffers: vec![8, 1],
+                removed_buffers: vec![4, 5, 3],
+                temporarily_removed_buffers: vec![6, 7, 2],
+                buffer_view_id: 1,
+                buffer_view_name: s!("All Chats"),
+                hide_inactive_buffers: true,
+                ..Default::default()
+            },
+            buffer_view_config
+        );
+    }
+
+    #[test]
+    fn bufferviewconfig_visible_buffers() {
+        let buffer = |id, network_id, buffer_type, name: &str| BufferInfo {
+            id,
+            network_id,
+            buffer_type,
+            name: name.to_owned(),
+        };
+
+        let buffers = vec![
+            buffer(1, 1, BufferType::Status, ""),
+            buffer(2, 1, BufferType::Channel, "#quassel"),
+            buffer(3, 1, BufferType::Query, "Kitty"),
+            buffer(4, 1, BufferType::Channel, "#Dev"),
+            buffer(5, 2, BufferType::Channel, "#other"),
+            buffer(6, 1, BufferType::Channel, "#removed"),
+            buffer(7, 1, BufferType::Channel, "#hidden"),
+            buffer(8, 1, BufferType::Channel, "#quiet"),
+        ];
+
+        let mut networks = HashMap::new();
+        networks.insert(
+            1,
+            Network {
+                is_connected: true,
+                irc_channels: vec![(s!("#quassel"), Default::default())]
+                    .into_iter()
+                    .collect(),
+                ..Default::default()
+            },
+        );
+        networks.insert(2, Network::default());
+
+        let mut buffer_syncer = BufferSyncer::default();
+        buffer_syncer.activities.insert(3, MessageType::JOIN);
+        buffer_syncer.activities.insert(4, MessageType::PLAIN);
+        buffer_syncer.highlight_counts.insert(2, 1);
+        buffer_syncer.activities.insert(7, MessageType::NOTICE);
+        buffer_syncer.activities.insert(8, MessageType::JOIN);
+
+        let ids = |config: &BufferViewConfig| -> Vec<i32> {
+            config
+                .visible_buffers(&buffers, &buffer_syncer, &networks)
+                .into_iter()
+                .map(|buffer| buffer.id)
+                .collect()
+        };
+
+        let mut config = BufferViewConfig {
+            buffers: vec![4, 2, 1],
+            removed_buffers: vec![6],
+            temporarily_removed_buffers: vec![7, 8],
+            allowed_buffer_types: 0x0f,
+            ..Default::default()
+        };
+        // Temporarily removed buffers come back with new messages
+        assert_eq!(ids(&config), vec![4, 2, 1, 7]);
+
+        config.add_new_buffers_automatically = true;
+        assert_eq!(ids(&config), vec![4, 2, 1, 3, 5, 7]);
+
+        config.sort_alphabetically = true;
+        assert_eq!(ids(&config), vec![1, 4, 7, 2, 3, 5]);
+
+        config.hide_inactive_networks = true;
+        assert_eq!(ids(&config), vec![1, 4, 7, 2, 3]);
+
+        config.hide_inactive_buffers = true;
+        assert_eq!(ids(&config), vec![1, 2]);
+
+        config.hide_inactive_buffers = false;
+        config.minimum_activity = 2;
+        assert_eq!(ids(&config), vec![4, 7, 2]);
+
+        config.minimum_activity = 0;
+        config.allowed_buffer_types = BufferType::Channel as i32;
+        config.network_id = 1;
+        assert_eq!(ids(&config), vec![4, 7, 2]);
     }
 }
@@ -18,9 +18,14 @@ pub struct BufferViewManager {
     pub buffer_view_configs: HashMap<i32, Option<BufferViewConfig>>,
 }
 
-// TODO initialize the BufferViewConfigs from somewhere
-// TODO add buffer view configs, where does the data come from?
 impl BufferViewManager {
+    /// Get a BufferViewConfig by its id if it has been initialized already
+    pub fn buffer_view_config(&mut self, id: i32) -> Option<&mut BufferViewConfig> {
+        self.buffer_view_configs
+            .get_mut(&id)
+            .and_then(Option::as_mut)
+    }
+
     pub fn request_create_buffer_view(&self, properties: BufferViewConfig) {
         sync!("requestCreateBufferView", [properties.to_network_map()])
     }
@@ -46,10 +51,12 @@ impl BufferViewManager {
         )
     }
 
+    /// Add a placeholder for the BufferViewConfig and request its InitData
     #[cfg(feature = "client")]
-    #[allow(unused_variables)]
     pub fn add_buffer_view_config(&mut self, id: i32) {
-        // TODO init!("BufferViewConfig", id);
+        self.buffer_view_configs.entry(id).or_insert(None);
+
+        self.send_init_request(Class::BufferViewConfig, &id.to_string());
     }
 
     #[cfg(feature = "server")]
@@ -128,6 +135,23 @@ impl StatefulSyncableServer for BufferViewManager {
 
 impl Syncable for BufferViewManager {
     const CLASS: Class = Class::BufferViewManager;
+
+    /// Keeps already initialized BufferViewConfigs and requests the
+    /// InitData for all the others, configs missing from `data` are dropped
+    #[allow(unused_variables)]
+    fn init(&mut self, data: Self) {
+        self.buffer_view_configs
+            .retain(|id, _| data.buffer_view_configs.contains_key(id));
+
+        for (id, config) in data.buffer_view_configs {
+            let stored = self.buffer_view_configs.entry(id).or_insert(config);
+
+            #[cfg(feature = "client")]
+            if stored.is_none() {
+                self.send_init_request(Class::BufferViewConfig, &id.to_string());
+            }
+        }
+    }
 }
 
 impl super::NetworkList for BufferViewManager {
@@ -155,7 +179,6 @@ impl super::NetworkList for BufferViewManager {
             _ => panic!("network::list::from: wrong variant type"),
         };
 
-        // TODO Somehow do the initrequests for all the IDs we get here
         Self {
             buffer_view_configs: ids
                 .into_iter()
@@ -184,10 +207,18 @@ impl super::NetworkMap for BufferViewManager {
         return res;
     }
 
-    fn from_network_map(_input: &mut Self::Item) -> Self {
-        // TODO Somehow do the initrequests for all the IDs we get here
+    fn from_network_map(input: &mut Self::Item) -> Self {
+        let ids: VariantList = input
+            .remove("BufferViewIds")
+            .or_else(|| input.remove("bufferViewIds"))
+            .map(|ids| ids.try_into().unwrap())
+            .unwrap_or_default();
+
         Self {
-            buffer_view_configs: HashMap::new(),
+            buffer_view_configs: ids
+                .into_iter()
+                .map(|id| (i32::try_from(id).unwrap(), Option::None))
+                .collect(),
         }
     }
 }
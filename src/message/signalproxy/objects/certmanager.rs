@@ -1,11 +1,16 @@
 use libquassel_derive::{NetworkList, NetworkMap};
 
 use crate::message::{Syncable, Class};
+use crate::primitive::IdentityId;
 #[allow(unused_imports)]
 use crate::primitive::Variant;
 
+/// The SSL key and certificate of an identity, there is one CertManager per identity
 #[derive(Debug, Clone, PartialEq, NetworkList, NetworkMap, Default)]
 pub struct CertManager {
+    /// Not part of the InitData, set from the object name
+    #[network(skip, default)]
+    pub identity_id: IdentityId,
     #[network(rename = "sslKey", variant = "ByteArray")]
     pub ssl_key: String,
     #[network(rename = "sslCert", variant = "ByteArray")]
@@ -47,4 +52,13 @@ impl crate::message::StatefulSyncableServer for CertManager {}
 
 impl Syncable for CertManager {
     const CLASS: Class = Class::CertManager;
+
+    fn send_sync(&self, function: &str, params: crate::primitive::VariantList) {
+        crate::message::signalproxy::SYNC_PROXY.get().unwrap().sync(
+            Self::CLASS,
+            Some(&self.identity_id.0.to_string()),
+            function,
+            params,
+        );
+    }
 }
@@ -7,7 +7,7 @@ use crate::{
     primitive::{StringList, Variant, VariantMap},
 };
 
-#[derive(Debug, Clone, PartialEq, Setters)]
+#[derive(Debug, Default, Clone, PartialEq, Setters)]
 pub struct ChanModes {
     /// Modes that add or remove items from a list, like commonly +b for the banlist.
     ///
@@ -1,9 +1,13 @@
 use libquassel_derive::{NetworkList, NetworkMap};
 
 use crate::message::signalproxy::translation::NetworkMap;
-use crate::message::{Syncable, Class};
-use crate::primitive::{DateTime, StringList};
+use crate::message::{Class, Syncable};
+use crate::primitive::{DateTime, StringList, Variant, VariantList, VariantMap};
 
+/// Information about the core and the clients connected to this session
+///
+/// With the `SyncedCoreInfo` feature the core keeps this up to date with `setCoreData`,
+/// older cores only send it once as InitData.
 #[derive(Default, Debug, Clone, PartialEq, NetworkList, NetworkMap)]
 #[network(repr = "map")]
 pub struct CoreInfo {
@@ -18,6 +22,24 @@ impl CoreInfo {
 
         self.core_data = data;
     }
+
+    /// Get a client connected to this session by its peer id
+    pub fn connected_client(&self, peer: i32) -> Option<&ConnectedClient> {
+        self.core_data
+            .session_connected_client_data
+            .iter()
+            .find(|client| client.id == peer)
+    }
+
+    /// Disconnect another client connected to this session by its peer id
+    ///
+    /// The core needs to support the `RemoteDisconnect` feature.
+    #[cfg(all(feature = "client", feature = "remote-disconnect"))]
+    pub fn kick_client(&self, peer: i32) {
+        self.send_rpc(crate::message::RpcCall::KickClient(
+            crate::message::KickClient { peer_id: peer },
+        ));
+    }
 }
 
 #[cfg(feature = "client")]
@@ -54,22 +76,20 @@ impl Syncable for CoreInfo {
     const CLASS: Class = Class::CoreInfo;
 }
 
-#[derive(Debug, Clone, PartialEq, NetworkMap)]
-#[network(repr = "map")]
+/// Get a field of a map, cores without `SyncedCoreInfo` do not send all of them
+fn get<T: TryFrom<Variant> + Default>(input: &mut VariantMap, key: &str) -> T {
+    input
+        .remove(key)
+        .and_then(|value| value.try_into().ok())
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct CoreData {
-    #[network(rename = "quasselVersion")]
     pub quassel_version: String,
-    #[network(rename = "quasselBuildDate")]
     pub quassel_build_date: String,
-    #[network(rename = "startTime")]
     pub start_time: DateTime,
-    #[network(rename = "sessionConnectedClients")]
     pub session_connected_clients: i32,
-    #[network(
-        rename = "sessionConnectedClientData",
-        variant = "VariantList",
-        network = "map",
-    )]
     pub session_connected_client_data: Vec<ConnectedClient>,
 }
 
@@ -78,32 +98,224 @@ impl Default for CoreData {
         Self {
             quassel_version: Default::default(),
             quassel_build_date: Default::default(),
-            start_time:DateTime::unix_epoch(),
+            start_time: DateTime::unix_epoch(),
             session_connected_clients: Default::default(),
             session_connected_client_data: Default::default(),
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, NetworkMap)]
-#[network(repr = "map")]
+impl NetworkMap for CoreData {
+    type Item = VariantMap;
+
+    fn to_network_map(&self) -> Self::Item {
+        let mut res = VariantMap::new();
+
+        res.insert(
+            s!("quasselVersion"),
+            Variant::String(self.quassel_version.clone()),
+        );
+        res.insert(
+            s!("quasselBuildDate"),
+            Variant::String(self.quassel_build_date.clone()),
+        );
+        res.insert(s!("startTime"), Variant::DateTime(self.start_time));
+        res.insert(
+            s!("sessionConnectedClients"),
+            Variant::i32(self.session_connected_clients),
+        );
+        res.insert(
+            s!("sessionConnectedClientData"),
+            Variant::VariantList(
+                self.session_connected_client_data
+                    .iter()
+                    .map(|client| Variant::VariantMap(client.to_network_map()))
+                    .collect(),
+            ),
+        );
+
+        res
+    }
+
+    fn from_network_map(input: &mut Self::Item) -> Self {
+        let clients: VariantList = get(input, "sessionConnectedClientData");
+
+        Self {
+            quassel_version: get(input, "quasselVersion"),
+            quassel_build_date: get(input, "quasselBuildDate"),
+            start_time: input
+                .remove("startTime")
+                .and_then(|time| time.try_into().ok())
+                .unwrap_or_else(DateTime::unix_epoch),
+            session_connected_clients: get(input, "sessionConnectedClients"),
+            session_connected_client_data: clients
+                .into_iter()
+                .filter_map(|client| VariantMap::try_from(client).ok())
+                .map(|mut client| ConnectedClient::from_network_map(&mut client))
+                .collect(),
+        }
+    }
+}
+
+/// A client connected to the session
+#[derive(Debug, Clone, PartialEq)]
 pub struct ConnectedClient {
-    #[network(rename = "id")]
+    /// Peer id of the client, used to kick it
     pub id: i32,
-    #[network(rename = "remoteAddress")]
     pub remote_address: String,
-    // #[network(rename = "location")]
-    // location: String,
-    #[network(rename = "clientVersion")]
+    /// Location of the remote address if the core knows it
+    pub location: String,
     pub client_version: String,
-    #[network(rename = "clientVersionDate")]
     pub client_version_date: String,
-    #[network(rename = "connectedSince")]
     pub connected_since: DateTime,
-    #[network(rename = "secure")]
     pub secure: bool,
-    #[network(rename = "features")]
+    /// Legacy feature flags of the client
     pub features: u32,
-    #[network(rename = "featureList")]
+    /// Extended features of the client
     pub feature_list: StringList,
 }
+
+impl Default for ConnectedClient {
+    fn default() -> Self {
+        Self {
+            id: Default::default(),
+            remote_address: Default::default(),
+            location: Default::default(),
+            client_version: Default::default(),
+            client_version_date: Default::default(),
+            connected_since: DateTime::unix_epoch(),
+            secure: Default::default(),
+            features: Default::default(),
+            feature_list: Default::default(),
+        }
+    }
+}
+
+impl ConnectedClient {
+    /// The client announced support for a feature like `RemoteDisconnect`
+    pub fn has_feature(&self, feature: &str) -> bool {
+        self.feature_list.iter().any(|name| name == feature)
+    }
+}
+
+impl NetworkMap for ConnectedClient {
+    type Item = VariantMap;
+
+    fn to_network_map(&self) -> Self::Item {
+        let mut res = VariantMap::new();
+
+        res.insert(s!("id"), Variant::i32(self.id));
+        res.insert(
+            s!("remoteAddress"),
+            Variant::String(self.remote_address.clone()),
+        );
+        res.insert(s!("location"), Variant::String(self.location.clone()));
+        res.insert(
+            s!("clientVersion"),
+            Variant::String(self.client_version.clone()),
+        );
+        res.insert(
+            s!("clientVersionDate"),
+            Variant::String(self.client_version_date.clone()),
+        );
+        res.insert(
+            s!("connectedSince"),
+            Variant::DateTime(self.connected_since),
+        );
+        res.insert(s!("secure"), Variant::bool(self.secure));
+        res.insert(s!("features"), Variant::u32(self.features));
+        res.insert(
+            s!("featureList"),
+            Variant::StringList(self.feature_list.clone()),
+        );
+
+        res
+    }
+
+    fn from_network_map(input: &mut Self::Item) -> Self {
+        Self {
+            id: get(input, "id"),
+            remote_address: get(input, "remoteAddress"),
+            location: get(input, "location"),
+            client_version: get(input, "clientVersion"),
+            client_version_date: get(input, "clientVersionDate"),
+            connected_since: input
+                .remove("connectedSince")
+                .and_then(|time| time.try_into().ok())
+                .unwrap_or_else(DateTime::unix_epoch),
+            secure: get(input, "secure"),
+            features: get(input, "features"),
+            feature_list: get(input, "featureList"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::message::NetworkList;
+
+    fn get_runtime() -> CoreInfo {
+        CoreInfo {
+            core_data: CoreData {
+                quassel_version: s!("v0.14.0"),
+                quassel_build_date: s!("2021-01-01"),
+                start_time: DateTime::unix_epoch(),
+                session_connected_clients: 1,
+                session_connected_client_data: vec![ConnectedClient {
+                    id: 3,
+                    remote_address: s!("127.0.0.1"),
+                    client_version: s!("libquassel"),
+                    secure: true,
+                    feature_list: vec![s!("RemoteDisconnect")],
+                    ..Default::default()
+                }],
+            },
+        }
+    }
+
+    #[test]
+    fn coreinfo_network_list() {
+        let mut list = get_runtime().to_network_list();
+
+        assert_eq!(list[0], Variant::ByteArray(s!("coreData")));
+        assert_eq!(CoreInfo::from_network_list(&mut list), get_runtime());
+    }
+
+    #[test]
+    fn coredata_missing_fields() {
+        let mut map = VariantMap::new();
+        map.insert(s!("quasselVersion"), Variant::String(s!("v0.12.5")));
+
+        assert_eq!(
+            CoreData::from_network_map(&mut map),
+            CoreData {
+                quassel_version: s!("v0.12.5"),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn coreinfo_set_core_data() {
+        use crate::message::{StatefulSyncableClient, SyncMessage};
+
+        let mut core_info = CoreInfo::default();
+        core_info.sync(SyncMessage {
+            class_name: Class::CoreInfo,
+            object_name: s!(""),
+            slot_name: s!("setCoreData"),
+            params: vec![Variant::VariantMap(
+                get_runtime().core_data.to_network_map(),
+            )],
+        });
+
+        assert_eq!(core_info, get_runtime());
+
+        let client = core_info.connected_client(3).unwrap();
+        assert!(client.has_feature("RemoteDisconnect"));
+        assert!(core_info.connected_client(4).is_none());
+    }
+}
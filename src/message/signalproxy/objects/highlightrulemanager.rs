@@ -7,8 +7,10 @@ use crate::message::StatefulSyncableClient;
 #[allow(unused_imports)]
 use crate::message::StatefulSyncableServer;
 
+use crate::expression::{CachedMatch, MatchMode};
+use crate::formatting;
 use crate::message::Syncable;
-use crate::primitive::Variant;
+use crate::primitive::{Message, MessageType, Variant};
 
 #[derive(Default, Debug, Clone, PartialEq, NetworkList, NetworkMap)]
 pub struct HighlightRuleManager {
@@ -18,6 +20,8 @@ pub struct HighlightRuleManager {
     pub highlight_nick: HighlightNickType,
     #[network(rename = "nicksCaseSensitive")]
     pub nicks_case_sensitive: bool,
+    #[network(skip, default)]
+    nick_match: CachedMatch,
 }
 
 impl HighlightRuleManager {
@@ -47,6 +51,80 @@ impl HighlightRuleManager {
         }
     }
 
+    /// Check if the message should be highlighted
+    ///
+    /// Only plain messages, notices and actions not sent by ourselves can be highlights.
+    /// The rules are checked first, an inverse rule that matches prevents any highlight.
+    /// Then depending on [`HighlightNickType`] the current nick or all nicks of the identity
+    /// are highlighted.
+    pub fn is_highlight(
+        &self,
+        msg: &Message,
+        current_nick: &str,
+        identity_nicks: &[String],
+    ) -> bool {
+        if !msg
+            .msg_type
+            .intersects(MessageType::PLAIN | MessageType::NOTICE | MessageType::ACTION)
+            || msg.is_self()
+        {
+            return false;
+        }
+
+        let content = formatting::strip(&msg.content);
+
+        let mut highlight = false;
+
+        for rule in self
+            .highlight_rule_list
+            .iter()
+            .filter(|rule| rule.is_enabled)
+        {
+            if !rule.channel_matches(&msg.buffer.name) {
+                continue;
+            }
+
+            if rule.contents_matches(&content) && rule.sender_matches(&msg.sender) {
+                if rule.is_inverse {
+                    return false;
+                }
+
+                highlight = true;
+            }
+        }
+
+        highlight
+            || (!current_nick.is_empty()
+                && self.nick_matches(&content, current_nick, identity_nicks))
+    }
+
+    /// Check the text for our own nicks according to [`HighlightNickType`]
+    fn nick_matches(&self, text: &str, current_nick: &str, identity_nicks: &[String]) -> bool {
+        let mut nicks: Vec<&str> = Vec::new();
+
+        match self.highlight_nick {
+            HighlightNickType::NoNick => (),
+            HighlightNickType::CurrentNick => nicks.push(current_nick),
+            HighlightNickType::AllNicks => {
+                nicks.push(current_nick);
+                nicks.extend(
+                    identity_nicks
+                        .iter()
+                        .map(String::as_str)
+                        .filter(|nick| *nick != current_nick),
+                );
+            }
+        }
+
+        self.nick_match
+            .get(
+                &nicks.join("\n"),
+                MatchMode::MultiPhrase,
+                self.nicks_case_sensitive,
+            )
+            .matches(text, false)
+    }
+
     pub fn request_remove_highlight_rule(&self, id: i32) {
         sync!("requestRemoveHighlightRule", [id])
     }
@@ -154,16 +232,16 @@ impl StatefulSyncableClient for HighlightRuleManager {
         match msg.slot_name.as_str() {
             "removeHighlightRule" => self.remove_highlight_rule(get_param!(msg)),
             "toggleHighlightRule" => self.toggle_highlight_rule(get_param!(msg)),
-            "addHighlightRule" => self.add_highlight_rule(HighlightRule {
-                id: get_param!(msg),
-                name: get_param!(msg),
-                is_regex: get_param!(msg),
-                is_case_sensitive: get_param!(msg),
-                is_enabled: get_param!(msg),
-                is_inverse: get_param!(msg),
-                sender: get_param!(msg),
-                channel: get_param!(msg),
-            }),
+            "addHighlightRule" => self.add_highlight_rule(HighlightRule::new(
+                get_param!(msg),
+                get_param!(msg),
+                get_param!(msg),
+                get_param!(msg),
+                get_param!(msg),
+                get_param!(msg),
+                get_param!(msg),
+                get_param!(msg),
+            )),
             "setHighlightNick" => self.set_highlight_nick(get_param!(msg)),
             "setNicksCaseSensitive" => self.set_nicks_case_sensitive(get_param!(msg)),
             _ => (),
@@ -180,16 +258,16 @@ impl StatefulSyncableServer for HighlightRuleManager {
         match msg.slot_name.as_str() {
             "requestRemoveHighlightRule" => self.remove_highlight_rule(get_param!(msg)),
             "requestToggleHighlightRule" => self.toggle_highlight_rule(get_param!(msg)),
-            "requestAddHighlightRule" => self.add_highlight_rule(HighlightRule {
-                id: get_param!(msg),
-                name: get_param!(msg),
-                is_regex: get_param!(msg),
-                is_case_sensitive: get_param!(msg),
-                is_enabled: get_param!(msg),
-                is_inverse: get_param!(msg),
-                sender: get_param!(msg),
-                channel: get_param!(msg),
-            }),
+            "requestAddHighlightRule" => self.add_highlight_rule(HighlightRule::new(
+                get_param!(msg),
+                get_param!(msg),
+                get_param!(msg),
+                get_param!(msg),
+                get_param!(msg),
+                get_param!(msg),
+                get_param!(msg),
+                get_param!(msg),
+            )),
             "requestSetHighlightNick" => self.set_highlight_nick(get_param!(msg)),
             "requestSetNicksCaseSensitive" => self.set_nicks_case_sensitive(get_param!(msg)),
             _ => (),
@@ -219,6 +297,72 @@ pub struct HighlightRule {
     pub sender: String,
     #[network(variant = "StringList")]
     pub channel: String,
+    #[network(skip, default)]
+    contents_match: CachedMatch,
+    #[network(skip, default)]
+    sender_match: CachedMatch,
+    #[network(skip, default)]
+    channel_match: CachedMatch,
+}
+
+impl HighlightRule {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        id: i32,
+        name: String,
+        is_regex: bool,
+        is_case_sensitive: bool,
+        is_enabled: bool,
+        is_inverse: bool,
+        sender: String,
+        channel: String,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            is_regex,
+            is_case_sensitive,
+            is_enabled,
+            is_inverse,
+            sender,
+            channel,
+            contents_match: CachedMatch::default(),
+            sender_match: CachedMatch::default(),
+            channel_match: CachedMatch::default(),
+        }
+    }
+
+    fn contents_matches(&self, text: &str) -> bool {
+        let mode = if self.is_regex {
+            MatchMode::RegEx
+        } else {
+            MatchMode::Phrase
+        };
+
+        self.contents_match
+            .get(&self.name, mode, self.is_case_sensitive)
+            .matches(text, true)
+    }
+
+    fn sender_matches(&self, sender: &str) -> bool {
+        self.sender_match
+            .get(&self.sender, self.wildcard_mode(), self.is_case_sensitive)
+            .matches(sender, true)
+    }
+
+    fn channel_matches(&self, channel: &str) -> bool {
+        self.channel_match
+            .get(&self.channel, self.wildcard_mode(), false)
+            .matches(channel, true)
+    }
+
+    fn wildcard_mode(&self) -> MatchMode {
+        if self.is_regex {
+            MatchMode::RegEx
+        } else {
+            MatchMode::MultiWildcard
+        }
+    }
 }
 
 #[derive(Default, Debug, Clone, PartialEq)]
@@ -290,18 +434,18 @@ mod tests {
 
     fn get_runtime() -> HighlightRuleManager {
         HighlightRuleManager {
-            highlight_rule_list: vec![HighlightRule {
-                id: 1,
-                name: s!("testrule"),
-                is_regex: false,
-                is_case_sensitive: false,
-                is_enabled: true,
-                is_inverse: false,
-                sender: s!("testuser"),
-                channel: s!("#test"),
-            }],
+            highlight_rule_list: vec![HighlightRule::new(
+                1,
+                s!("testrule"),
+                false,
+                false,
+                true,
+                false,
+                s!("testuser"),
+                s!("#test"),
+            )],
             highlight_nick: HighlightNickType::CurrentNick,
-            nicks_case_sensitive: false,
+            ..Default::default()
         }
     }
 
@@ -317,4 +461,115 @@ mod tests {
             get_runtime()
         )
     }
+
+    fn message(sender: &str, content: &str) -> Message {
+        Message {
+            sender: s!(sender),
+            ..Message::test(1, content)
+        }
+    }
+
+    #[test]
+    fn highlightrulemanager_nick_highlight() {
+        let mut manager = HighlightRuleManager {
+            highlight_nick: HighlightNickType::CurrentNick,
+            ..Default::default()
+        };
+        let identity_nicks = vec![s!("audron"), s!("audron_")];
+
+        assert!(manager.is_highlight(
+            &message("a!b@c", "hey \x02Audron\x02: hi"),
+            "audron",
+            &identity_nicks
+        ));
+        assert!(!manager.is_highlight(&message("a!b@c", "audronbot"), "audron", &identity_nicks));
+        assert!(!manager.is_highlight(
+            &message("a!b@c", "audron_ ping"),
+            "audron",
+            &identity_nicks
+        ));
+
+        manager.highlight_nick = HighlightNickType::AllNicks;
+        assert!(manager.is_highlight(&message("a!b@c", "audron_ ping"), "audron", &identity_nicks));
+
+        manager.highlight_nick = HighlightNickType::NoNick;
+        assert!(!manager.is_highlight(&message("a!b@c", "audron"), "audron", &identity_nicks));
+
+        let mut own = message("audron!b@c", "audron");
+        own.flags = crate::primitive::MessageFlags::SELF;
+        manager.highlight_nick = HighlightNickType::CurrentNick;
+        assert!(!manager.is_highlight(&own, "audron", &identity_nicks));
+    }
+
+    #[test]
+    fn highlightrulemanager_rules() {
+        let rule = HighlightRule::new(
+            1,
+            s!("deploy"),
+            false,
+            false,
+            true,
+            false,
+            s!(""),
+            s!("#test;#ops"),
+        );
+        let mut manager = HighlightRuleManager {
+            highlight_rule_list: vec![
+                rule.clone(),
+                HighlightRule {
+                    id: 2,
+                    name: s!(r"^build \d+ failed"),
+                    is_regex: true,
+                    sender: s!("^ci!"),
+                    channel: s!(""),
+                    ..rule.clone()
+                },
+                HighlightRule {
+                    id: 3,
+                    name: s!(""),
+                    is_inverse: true,
+                    sender: s!("*bot!*"),
+                    ..rule
+                },
+            ],
+            highlight_nick: HighlightNickType::NoNick,
+            ..Default::default()
+        };
+
+        assert!(manager.is_highlight(&message("a!b@c", "Deploy done"), "audron", &[]));
+        assert!(!manager.is_highlight(&message("a!b@c", "deployment"), "audron", &[]));
+        assert!(manager.is_highlight(&message("ci!b@c", "build 12 failed"), "audron", &[]));
+        assert!(!manager.is_highlight(&message("other!b@c", "build 12 failed"), "audron", &[]));
+        assert!(!manager.is_highlight(&message("deploybot!b@c", "deploy done"), "audron", &[]));
+
+        manager.toggle_highlight_rule(3);
+        assert!(manager.is_highlight(&message("deploybot!b@c", "deploy done"), "audron", &[]));
+
+        // A matching inverse rule also prevents nick highlights
+        manager.toggle_highlight_rule(3);
+        manager.highlight_nick = HighlightNickType::CurrentNick;
+        assert!(manager.is_highlight(&message("a!b@c", "audron: hi"), "audron", &[]));
+        assert!(!manager.is_highlight(&message("deploybot!b@c", "audron: hi"), "audron", &[]));
+    }
+
+    #[test]
+    fn highlightrulemanager_rule_phrase() {
+        let rule = HighlightRule::new(1, s!("foo;bar"), false, false, true, false, s!(""), s!(""));
+        let mut manager = HighlightRuleManager {
+            highlight_rule_list: vec![rule.clone()],
+            highlight_nick: HighlightNickType::NoNick,
+            ..Default::default()
+        };
+
+        // The whole name is a single phrase, unlike the multiple nicks of a nick highlight
+        assert!(manager.is_highlight(&message("a!b@c", "about foo;bar now"), "audron", &[]));
+        assert!(!manager.is_highlight(&message("a!b@c", "foo"), "audron", &[]));
+        assert!(!manager.is_highlight(&message("a!b@c", "bar"), "audron", &[]));
+
+        manager.highlight_rule_list = vec![HighlightRule {
+            name: s!("foo\nbar"),
+            ..rule
+        }];
+        assert!(!manager.is_highlight(&message("a!b@c", "foo"), "audron", &[]));
+    }
 }
@@ -9,6 +9,10 @@ use crate::message::StatefulSyncableClient;
 use crate::message::StatefulSyncableServer;
 
 use crate::message::Syncable;
+#[cfg(feature = "client")]
+use crate::message::{objects::CertManager, CreateIdentity, RemoveIdentity, RpcCall};
+#[cfg(feature = "client")]
+use crate::primitive::IdentityId;
 
 #[allow(unused_imports)]
 use crate::message::signalproxy::translation::NetworkMap;
@@ -67,6 +71,27 @@ impl Identity {
 
         *self = other;
     }
+
+    /// Client -> Server: Ask the core to create this identity
+    ///
+    /// The id is assigned by the core, which answers with an `identityCreated` RPC.
+    /// The CertFP key and certificate are taken from the `cert_manager` of the identity.
+    #[cfg(feature = "client")]
+    pub fn create_identity(&self, cert_manager: &CertManager) {
+        self.send_rpc(RpcCall::CreateIdentity(CreateIdentity {
+            identity: self.clone(),
+            ssl_key: cert_manager.ssl_key.clone(),
+            ssl_cert: cert_manager.ssl_cert.clone(),
+        }));
+    }
+
+    /// Client -> Server: Ask the core to remove this identity
+    #[cfg(feature = "client")]
+    pub fn remove_identity(&self) {
+        self.send_rpc(RpcCall::RemoveIdentity(RemoveIdentity {
+            identity_id: IdentityId(self.identity_id),
+        }));
+    }
 }
 
 #[cfg(feature = "client")]
@@ -1,6 +1,7 @@
 use crate::{
+    expression::{CachedMatch, MatchMode},
     message::{Class, Syncable},
-    primitive::Variant,
+    primitive::{Message, MessageType, Variant},
 };
 
 use libquassel_derive::{sync, NetworkList, NetworkMap};
@@ -40,6 +41,62 @@ impl IgnoreListManager {
         }
     }
 
+    /// Check if the message is ignored by any of the active rules
+    ///
+    /// Returns the strictness of the first matching rule, or `None` if the message is not ignored.
+    /// Only plain messages, notices and actions can be ignored, CTCP rules are handled by [`Self::ctcp_matches`].
+    pub fn matches(&self, msg: &Message, network_name: &str) -> Option<StrictnessType> {
+        if !msg
+            .msg_type
+            .intersects(MessageType::PLAIN | MessageType::NOTICE | MessageType::ACTION)
+        {
+            return None;
+        }
+
+        self.ignore_list
+            .iter()
+            .filter(|item| item.is_active && item.ignore_type != IgnoreType::CtcpIgnore)
+            .filter(|item| item.scope_matches(network_name, &msg.buffer.name))
+            .find(|item| {
+                let text = match item.ignore_type {
+                    IgnoreType::MessageIgnore => &msg.content,
+                    _ => &msg.sender,
+                };
+
+                item.contents_match
+                    .get(&item.ignore_rule, item.match_mode(), false)
+                    .matches(text, false)
+            })
+            .map(|item| item.strictness)
+    }
+
+    /// Check if a CTCP request of the given type from sender is ignored
+    ///
+    /// CTCP rules are in the form of `sender [TYPE...]`, without types all CTCP requests are ignored.
+    pub fn ctcp_matches(&self, sender: &str, network_name: &str, ctcp_type: &str) -> bool {
+        self.ignore_list
+            .iter()
+            .filter(|item| item.is_active && item.ignore_type == IgnoreType::CtcpIgnore)
+            // Channel scopes don't apply to CTCP requests
+            .filter(|item| match item.scope {
+                ScopeType::GlobalScope | ScopeType::NetworkScope => {
+                    item.scope_matches(network_name, "")
+                }
+                ScopeType::ChannelScope => false,
+            })
+            .any(|item| {
+                let mut rule = item.ignore_rule.split_whitespace();
+                let ctcp_sender = rule.next().unwrap_or_default();
+                let mut ctcp_types = rule.peekable();
+
+                item.contents_match
+                    .get(ctcp_sender, item.match_mode(), false)
+                    .matches(sender, false)
+                    && (ctcp_types.peek().is_none()
+                        || ctcp_types.any(|t| t.eq_ignore_ascii_case(ctcp_type)))
+            })
+    }
+
     pub fn request_add_ignore_list_item(
         &self,
         IgnoreListItem {
@@ -50,6 +107,7 @@ impl IgnoreListManager {
             scope,
             scope_rule,
             is_active,
+            ..
         }: IgnoreListItem,
     ) {
         sync!(
@@ -124,15 +182,15 @@ impl crate::message::StatefulSyncableClient for IgnoreListManager {
         Self: Sized,
     {
         match msg.slot_name.as_str() {
-            "addIgnoreListItem" => self.add_ignore_list_item(IgnoreListItem {
-                ignore_type: get_param!(msg),
-                ignore_rule: get_param!(msg),
-                is_regex: get_param!(msg),
-                strictness: get_param!(msg),
-                scope: get_param!(msg),
-                scope_rule: get_param!(msg),
-                is_active: get_param!(msg),
-            }),
+            "addIgnoreListItem" => self.add_ignore_list_item(IgnoreListItem::new(
+                get_param!(msg),
+                get_param!(msg),
+                get_param!(msg),
+                get_param!(msg),
+                get_param!(msg),
+                get_param!(msg),
+                get_param!(msg),
+            )),
             "removeIgnoreListItem" => {
                 let rule: String = get_param!(msg);
                 self.remove_ignore_list_item(&rule);
@@ -153,15 +211,15 @@ impl crate::message::StatefulSyncableServer for IgnoreListManager {
         Self: Sized,
     {
         match msg.slot_name.as_str() {
-            "requestAddIgnoreListItem" => self.add_ignore_list_item(IgnoreListItem {
-                ignore_type: get_param!(msg),
-                ignore_rule: get_param!(msg),
-                is_regex: get_param!(msg),
-                strictness: get_param!(msg),
-                scope: get_param!(msg),
-                scope_rule: get_param!(msg),
-                is_active: get_param!(msg),
-            }),
+            "requestAddIgnoreListItem" => self.add_ignore_list_item(IgnoreListItem::new(
+                get_param!(msg),
+                get_param!(msg),
+                get_param!(msg),
+                get_param!(msg),
+                get_param!(msg),
+                get_param!(msg),
+                get_param!(msg),
+            )),
             "requestRemoveIgnoreListItem" => {
                 let rule: String = get_param!(msg);
                 self.remove_ignore_list_item(&rule);
@@ -196,6 +254,56 @@ pub struct IgnoreListItem {
     pub scope_rule: String,
     #[network(rename = "isActive")]
     pub is_active: bool,
+    #[network(skip, default)]
+    contents_match: CachedMatch,
+    #[network(skip, default)]
+    scope_match: CachedMatch,
+}
+
+impl IgnoreListItem {
+    pub fn new(
+        ignore_type: IgnoreType,
+        ignore_rule: String,
+        is_regex: bool,
+        strictness: StrictnessType,
+        scope: ScopeType,
+        scope_rule: String,
+        is_active: bool,
+    ) -> Self {
+        Self {
+            ignore_type,
+            ignore_rule,
+            is_regex,
+            strictness,
+            scope,
+            scope_rule,
+            is_active,
+            contents_match: CachedMatch::default(),
+            scope_match: CachedMatch::default(),
+        }
+    }
+
+    fn match_mode(&self) -> MatchMode {
+        if self.is_regex {
+            MatchMode::RegEx
+        } else {
+            MatchMode::Wildcard
+        }
+    }
+
+    /// Check if the rule applies in the given network and buffer
+    fn scope_matches(&self, network_name: &str, buffer_name: &str) -> bool {
+        let scope_rule = || {
+            self.scope_match
+                .get(&self.scope_rule, MatchMode::MultiWildcard, false)
+        };
+
+        match self.scope {
+            ScopeType::GlobalScope => true,
+            ScopeType::NetworkScope => scope_rule().matches(network_name, false),
+            ScopeType::ChannelScope => scope_rule().matches(buffer_name, false),
+        }
+    }
 }
 
 /////////////////////////////////////
@@ -320,3 +428,140 @@ impl TryFrom<i32> for ScopeType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(
+        ignore_type: IgnoreType,
+        rule: &str,
+        scope: ScopeType,
+        scope_rule: &str,
+    ) -> IgnoreListItem {
+        IgnoreListItem::new(
+            ignore_type,
+            s!(rule),
+            false,
+            StrictnessType::SoftStrictness,
+            scope,
+            s!(scope_rule),
+            true,
+        )
+    }
+
+    fn message(sender: &str, content: &str) -> Message {
+        Message {
+            sender: s!(sender),
+            ..Message::test(1, content)
+        }
+    }
+
+    #[test]
+    fn ignore_matches_sender_and_message() {
+        let manager = IgnoreListManager {
+            ignore_list: vec![
+                item(
+                    IgnoreType::SenderIgnore,
+                    "*!*@spam.example.com",
+                    ScopeType::GlobalScope,
+                    "",
+                ),
+                IgnoreListItem {
+                    is_regex: true,
+                    strictness: StrictnessType::HardStrictness,
+                    ..item(
+                        IgnoreType::MessageIgnore,
+                        r"buy \w+ now",
+                        ScopeType::GlobalScope,
+                        "",
+                    )
+                },
+            ],
+        };
+
+        assert_eq!(
+            manager.matches(&message("bot!bot@spam.example.com", "hi"), "libera"),
+            Some(StrictnessType::SoftStrictness)
+        );
+        assert_eq!(
+            manager.matches(&message("nick!user@host", "please BUY pills now"), "libera"),
+            Some(StrictnessType::HardStrictness)
+        );
+        assert_eq!(
+            manager.matches(&message("nick!user@host", "hello"), "libera"),
+            None
+        );
+
+        let mut join = message("bot!bot@spam.example.com", "#test");
+        join.msg_type = MessageType::JOIN;
+        assert_eq!(manager.matches(&join, "libera"), None);
+    }
+
+    #[test]
+    fn ignore_matches_scope() {
+        let mut manager = IgnoreListManager {
+            ignore_list: vec![
+                item(
+                    IgnoreType::SenderIgnore,
+                    "nick!*",
+                    ScopeType::NetworkScope,
+                    "oftc; libera",
+                ),
+                item(
+                    IgnoreType::SenderIgnore,
+                    "other!*",
+                    ScopeType::ChannelScope,
+                    "#t*;!#tmp",
+                ),
+            ],
+        };
+
+        assert!(manager
+            .matches(&message("nick!user@host", "hi"), "libera")
+            .is_some());
+        assert!(manager
+            .matches(&message("nick!user@host", "hi"), "efnet")
+            .is_none());
+        assert!(manager
+            .matches(&message("other!user@host", "hi"), "efnet")
+            .is_some());
+
+        manager.ignore_list[0].is_active = false;
+        assert!(manager
+            .matches(&message("nick!user@host", "hi"), "libera")
+            .is_none());
+    }
+
+    #[test]
+    fn ignore_ctcp_matches() {
+        let manager = IgnoreListManager {
+            ignore_list: vec![
+                item(
+                    IgnoreType::CtcpIgnore,
+                    "*!*@host VERSION TIME",
+                    ScopeType::GlobalScope,
+                    "",
+                ),
+                item(
+                    IgnoreType::CtcpIgnore,
+                    "flood!*",
+                    ScopeType::NetworkScope,
+                    "libera",
+                ),
+                item(
+                    IgnoreType::CtcpIgnore,
+                    "spam!*",
+                    ScopeType::ChannelScope,
+                    "*",
+                ),
+            ],
+        };
+
+        assert!(manager.ctcp_matches("nick!user@host", "libera", "version"));
+        assert!(!manager.ctcp_matches("nick!user@host", "libera", "PING"));
+        assert!(manager.ctcp_matches("flood!user@else", "libera", "PING"));
+        assert!(!manager.ctcp_matches("flood!user@else", "oftc", "PING"));
+        assert!(!manager.ctcp_matches("spam!user@else", "libera", "PING"));
+    }
+}
@@ -11,7 +11,7 @@ use crate::primitive::StringList;
 use super::{ChanModes, ChannelModeType};
 
 #[allow(dead_code)]
-#[derive(Debug, Clone, PartialEq, Setters, NetworkMap)]
+#[derive(Debug, Default, Clone, PartialEq, Setters, NetworkMap)]
 #[network(repr = "maplist")]
 pub struct IrcChannel {
     #[network(rename = "ChanModes", network = "map")]
@@ -197,6 +197,20 @@ impl Syncable for IrcChannel {
     const CLASS: Class = Class::IrcChannel;
 }
 
+/// Used when the IrcChannel is initialized on its own instead of as part of its Network
+impl crate::message::NetworkList for IrcChannel {
+    fn to_network_list(&self) -> crate::primitive::VariantList {
+        let map = crate::message::NetworkMap::to_network_map(self);
+        crate::message::signalproxy::maplist_to_list(map)
+    }
+
+    fn from_network_list(input: &mut crate::primitive::VariantList) -> Self {
+        crate::message::NetworkMap::from_network_map(
+            &mut crate::message::signalproxy::list_to_maplist(input),
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -293,6 +307,15 @@ mod tests {
         )
     }
 
+    #[test]
+    fn ircchannel_network_list() {
+        use crate::message::NetworkList;
+
+        let mut list = get_runtime().to_network_list();
+
+        assert_eq!(IrcChannel::from_network_list(&mut list), get_runtime())
+    }
+
     #[test]
     fn add_user_mode() {
         let mut base = get_runtime();
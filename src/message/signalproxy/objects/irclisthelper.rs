@@ -0,0 +1,433 @@
+// interface IrcListHelper {
+//
+//
+//     // C->S calls
+//
+//     /**
+//      * Start a LIST on the network, empty filters request the result of the finished LIST
+//      */
+//     requestChannelList(netId: NetworkId, channelFilters: QStringList)
+//
+//
+//     // S->C calls
+//
+//     /**
+//      * The response to `requestChannelList`, with every channel as a QVariantList of
+//      * name, user count and topic in the `channels` parameter.
+//      */
+//     receiveChannelList(netId: NetworkId, channelFilters: QStringList, channels: QVariantList)
+//     /**
+//      * The LIST on the network finished and can be requested with empty filters
+//      */
+//     reportFinishedList(netId: NetworkId)
+//     /**
+//      * The LIST failed
+//      */
+//     reportError(error: QString)
+// }
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Condvar, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use libquassel_derive::{NetworkList, NetworkMap};
+
+use crate::message::{Class, Syncable};
+use crate::primitive::{NetworkId, StringList, Variant, VariantList};
+
+/// Lists the channels of a network
+///
+/// A list is requested with [`IrcListHelper::list_channels`]. The core first runs the LIST
+/// and reports when it finished, then the result is requested and resolves the
+/// returned [`PendingChannelList`].
+#[derive(Clone, Debug, Default, PartialEq, NetworkList, NetworkMap)]
+pub struct IrcListHelper {
+    #[network(skip, default)]
+    pending: Vec<(NetworkId, PendingChannelList)>,
+    /// Networks whose finished list was requested, the next response resolves their lists
+    #[network(skip, default)]
+    finished: Vec<NetworkId>,
+}
+
+/// A channel as reported by the LIST command
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ChannelListEntry {
+    pub name: String,
+    pub user_count: u32,
+    pub topic: String,
+}
+
+impl ChannelListEntry {
+    fn from_variant(input: Variant) -> Option<Self> {
+        let mut fields = VariantList::try_from(input).ok()?.into_iter();
+
+        let name = fields.next()?.into();
+        let user_count = match fields.next()? {
+            Variant::u32(count) => count,
+            Variant::i32(count) => count as u32,
+            _ => return None,
+        };
+        let topic = fields.next().map(Into::into).unwrap_or_default();
+
+        Some(Self {
+            name,
+            user_count,
+            topic,
+        })
+    }
+
+    #[cfg(feature = "server")]
+    fn to_variant(&self) -> Variant {
+        Variant::VariantList(vec![
+            Variant::String(self.name.clone()),
+            Variant::u32(self.user_count),
+            Variant::String(self.topic.clone()),
+        ])
+    }
+}
+
+impl IrcListHelper {
+    /// Run a LIST on the network, only channels matching one of the filters are listed
+    pub fn request_channel_list(&self, network_id: NetworkId, filters: StringList) {
+        self.send_sync(
+            "requestChannelList",
+            vec![network_id.into(), Variant::StringList(filters)],
+        );
+    }
+
+    /// List the channels of a network, resolves once the core sent the finished list
+    pub fn list_channels(
+        &mut self,
+        network_id: NetworkId,
+        filters: StringList,
+    ) -> PendingChannelList {
+        let pending = PendingChannelList::default();
+        self.pending.push((network_id, pending.clone()));
+
+        self.request_channel_list(network_id, filters);
+
+        pending
+    }
+
+    /// Number of lists still waiting for a response
+    pub fn pending_requests(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Resolve the pending lists of the network with the channels
+    ///
+    /// The response to the initial request is empty and only ends the request
+    /// if the core already had a finished list. Once the LIST finished,
+    /// the response resolves the lists even if the network has no channels.
+    pub fn receive_channel_list(&mut self, network_id: NetworkId, channels: Vec<ChannelListEntry>) {
+        if channels.is_empty() && !self.finished.contains(&network_id) {
+            return;
+        }
+
+        self.finished.retain(|id| *id != network_id);
+
+        self.pending.retain(|(id, pending)| {
+            if *id == network_id {
+                pending.resolve(Ok(channels.clone()));
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    /// Request the finished list for networks with pending lists
+    pub fn report_finished_list(&mut self, network_id: NetworkId) {
+        if self.pending.iter().any(|(id, _)| *id == network_id) {
+            if !self.finished.contains(&network_id) {
+                self.finished.push(network_id);
+            }
+
+            self.request_channel_list(network_id, StringList::new());
+        }
+    }
+
+    /// Fail all pending lists, the error is not specific to a network
+    pub fn report_error(&mut self, error: String) {
+        log::warn!("listing channels failed: {}", error);
+
+        for (_, pending) in self.pending.drain(..) {
+            pending.resolve(Err(error.clone()));
+        }
+        self.finished.clear();
+    }
+
+    /// Send the channels of a LIST to the client
+    #[cfg(feature = "server")]
+    pub fn send_channel_list(
+        &self,
+        network_id: NetworkId,
+        filters: StringList,
+        channels: &[ChannelListEntry],
+    ) {
+        self.send_sync(
+            "receiveChannelList",
+            vec![
+                network_id.into(),
+                Variant::StringList(filters),
+                Variant::VariantList(channels.iter().map(ChannelListEntry::to_variant).collect()),
+            ],
+        );
+    }
+
+    #[cfg(feature = "server")]
+    pub fn send_finished_list(&self, network_id: NetworkId) {
+        self.send_sync("reportFinishedList", vec![network_id.into()]);
+    }
+
+    #[cfg(feature = "server")]
+    pub fn send_error(&self, error: String) {
+        self.send_sync("reportError", vec![Variant::String(error)]);
+    }
+}
+
+#[cfg(feature = "client")]
+impl crate::message::StatefulSyncableClient for IrcListHelper {
+    fn sync_custom(&mut self, mut msg: crate::message::SyncMessage)
+    where
+        Self: Sized,
+    {
+        match msg.slot_name.as_str() {
+            "receiveChannelList" => {
+                let network_id = get_param!(msg);
+                let _filters: StringList = get_param!(msg);
+                let channels: VariantList = get_param!(msg);
+
+                self.receive_channel_list(
+                    network_id,
+                    channels
+                        .into_iter()
+                        .filter_map(ChannelListEntry::from_variant)
+                        .collect(),
+                )
+            }
+            "reportFinishedList" => self.report_finished_list(get_param!(msg)),
+            "reportError" => self.report_error(msg.params.remove(0).into()),
+            _ => (),
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+impl crate::message::StatefulSyncableServer for IrcListHelper {}
+
+impl Syncable for IrcListHelper {
+    const CLASS: Class = Class::IrcListHelper;
+}
+
+/// Channels of a LIST that will be available once the core finished it
+///
+/// Can be waited on blocking with [`PendingChannelList::wait`] or awaited as a [`Future`].
+#[derive(Clone, Debug, Default)]
+pub struct PendingChannelList {
+    state: Arc<(Mutex<PendingState>, Condvar)>,
+}
+
+type ChannelListResult = Result<Vec<ChannelListEntry>, String>;
+
+#[derive(Debug, Default)]
+struct PendingState {
+    channels: Option<ChannelListResult>,
+    waker: Option<Waker>,
+}
+
+impl PendingChannelList {
+    fn resolve(&self, channels: ChannelListResult) {
+        let (state, condvar) = &*self.state;
+        let mut state = state.lock().unwrap();
+
+        state.channels = Some(channels);
+        if let Some(waker) = state.waker.take() {
+            waker.wake()
+        }
+
+        condvar.notify_all();
+    }
+
+    /// Take the channels if the list already finished
+    pub fn try_take(&self) -> Option<ChannelListResult> {
+        self.state.0.lock().unwrap().channels.take()
+    }
+
+    /// Block until the list finished and take the channels
+    pub fn wait(&self) -> ChannelListResult {
+        let (state, condvar) = &*self.state;
+        let mut state = condvar
+            .wait_while(state.lock().unwrap(), |state| state.channels.is_none())
+            .unwrap();
+
+        state.channels.take().unwrap()
+    }
+}
+
+/// Two pending lists are equal if they wait for the same response
+impl PartialEq for PendingChannelList {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.state, &other.state)
+    }
+}
+
+impl Future for PendingChannelList {
+    type Output = ChannelListResult;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.0.lock().unwrap();
+
+        match state.channels.take() {
+            Some(channels) => Poll::Ready(channels),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel_list() -> VariantList {
+        vec![
+            Variant::VariantList(vec![
+                Variant::String(s!("#quassel")),
+                Variant::u32(300),
+                Variant::String(s!("Quassel IRC")),
+            ]),
+            Variant::VariantList(vec![
+                Variant::String(s!("#libquassel")),
+                Variant::u32(3),
+                Variant::String(s!("")),
+            ]),
+        ]
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn channellistentry_variant_roundtrip() {
+        let entry = ChannelListEntry {
+            name: s!("#quassel"),
+            user_count: 300,
+            topic: s!("Quassel IRC"),
+        };
+
+        assert_eq!(
+            ChannelListEntry::from_variant(entry.to_variant()),
+            Some(entry)
+        );
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn irclisthelper_list_channels() {
+        use crate::message::{StatefulSyncableClient, SyncMessage};
+
+        crate::message::signalproxy::init_test_sync_proxy();
+
+        let sync = |slot_name: &str, params: VariantList| SyncMessage {
+            class_name: Class::IrcListHelper,
+            object_name: s!(""),
+            slot_name: slot_name.to_owned(),
+            params,
+        };
+
+        let mut helper = IrcListHelper::default();
+        let first = helper.list_channels(NetworkId(1), vec![s!("#*quassel*")]);
+        let second = helper.list_channels(NetworkId(2), vec![]);
+
+        // The response to the initial request is empty
+        helper.sync(sync(
+            "receiveChannelList",
+            vec![
+                Variant::i32(1),
+                Variant::StringList(vec![s!("#*quassel*")]),
+                Variant::VariantList(vec![]),
+            ],
+        ));
+        helper.sync(sync("reportFinishedList", vec![Variant::i32(1)]));
+        assert_eq!(first.try_take(), None);
+
+        helper.sync(sync(
+            "receiveChannelList",
+            vec![
+                Variant::i32(1),
+                Variant::StringList(vec![]),
+                Variant::VariantList(channel_list()),
+            ],
+        ));
+
+        assert_eq!(
+            first.wait().unwrap(),
+            vec![
+                ChannelListEntry {
+                    name: s!("#quassel"),
+                    user_count: 300,
+                    topic: s!("Quassel IRC"),
+                },
+                ChannelListEntry {
+                    name: s!("#libquassel"),
+                    user_count: 3,
+                    topic: s!(""),
+                },
+            ]
+        );
+        assert_eq!(helper.pending_requests(), 1);
+
+        helper.sync(sync(
+            "reportError",
+            vec![Variant::String(s!("not connected"))],
+        ));
+        assert_eq!(second.wait(), Err(s!("not connected")));
+        assert_eq!(helper.pending_requests(), 0);
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn irclisthelper_empty_list() {
+        use crate::message::{StatefulSyncableClient, SyncMessage};
+
+        crate::message::signalproxy::init_test_sync_proxy();
+
+        let receive = |network_id: i32| SyncMessage {
+            class_name: Class::IrcListHelper,
+            object_name: s!(""),
+            slot_name: s!("receiveChannelList"),
+            params: vec![
+                Variant::i32(network_id),
+                Variant::StringList(vec![]),
+                Variant::VariantList(vec![]),
+            ],
+        };
+
+        let mut helper = IrcListHelper::default();
+        let pending = helper.list_channels(NetworkId(1), vec![s!("#nothing")]);
+
+        helper.sync(receive(1));
+        assert_eq!(pending.try_take(), None);
+
+        helper.sync(SyncMessage {
+            slot_name: s!("reportFinishedList"),
+            params: vec![Variant::i32(1)],
+            ..receive(1)
+        });
+        assert_eq!(pending.try_take(), None);
+
+        // The network has no matching channels, the finished list is empty
+        helper.sync(receive(1));
+        assert_eq!(pending.wait(), Ok(vec![]));
+        assert_eq!(helper.pending_requests(), 0);
+
+        // The next list waits for its LIST to finish again
+        let pending = helper.list_channels(NetworkId(1), vec![s!("#nothing")]);
+        helper.sync(receive(1));
+        assert_eq!(pending.try_take(), None);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::{
+    hostmask::Hostmask,
     message::{Syncable, Class},
     primitive::{DateTime, StringList},
 };
@@ -9,7 +10,7 @@ use libquassel_derive::sync;
 use libquassel_derive::{NetworkMap, Setters};
 
 #[allow(dead_code)]
-#[derive(Debug, Clone, PartialEq, NetworkMap, Setters)]
+#[derive(Debug, Default, Clone, PartialEq, NetworkMap, Setters)]
 #[network(repr = "maplist")]
 pub struct IrcUser {
     pub user: String,
@@ -65,8 +66,22 @@ impl IrcUser {
         sync!("removeUserModes", [modes]);
     }
 
+    /// The hostmask of the user as `nick!user@host`
+    pub fn hostmask(&self) -> Hostmask {
+        Hostmask::new(&self.nick, &self.user, &self.host)
+    }
+
+    /// Update user and host from the given hostmask, the nick is ignored
     pub fn update_hostmask(&mut self, mask: String) {
+        let mask = Hostmask::parse(&mask);
+
+        if mask.user != self.user {
+            self.set_user(mask.user);
+        }
 
+        if mask.host != self.host {
+            self.set_host(mask.host);
+        }
     }
 
     pub fn join_channel(&mut self, channel: String) {
@@ -75,7 +90,7 @@ impl IrcUser {
         }
 
         #[cfg(feature = "server")]
-        sync!("partChannel", [channel]);
+        sync!("joinChannel", [channel]);
     }
 
     pub fn part_channel(&mut self, channel: String) {
@@ -87,11 +102,48 @@ impl IrcUser {
         sync!("partChannel", [channel]);
     }
 
-    pub fn quit(&mut self) {}
+    /// Leave all channels, the network removes the user afterwards
+    pub fn quit(&mut self) {
+        self.channels.clear();
+
+        #[cfg(feature = "server")]
+        sync!("quit", []);
+    }
 }
 
 #[cfg(feature = "client")]
-impl crate::message::StatefulSyncableClient for IrcUser {}
+impl crate::message::StatefulSyncableClient for IrcUser {
+    fn sync_custom(&mut self, mut msg: crate::message::SyncMessage)
+    where
+        Self: Sized,
+    {
+        match msg.slot_name.as_str() {
+            "addUserModes" => self.add_user_modes(get_param!(msg)),
+            "removeUserModes" => self.remove_user_modes(get_param!(msg)),
+            "joinChannel" => self.join_channel(get_param!(msg)),
+            "partChannel" => self.part_channel(get_param!(msg)),
+            "quit" => self.quit(),
+            "updateHostmask" => self.update_hostmask(get_param!(msg)),
+            "setUser" => self.set_user(get_param!(msg)),
+            "setHost" => self.set_host(get_param!(msg)),
+            "setNick" => self.set_nick(get_param!(msg)),
+            "setRealName" => self.set_real_name(get_param!(msg)),
+            "setAccount" => self.set_account(get_param!(msg)),
+            "setAway" => self.set_away(get_param!(msg)),
+            "setAwayMessage" => self.set_away_message(get_param!(msg)),
+            "setIdleTime" => self.set_idle_time(get_param!(msg)),
+            "setLoginTime" => self.set_login_time(get_param!(msg)),
+            "setServer" => self.set_server(get_param!(msg)),
+            "setIrcOperator" => self.set_irc_operator(get_param!(msg)),
+            "setLastAwayMessageTime" => self.set_last_away_message_time(get_param!(msg)),
+            "setWhoisServiceReply" => self.set_whois_service_reply(get_param!(msg)),
+            "setSuserHost" => self.set_suser_host(get_param!(msg)),
+            "setEncrypted" => self.set_encrypted(get_param!(msg)),
+            "setUserModes" => self.set_user_modes(get_param!(msg)),
+            _ => (),
+        }
+    }
+}
 
 #[cfg(feature = "server")]
 impl crate::message::StatefulSyncableServer for IrcUser {}
@@ -109,11 +161,24 @@ impl Syncable for IrcUser {
     }
 }
 
+/// Used when the IrcUser is initialized on its own instead of as part of its Network
+impl crate::message::NetworkList for IrcUser {
+    fn to_network_list(&self) -> crate::primitive::VariantList {
+        let map = crate::message::NetworkMap::to_network_map(self);
+        crate::message::signalproxy::maplist_to_list(map)
+    }
+
+    fn from_network_list(input: &mut crate::primitive::VariantList) -> Self {
+        crate::message::NetworkMap::from_network_map(
+            &mut crate::message::signalproxy::list_to_maplist(input),
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::message::signalproxy::NetworkMap;
     use crate::primitive::{Variant, VariantMap};
-    use time::OffsetDateTime;
 
     use super::*;
 
@@ -126,12 +191,12 @@ mod tests {
             account: s!(""),
             away: false,
             away_message: s!(""),
-            idle_time: OffsetDateTime::unix_epoch(),
-            login_time: OffsetDateTime::unix_epoch(),
+            idle_time: DateTime::null(),
+            login_time: DateTime::null(),
             server: s!(""),
             irc_operator: s!(""),
             // last_away_message: 0,
-            last_away_message_time: OffsetDateTime::unix_epoch(),
+            last_away_message_time: DateTime::null(),
             whois_service_reply: s!(""),
             suser_host: s!(""),
             encrypted: false,
@@ -152,7 +217,7 @@ mod tests {
             s!("lastAwayMessageTime") => Variant::VariantList(vec!
                 [
                     Variant::DateTime(
-                        OffsetDateTime::unix_epoch() ,
+                        DateTime::null(),
                     ),
                 ],
             ),
@@ -180,7 +245,7 @@ mod tests {
             s!("loginTime") => Variant::VariantList(vec!
                 [
                     Variant::DateTime(
-                        OffsetDateTime::unix_epoch()
+                        DateTime::null()
                     ),
                 ],
             ),
@@ -229,7 +294,7 @@ mod tests {
             s!("idleTime") => Variant::VariantList(vec!
                 [
                     Variant::DateTime(
-                        OffsetDateTime::unix_epoch()
+                        DateTime::null()
                     ),
                 ],
             ),
@@ -278,4 +343,28 @@ mod tests {
     fn vec_ircuser_to_network() {
         assert_eq!(get_runtime().to_network_map(), get_network())
     }
+
+    #[test]
+    fn ircuser_network_list() {
+        use crate::message::NetworkList;
+
+        let list = get_runtime().to_network_list();
+
+        assert_eq!(list[0], Variant::ByteArray(s!("account")));
+        assert_eq!(list[1], Variant::String(s!("")));
+        assert_eq!(IrcUser::from_network_list(&mut list.clone()), get_runtime())
+    }
+
+    #[test]
+    fn ircuser_update_hostmask() {
+        let mut user = get_runtime();
+        user.update_hostmask(s!("NickServ!~service@services.example.com"));
+
+        assert_eq!(user.user, "~service");
+        assert_eq!(user.host, "services.example.com");
+        assert_eq!(
+            user.hostmask().to_string(),
+            "NickServ!~service@services.example.com"
+        );
+    }
 }
@@ -1,6 +1,7 @@
 mod aliasmanager;
 mod buffersyncer;
 mod backlogmanager;
+mod backlogstore;
 mod bufferviewmanager;
 mod bufferviewconfig;
 mod certmanager;
@@ -9,6 +10,7 @@ mod highlightrulemanager;
 mod identity;
 mod ignorelistmanager;
 mod ircchannel;
+mod irclisthelper;
 mod chanmodes;
 mod ircuser;
 mod network;
@@ -17,6 +19,7 @@ mod networkinfo;
 pub use aliasmanager::*;
 pub use buffersyncer::*;
 pub use backlogmanager::*;
+pub use backlogstore::*;
 pub use bufferviewmanager::*;
 pub use bufferviewconfig::*;
 pub use certmanager::*;
@@ -25,6 +28,7 @@ pub use highlightrulemanager::*;
 pub use identity::*;
 pub use ignorelistmanager::*;
 pub use ircchannel::*;
+pub use irclisthelper::*;
 pub use chanmodes::*;
 pub use ircuser::*;
 pub use network::*;
@@ -47,30 +51,32 @@ use crate::primitive::VariantList;
 ///  - [X] CoreInfo
 ///  - [X] CoreData
 ///  - [X] HighlightRuleManager
-///  - [ ] Identity
+///  - [X] Identity
 ///  - [X] IgnoreListManager
-///  - [ ] IrcChannel
-///  - [ ] IrcListHelper
-///  - [ ] IrcUser
+///  - [X] IrcChannel
+///  - [X] IrcListHelper
+///  - [X] IrcUser
 ///  - [X] Network
 ///  - [X] NetworkInfo
 ///  - [X] NetworkConfig
-// TODO Handle SyncedCoreInfo feature flag
 #[derive(Debug, Clone, PartialEq, From)]
 pub enum Types {
     AliasManager(AliasManager),
     BufferSyncer(BufferSyncer),
     BufferViewConfig(BufferViewConfig),
     BufferViewManager(BufferViewManager),
-    // CoreInfo(CoreInfo),
+    CoreInfo(CoreInfo),
     CoreData(CoreData),
     HighlightRuleManager(HighlightRuleManager),
+    Identity(Identity),
     IgnoreListManager(IgnoreListManager),
     CertManager(CertManager),
     Network(network::Network),
     NetworkInfo(NetworkInfo),
     NetworkConfig(NetworkConfig),
-    // IrcChannel(IrcChannel),
+    IrcChannel(IrcChannel),
+    IrcListHelper(IrcListHelper),
+    IrcUser(IrcUser),
     Unknown(VariantList),
 }
 
@@ -82,15 +88,18 @@ impl Types {
             Types::BufferSyncer(val) => val.to_network_list(),
             Types::BufferViewConfig(val) => val.to_network_list(),
             Types::BufferViewManager(val) => val.to_network_list(),
-            // Types::CoreInfo(val) => vec![val.to_network_map().into()],
+            Types::CoreInfo(val) => val.to_network_list(),
             Types::CoreData(val) => vec![val.to_network_map().into()],
             Types::HighlightRuleManager(val) => val.to_network_list(),
+            Types::Identity(val) => val.to_network_list(),
             Types::IgnoreListManager(val) => val.to_network_list(),
             Types::CertManager(val) => val.to_network_list(),
             Types::Network(val) => val.to_network_list(),
             Types::NetworkInfo(val) => val.to_network_list(),
             Types::NetworkConfig(val) => val.to_network_list(),
-            // Types::IrcChannel(val) => todo!(),
+            Types::IrcChannel(val) => val.to_network_list(),
+            Types::IrcListHelper(val) => val.to_network_list(),
+            Types::IrcUser(val) => val.to_network_list(),
             Types::Unknown(val) => val.clone(),
         }
     }
@@ -111,15 +120,14 @@ impl Types {
             "BufferViewManager" => {
                 Types::BufferViewManager(BufferViewManager::from_network_list(input))
             }
-            // "CoreInfo" => Types::CoreInfo(CoreInfo::from_network_map(
-            //     &mut input.remove(0).try_into().unwrap(),
-            // )),
+            "CoreInfo" => Types::CoreInfo(CoreInfo::from_network_list(input)),
             "CoreData" => Types::CoreData(CoreData::from_network_map(
                 &mut input.remove(0).try_into().unwrap(),
             )),
             "HighlightRuleManager" => {
                 Types::HighlightRuleManager(HighlightRuleManager::from_network_list(input))
             }
+            "Identity" => Types::Identity(Identity::from_network_list(input)),
             "IgnoreListManager" => {
                 Types::IgnoreListManager(IgnoreListManager::from_network_list(input))
             }
@@ -127,7 +135,9 @@ impl Types {
             "Network" => Types::Network(Network::from_network_list(input)),
             "NetworkInfo" => Types::NetworkInfo(NetworkInfo::from_network_list(input)),
             "NetworkConfig" => Types::NetworkConfig(NetworkConfig::from_network_list(input)),
-            // "IrcChannel" => Types::IrcChannel(IrcChannel::from_network_list(input)),
+            "IrcChannel" => Types::IrcChannel(IrcChannel::from_network_list(input)),
+            "IrcListHelper" => Types::IrcListHelper(IrcListHelper::from_network_list(input)),
+            "IrcUser" => Types::IrcUser(IrcUser::from_network_list(input)),
             _ => Types::Unknown(input.to_owned()),
         }
     }
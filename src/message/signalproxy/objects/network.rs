@@ -5,8 +5,12 @@ use num_traits::FromPrimitive;
 
 use libquassel_derive::{NetworkList, NetworkMap};
 
+use log::warn;
+
+use crate::hostmask::Hostmask;
 use crate::message::signalproxy::translation::NetworkMap;
-use crate::primitive::{Variant, VariantList, VariantMap};
+use crate::message::{Class, Syncable};
+use crate::primitive::{StringList, Variant, VariantList, VariantMap};
 
 use super::{ircchannel::IrcChannel, ircuser::IrcUser, networkinfo::NetworkInfo};
 
@@ -29,6 +33,16 @@ pub struct Network {
 }
 
 impl Network {
+    /// Get an IrcUser by nick, IRC nicks are case insensitive
+    pub fn irc_user(&self, nick: &str) -> Option<&IrcUser> {
+        self.irc_users.get(nick).or_else(|| {
+            self.irc_users
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(nick))
+                .map(|(_, user)| user)
+        })
+    }
+
     pub fn get_channel_mode_type(&self, mode: char) -> ChannelModeType {
         if let Some((mode_type, _)) = self.channel_modes.iter().find(|(_, v)| v.contains(mode)) {
             *mode_type
@@ -39,30 +53,41 @@ impl Network {
 
     /// The `channel_modes` field is populated by the ``supports["CHANMODES"]` string,
     /// which is represented as the channel mode types a,b,c,d in a comma sepperated string.
+    ///
+    /// Missing types are left empty, additional types are ignored.
     fn determine_channel_mode_types(&mut self) {
-        let mut modes: Vec<&str> = self.supports.get("CHANMODES").unwrap().split(',').collect();
-
-        self.channel_modes.insert(ChannelModeType::DChanmode, modes.pop().unwrap().to_owned());
-        self.channel_modes.insert(ChannelModeType::CChanmode, modes.pop().unwrap().to_owned());
-        self.channel_modes.insert(ChannelModeType::BChanmode, modes.pop().unwrap().to_owned());
-        self.channel_modes.insert(ChannelModeType::AChanmode, modes.pop().unwrap().to_owned());
+        let chanmodes = self.supports.get("CHANMODES").cloned().unwrap_or_default();
+        let mut modes = chanmodes.split(',');
+
+        for mode_type in [
+            ChannelModeType::AChanmode,
+            ChannelModeType::BChanmode,
+            ChannelModeType::CChanmode,
+            ChannelModeType::DChanmode,
+        ] {
+            self.channel_modes
+                .insert(mode_type, modes.next().unwrap_or_default().to_owned());
+        }
     }
 
     fn determine_prefixes(&mut self) {
         let default_prefixes = vec!['~', '&', '@', '%', '+'];
         let default_prefix_modes = vec!['q', 'a', 'o', 'h', 'v'];
 
-        match self.supports.get("PREFIX") {
-            Some(prefix) => {
-                if prefix.starts_with('(') {
-                    let (prefix_modes, prefixes) = prefix[1..].split_once(')').unwrap();
-
-                    self.prefix_modes = prefix_modes.chars().collect();
-                    self.prefixes = prefixes.chars().collect();
-                } else {
-                    self.prefixes = default_prefixes;
-                    self.prefix_modes = default_prefix_modes;
-                }
+        // Like quassel, fall back to the defaults if the server sends something malformed
+        let prefix = self
+            .supports
+            .get("PREFIX")
+            .and_then(|prefix| prefix.strip_prefix('('))
+            .and_then(|prefix| prefix.split_once(')'))
+            .filter(|(prefix_modes, prefixes)| {
+                prefix_modes.chars().count() == prefixes.chars().count()
+            });
+
+        match prefix {
+            Some((prefix_modes, prefixes)) => {
+                self.prefix_modes = prefix_modes.chars().collect();
+                self.prefixes = prefixes.chars().collect();
             },
             None => {
                 self.prefixes = default_prefixes;
@@ -72,6 +97,490 @@ impl Network {
     }
 }
 
+impl Network {
+    pub fn set_my_nick(&mut self, nick: String) {
+        if !nick.is_empty() && self.irc_user(&nick).is_none() {
+            self.add_irc_user(nick.clone());
+        }
+
+        self.my_nick = nick;
+    }
+
+    pub fn set_latency(&mut self, latency: i32) {
+        self.latency = latency;
+    }
+
+    pub fn set_current_server(&mut self, server: String) {
+        self.current_server = server;
+    }
+
+    /// On disconnect the nick, server and all users and channels are cleared
+    pub fn set_connected(&mut self, connected: bool) {
+        self.is_connected = connected;
+
+        if !connected {
+            self.my_nick.clear();
+            self.current_server.clear();
+            self.irc_users.clear();
+            self.irc_channels.clear();
+        }
+    }
+
+    pub fn set_connection_state(&mut self, state: i32) {
+        match ConnectionState::from_i32(state) {
+            Some(state) => self.connection_state = state,
+            None => warn!("unknown connection state {}", state),
+        }
+    }
+
+    /// Add a user from a `nick!user@host` mask, updates user and host if the nick is already known
+    pub fn add_irc_user(&mut self, hostmask: String) {
+        let mask = Hostmask::parse(&hostmask);
+
+        let existing = self
+            .irc_users
+            .keys()
+            .find(|nick| nick.eq_ignore_ascii_case(&mask.nick))
+            .cloned();
+
+        match existing {
+            Some(nick) => {
+                if let Some(user) = self.irc_users.get_mut(&nick) {
+                    user.update_hostmask(hostmask);
+                }
+            }
+            None => {
+                let user = IrcUser {
+                    nick: mask.nick.clone(),
+                    user: mask.user,
+                    host: mask.host,
+                    ..Default::default()
+                };
+
+                self.irc_users.insert(mask.nick, user);
+            }
+        }
+    }
+
+    /// The key of a user in `irc_users`, IRC nicks are case insensitive
+    fn irc_user_key(&self, nick: &str) -> Option<String> {
+        if self.irc_users.contains_key(nick) {
+            return Some(nick.to_owned());
+        }
+
+        self.irc_users
+            .keys()
+            .find(|key| key.eq_ignore_ascii_case(nick))
+            .cloned()
+    }
+
+    /// Change the nick of a user, moving it to the new key in `irc_users`
+    /// and in the user modes of every channel
+    pub fn rename_irc_user(&mut self, old_nick: &str, new_nick: String) {
+        let key = match self.irc_user_key(old_nick) {
+            Some(key) => key,
+            None => {
+                warn!("tried to rename unknown user {}", old_nick);
+                return;
+            }
+        };
+
+        if let Some(mut user) = self.irc_users.remove(&key) {
+            user.nick = new_nick.clone();
+            self.irc_users.insert(new_nick.clone(), user);
+        }
+
+        for channel in self.irc_channels.values_mut() {
+            if let Some(modes) = channel.user_modes.remove(&key) {
+                channel.user_modes.insert(new_nick.clone(), modes);
+            }
+        }
+    }
+
+    /// Remove a user from the network and from every channel it is in
+    pub fn remove_irc_user(&mut self, nick: &str) {
+        if let Some(key) = self.irc_user_key(nick) {
+            self.irc_users.remove(&key);
+
+            for channel in self.irc_channels.values_mut() {
+                channel.user_modes.remove(&key);
+            }
+        }
+    }
+
+    /// Apply a sync message for the IrcUser with the given nick
+    ///
+    /// Slots changing the nick or the channels of the user are also applied
+    /// to `irc_users` and the joined channels. Returns `false` if the user is not known.
+    pub fn sync_irc_user(&mut self, nick: &str, mut msg: crate::message::SyncMessage) -> bool {
+        #[cfg(feature = "client")]
+        use crate::message::StatefulSyncableClient;
+        #[cfg(feature = "server")]
+        use crate::message::StatefulSyncableServer;
+
+        let key = match self.irc_user_key(nick) {
+            Some(key) => key,
+            None => {
+                warn!("Could not find IrcUser {}", nick);
+                return false;
+            }
+        };
+
+        match msg.slot_name.as_str() {
+            "setNick" => self.rename_irc_user(&key, get_param!(msg)),
+            "quit" => self.remove_irc_user(&key),
+            "joinChannel" => {
+                let channel: String = get_param!(msg);
+
+                self.add_irc_channel(channel.clone());
+                if let Some(irc_channel) = self.irc_channels.get_mut(&channel) {
+                    irc_channel.user_modes.entry(key.clone()).or_default();
+                }
+
+                if let Some(user) = self.irc_users.get_mut(&key) {
+                    user.join_channel(channel);
+                }
+            }
+            "partChannel" => {
+                let channel: String = get_param!(msg);
+                self.part_irc_channel(&key, &channel);
+            }
+            _ => {
+                if let Some(user) = self.irc_users.get_mut(&key) {
+                    user.sync(msg)
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Join users to a channel, users that are not known yet are added to the network
+    ///
+    /// The channel keeps the users by their key in `irc_users`,
+    /// no matter which casing of the nick the core sent.
+    pub fn join_irc_users(&mut self, channel: &str, nicks: StringList, modes: StringList) {
+        if !self.irc_channels.contains_key(channel) {
+            warn!("Could not find IrcChannel {}", channel);
+            return;
+        }
+
+        let keys: StringList = nicks
+            .into_iter()
+            .map(|nick| match self.irc_user_key(&nick) {
+                Some(key) => key,
+                None => {
+                    self.add_irc_user(nick.clone());
+                    nick
+                }
+            })
+            .collect();
+
+        for key in &keys {
+            if let Some(user) = self.irc_users.get_mut(key) {
+                user.join_channel(channel.to_owned());
+            }
+        }
+
+        if let Some(irc_channel) = self.irc_channels.get_mut(channel) {
+            irc_channel.join_irc_users(keys, modes);
+        }
+    }
+
+    /// Remove a user from a channel
+    ///
+    /// Like quassel, a user that is left without channels is removed from the network unless it is us.
+    /// If we left the channel it is removed and parted by all of its users.
+    pub fn part_irc_channel(&mut self, nick: &str, channel: &str) {
+        let is_me = nick.eq_ignore_ascii_case(&self.my_nick);
+
+        let mut nicks = vec![nick.to_owned()];
+        if is_me {
+            if let Some(irc_channel) = self.irc_channels.remove(channel) {
+                nicks.extend(
+                    irc_channel
+                        .user_modes
+                        .into_keys()
+                        .filter(|key| !key.eq_ignore_ascii_case(nick)),
+                );
+            }
+        } else if let Some(irc_channel) = self.irc_channels.get_mut(channel) {
+            irc_channel
+                .user_modes
+                .retain(|key, _| !key.eq_ignore_ascii_case(nick));
+        }
+
+        for nick in nicks {
+            let key = match self.irc_user_key(&nick) {
+                Some(key) => key,
+                None => continue,
+            };
+
+            let mut parted_all = false;
+            if let Some(user) = self.irc_users.get_mut(&key) {
+                user.part_channel(channel.to_owned());
+                parted_all = user.channels.is_empty();
+            }
+
+            // Users we share no channel with are no longer known
+            if parted_all && !key.eq_ignore_ascii_case(&self.my_nick) {
+                self.remove_irc_user(&key);
+            }
+        }
+    }
+
+    pub fn add_irc_channel(&mut self, name: String) {
+        self.irc_channels
+            .entry(name.clone())
+            .or_insert_with(|| IrcChannel {
+                name,
+                ..Default::default()
+            });
+    }
+
+    /// Add or replace a user with the InitData received for it on its own
+    pub fn init_irc_user(&mut self, nick: &str, mut user: IrcUser) {
+        if user.nick.is_empty() {
+            user.nick = nick.to_owned();
+        }
+
+        let key = self.irc_user_key(nick).unwrap_or_else(|| user.nick.clone());
+        self.irc_users.insert(key, user);
+    }
+
+    /// Add or replace a channel with the InitData received for it on its own,
+    /// known users of the channel are joined to it
+    pub fn init_irc_channel(&mut self, name: &str, mut channel: IrcChannel) {
+        if channel.name.is_empty() {
+            channel.name = name.to_owned();
+        }
+
+        for nick in channel.user_modes.keys() {
+            if let Some(key) = self.irc_user_key(nick) {
+                if let Some(user) = self.irc_users.get_mut(&key) {
+                    user.join_channel(channel.name.clone());
+                }
+            }
+        }
+
+        self.irc_channels.insert(channel.name.clone(), channel);
+    }
+
+    /// Add or replace a value of the ISUPPORT reply, `PREFIX` and `CHANMODES` update the known modes
+    pub fn add_support(&mut self, param: String, value: String) {
+        self.supports.insert(param.clone(), value);
+        self.update_modes(&param);
+    }
+
+    pub fn remove_support(&mut self, param: String) {
+        self.supports.remove(&param);
+        self.update_modes(&param);
+    }
+
+    fn update_modes(&mut self, param: &str) {
+        match param {
+            "PREFIX" => self.determine_prefixes(),
+            "CHANMODES" => self.determine_channel_mode_types(),
+            _ => (),
+        }
+    }
+
+    /// Add a capability offered by the server
+    pub fn add_cap(&mut self, cap: String, value: String) {
+        self.caps.insert(cap.to_lowercase(), value);
+    }
+
+    /// Mark a capability as enabled after the server acknowledged it
+    pub fn acknowledge_cap(&mut self, cap: String) {
+        let cap = cap.to_lowercase();
+
+        if !self.caps_enabled.contains(&cap) {
+            self.caps_enabled.push(cap);
+        }
+    }
+
+    pub fn remove_cap(&mut self, cap: String) {
+        let cap = cap.to_lowercase();
+
+        self.caps.remove(&cap);
+        self.caps_enabled.retain(|enabled| *enabled != cap);
+    }
+
+    pub fn clear_caps(&mut self) {
+        self.caps.clear();
+        self.caps_enabled.clear();
+    }
+}
+
+#[cfg(feature = "client")]
+impl crate::message::StatefulSyncableClient for Network {
+    fn sync_custom(&mut self, mut msg: crate::message::SyncMessage)
+    where
+        Self: Sized,
+    {
+        match msg.slot_name.as_str() {
+            "setMyNick" => self.set_my_nick(get_param!(msg)),
+            "setLatency" => self.set_latency(get_param!(msg)),
+            "setCurrentServer" => self.set_current_server(get_param!(msg)),
+            "setConnected" => self.set_connected(get_param!(msg)),
+            "setConnectionState" => self.set_connection_state(get_param!(msg)),
+            "addIrcUser" => self.add_irc_user(get_param!(msg)),
+            "addIrcChannel" => self.add_irc_channel(get_param!(msg)),
+            "addSupport" => self.add_support(get_param!(msg), get_param!(msg)),
+            "removeSupport" => self.remove_support(get_param!(msg)),
+            "addCap" => self.add_cap(get_param!(msg), get_param!(msg)),
+            "acknowledgeCap" => self.acknowledge_cap(get_param!(msg)),
+            "removeCap" => self.remove_cap(get_param!(msg)),
+            "clearCaps" => self.clear_caps(),
+            "setNetworkInfo" => {
+                let network_id = self.network_info.network_id;
+                self.network_info = NetworkInfo::from_network_map(&mut get_param!(msg));
+                self.network_info.network_id = network_id;
+            }
+            "setIdentity" => self.network_info.identity_id = get_param!(msg),
+            "setNetworkName" => self.network_info.network_name = get_param!(msg),
+            "setServerList" => {
+                let servers: VariantList = get_param!(msg);
+
+                self.network_info.server_list = servers
+                    .into_iter()
+                    .filter_map(|server| match server {
+                        Variant::VariantMap(mut server) => {
+                            Some(NetworkServer::from_network_map(&mut server))
+                        }
+                        server => {
+                            warn!("ignoring malformed server {:?}", server);
+                            None
+                        }
+                    })
+                    .collect();
+            }
+            "setPerform" => self.network_info.perform = get_param!(msg),
+            "setSkipCaps" => self.network_info.skip_caps = get_param!(msg),
+            "setUseAutoIdentify" => self.network_info.use_auto_identify = get_param!(msg),
+            "setAutoIdentifyService" => self.network_info.auto_identify_service = get_param!(msg),
+            "setAutoIdentifyPassword" => self.network_info.auto_identify_password = get_param!(msg),
+            "setUseSasl" => self.network_info.use_sasl = get_param!(msg),
+            "setSaslAccount" => self.network_info.sasl_account = get_param!(msg),
+            "setSaslPassword" => self.network_info.sasl_password = get_param!(msg),
+            "setUseAutoReconnect" => self.network_info.use_auto_reconnect = get_param!(msg),
+            "setAutoReconnectInterval" => {
+                self.network_info.auto_reconnect_interval = get_param!(msg)
+            }
+            "setAutoReconnectRetries" => self.network_info.auto_reconnect_retries = get_param!(msg),
+            "setUnlimitedReconnectRetries" => {
+                self.network_info.unlimited_reconnect_retries = get_param!(msg)
+            }
+            "setRejoinChannels" => self.network_info.rejoin_channels = get_param!(msg),
+            "setUseRandomServer" => self.network_info.use_random_server = get_param!(msg),
+            "setUseCustomMessageRate" => {
+                self.network_info.use_custom_message_rate = get_param!(msg)
+            }
+            "setMessageRateBurstSize" => self.network_info.msg_rate_burst_size = get_param!(msg),
+            "setMessageRateDelay" => self.network_info.msg_rate_message_delay = get_param!(msg),
+            "setUnlimitedMessageRate" => self.network_info.unlimited_message_rate = get_param!(msg),
+            "setCodecForServer" => self.network_info.codec_for_server = get_param!(msg),
+            "setCodecForEncoding" => self.network_info.codec_for_encoding = get_param!(msg),
+            "setCodecForDecoding" => self.network_info.codec_for_decoding = get_param!(msg),
+            _ => (),
+        }
+    }
+
+    /// Not Implemented for this type
+    fn request_update(&mut self)
+    where
+        Self: Sized,
+    {
+    }
+}
+
+#[cfg(feature = "server")]
+impl crate::message::StatefulSyncableServer for Network {
+    /// Not Implemented for this type
+    fn request_update(&mut self, _param: <Network as NetworkMap>::Item)
+    where
+        Self: Sized,
+    {
+    }
+}
+
+impl Syncable for Network {
+    const CLASS: Class = Class::Network;
+
+    fn send_sync(&self, function: &str, params: VariantList) {
+        crate::message::signalproxy::SYNC_PROXY.get().unwrap().sync(
+            Self::CLASS,
+            Some(&self.network_info.network_id.0.to_string()),
+            function,
+            params,
+        );
+    }
+}
+
+#[cfg(feature = "client")]
+impl Network {
+    /// Client -> Server: Connect to the network
+    pub fn request_connect(&self) {
+        self.send_sync("requestConnect", vec![]);
+    }
+
+    /// Client -> Server: Disconnect from the network
+    pub fn request_disconnect(&self) {
+        self.send_sync("requestDisconnect", vec![]);
+    }
+
+    /// Client -> Server: Replace the settings of the network
+    ///
+    /// The core applies the settings and syncs the changed fields back.
+    pub fn request_set_network_info(&self, info: &NetworkInfo) -> Result<(), failure::Error> {
+        let info = NetworkInfo {
+            network_id: self.network_info.network_id,
+            ..info.clone()
+        };
+
+        self.send_sync("requestSetNetworkInfo", vec![info.to_user_type()?]);
+
+        Ok(())
+    }
+
+    /// Client -> Server: Delete the network, the core answers with a `networkRemoved` RPC
+    pub fn remove_network(&self) {
+        self.send_rpc(crate::message::RpcCall::RemoveNetwork(
+            crate::message::RemoveNetwork {
+                network_id: self.network_info.network_id,
+            },
+        ));
+    }
+}
+
+/// The network is represented as a map of the same properties as [`NetworkList`](crate::message::signalproxy::NetworkList)
+impl NetworkMap for Network {
+    type Item = VariantMap;
+
+    fn to_network_map(&self) -> Self::Item {
+        use crate::message::signalproxy::NetworkList;
+
+        self.to_network_list()
+            .chunks(2)
+            .filter_map(|pair| match pair {
+                [Variant::ByteArray(key), value] => Some((key.clone(), value.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn from_network_map(input: &mut Self::Item) -> Self {
+        use crate::message::signalproxy::NetworkList;
+
+        let mut list: VariantList = input
+            .drain()
+            .flat_map(|(key, value)| [Variant::ByteArray(key), value])
+            .collect();
+
+        Self::from_network_list(&mut list)
+    }
+}
+
 impl crate::message::signalproxy::NetworkList for Network {
     fn to_network_list(&self) -> VariantList {
         let mut res = VariantList::new();
@@ -143,6 +652,11 @@ impl crate::message::signalproxy::NetworkList for Network {
 
         res.extend(self.network_info.to_network_list());
 
+        res.push(Variant::ByteArray(s!("identityId")));
+        res.push(self.network_info.identity_id.into());
+        res.push(Variant::ByteArray(s!("skipCaps")));
+        res.push(Variant::StringList(self.network_info.skip_caps.clone()));
+
         res
     }
 
@@ -252,6 +766,22 @@ impl crate::message::signalproxy::NetworkList for Network {
             network_info: NetworkInfo::from_network_list(input),
         };
 
+        // Not part of the NetworkInfo derive, the identity is sent as UserType
+        // and skipCaps is missing from older cores
+        let get = |key: &str| {
+            input.chunks(2).find_map(|pair| match pair {
+                [Variant::ByteArray(name), value] if name == key => Some(value.clone()),
+                _ => None,
+            })
+        };
+
+        if let Some(Ok(identity_id)) = get("identityId").map(TryInto::try_into) {
+            network.network_info.identity_id = identity_id;
+        }
+        if let Some(Variant::StringList(skip_caps)) = get("skipCaps") {
+            network.network_info.skip_caps = skip_caps;
+        }
+
         network.determine_channel_mode_types();
         network.determine_prefixes();
 
@@ -287,24 +817,132 @@ pub struct NetworkServer {
     pub proxy_pass: String,
 }
 
-#[derive(Debug, Clone, PartialEq, NetworkList)]
+impl NetworkServer {
+    /// Start building a server entry with the defaults of quassel
+    pub fn builder(host: &str, port: u32) -> NetworkServerBuilder {
+        NetworkServerBuilder::new(host, port)
+    }
+}
+
+/// Builder for a [`NetworkServer`]
+#[derive(Debug, Clone)]
+pub struct NetworkServerBuilder {
+    server: NetworkServer,
+}
+
+impl NetworkServerBuilder {
+    /// Creates a new builder with the defaults of quassel
+    pub fn new(host: &str, port: u32) -> NetworkServerBuilder {
+        NetworkServerBuilder {
+            server: NetworkServer {
+                host: host.to_string(),
+                port,
+                password: String::new(),
+                use_ssl: false,
+                ssl_verify: true,
+                ssl_version: 0,
+                use_proxy: false,
+                proxy_type: 1,
+                proxy_host: s!("localhost"),
+                proxy_port: 8080,
+                proxy_user: String::new(),
+                proxy_pass: String::new(),
+            },
+        }
+    }
+
+    pub fn password(&mut self, password: &str) -> &mut Self {
+        self.server.password = password.to_string();
+        self
+    }
+
+    /// Enables or disables TLS
+    pub fn ssl(&mut self, val: bool) -> &mut Self {
+        self.server.use_ssl = val;
+        self
+    }
+
+    /// Enables or disables verification of the server certificate
+    pub fn ssl_verify(&mut self, val: bool) -> &mut Self {
+        self.server.ssl_verify = val;
+        self
+    }
+
+    /// Connects through a proxy, `proxy_type` is the `QNetworkProxy::ProxyType`
+    pub fn proxy(&mut self, proxy_type: i32, host: &str, port: u32) -> &mut Self {
+        self.server.use_proxy = true;
+        self.server.proxy_type = proxy_type;
+        self.server.proxy_host = host.to_string();
+        self.server.proxy_port = port;
+        self
+    }
+
+    pub fn proxy_auth(&mut self, user: &str, pass: &str) -> &mut Self {
+        self.server.proxy_user = user.to_string();
+        self.server.proxy_pass = pass.to_string();
+        self
+    }
+
+    /// Create the configured `NetworkServer`
+    pub fn build(&self) -> NetworkServer {
+        self.server.clone()
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, NetworkList, NetworkMap)]
 pub struct NetworkConfig {
     #[network(rename = "pingTimeoutEnabled")]
-    ping_timeout_enabled: bool,
+    pub ping_timeout_enabled: bool,
     #[network(rename = "pingInterval")]
-    ping_interval: i32,
+    pub ping_interval: i32,
     #[network(rename = "maxPingCount")]
-    max_ping_count: i32,
+    pub max_ping_count: i32,
     #[network(rename = "autoWhoEnabled")]
-    auto_who_enabled: bool,
+    pub auto_who_enabled: bool,
     #[network(rename = "autoWhoInterval")]
-    auto_who_interval: i32,
+    pub auto_who_interval: i32,
     #[network(rename = "autoWhoNickLimit")]
-    auto_who_nick_limit: i32,
+    pub auto_who_nick_limit: i32,
     #[network(rename = "autoWhoDelay")]
-    auto_who_delay: i32,
+    pub auto_who_delay: i32,
     #[network(rename = "standardCtcp")]
-    standard_ctcp: bool,
+    pub standard_ctcp: bool,
+}
+
+#[cfg(feature = "client")]
+impl crate::message::StatefulSyncableClient for NetworkConfig {
+    fn sync_custom(&mut self, mut msg: crate::message::SyncMessage)
+    where
+        Self: Sized,
+    {
+        match msg.slot_name.as_str() {
+            "setPingTimeoutEnabled" => self.ping_timeout_enabled = get_param!(msg),
+            "setPingInterval" => self.ping_interval = get_param!(msg),
+            "setMaxPingCount" => self.max_ping_count = get_param!(msg),
+            "setAutoWhoEnabled" => self.auto_who_enabled = get_param!(msg),
+            "setAutoWhoInterval" => self.auto_who_interval = get_param!(msg),
+            "setAutoWhoNickLimit" => self.auto_who_nick_limit = get_param!(msg),
+            "setAutoWhoDelay" => self.auto_who_delay = get_param!(msg),
+            "setStandardCtcp" => self.standard_ctcp = get_param!(msg),
+            _ => (),
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+impl crate::message::StatefulSyncableServer for NetworkConfig {}
+
+impl Syncable for NetworkConfig {
+    const CLASS: Class = Class::NetworkConfig;
+
+    fn send_sync(&self, function: &str, params: VariantList) {
+        crate::message::signalproxy::SYNC_PROXY.get().unwrap().sync(
+            Self::CLASS,
+            Some("GlobalNetworkConfig"),
+            function,
+            params,
+        );
+    }
 }
 
 #[cfg(test)]
@@ -376,6 +1014,36 @@ mod tests {
         )
     }
 
+    #[test]
+    fn network_info_roundtrip() {
+        use crate::message::signalproxy::NetworkList;
+        use crate::primitive::IdentityId;
+        use crate::{deserialize::Deserialize, serialize::Serialize};
+
+        let network = Network {
+            network_info: NetworkInfo {
+                identity_id: IdentityId(2),
+                skip_caps: vec![s!("away-notify"), s!("chghost")],
+                ..NetworkInfo::builder("snoonet").build()
+            },
+            ..Default::default()
+        };
+
+        let bytes = network.to_network_list().serialize().unwrap();
+        let (_, mut list) = VariantList::parse(&bytes).unwrap();
+
+        // Empty user and channel maps can not be parsed, they are not under test here
+        let users_and_channels = list
+            .iter()
+            .position(|x| *x == Variant::ByteArray(s!("IrcUsersAndChannels")))
+            .unwrap();
+        list[users_and_channels + 1] = Variant::VariantMap(VariantMap::new());
+
+        let parsed = Network::from_network_list(&mut list);
+
+        assert_eq!(parsed.network_info, network.network_info);
+    }
+
     #[test]
     fn network_determine_channel_modes() {
         let mut network = Network::default();
@@ -418,6 +1086,241 @@ mod tests {
 
         assert_eq!(network.prefixes, vec!['!', '@', '%', '+']);
         assert_eq!(network.prefix_modes, vec!['Y', 'o', 'h', 'v']);
+
+        for malformed in ["(ov", "(ov)@", "@+"] {
+            network.supports.insert(s!("PREFIX"), s!(malformed));
+            network.determine_prefixes();
+
+            assert_eq!(network.prefixes, vec!['~', '&', '@', '%', '+']);
+            assert_eq!(network.prefix_modes, vec!['q', 'a', 'o', 'h', 'v']);
+        }
+    }
+
+    fn sync_message(slot_name: &str, params: VariantList) -> crate::message::SyncMessage {
+        crate::message::SyncMessage {
+            class_name: Class::Network,
+            object_name: s!("1"),
+            slot_name: s!(slot_name),
+            params,
+        }
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn network_sync() {
+        use crate::message::StatefulSyncableClient;
+
+        let mut network = Network::default();
+
+        network.sync(sync_message(
+            "setMyNick",
+            vec![Variant::String(s!("audron"))],
+        ));
+        network.sync(sync_message("setLatency", vec![Variant::i32(42)]));
+        network.sync(sync_message("setConnectionState", vec![Variant::i32(3)]));
+        network.sync(sync_message(
+            "addIrcUser",
+            vec![Variant::String(s!("Audron!~audron@example.com"))],
+        ));
+        network.sync(sync_message(
+            "addIrcChannel",
+            vec![Variant::String(s!("#test"))],
+        ));
+
+        assert_eq!(network.my_nick, "audron");
+        assert_eq!(network.latency, 42);
+        assert_eq!(network.connection_state, ConnectionState::Initialized);
+        assert_eq!(network.irc_users.len(), 1);
+        assert_eq!(network.irc_user("audron").unwrap().host, "example.com");
+        assert_eq!(network.irc_channels.get("#test").unwrap().name, "#test");
+
+        network.sync(sync_message(
+            "addSupport",
+            vec![Variant::String(s!("PREFIX")), Variant::String(s!("(ov)@+"))],
+        ));
+        network.sync(sync_message(
+            "addSupport",
+            vec![
+                Variant::String(s!("CHANMODES")),
+                Variant::String(s!("b,k,l,imnt")),
+            ],
+        ));
+
+        assert_eq!(network.prefixes, vec!['@', '+']);
+        assert_eq!(
+            network.get_channel_mode_type('l'),
+            ChannelModeType::CChanmode
+        );
+
+        network.sync(sync_message(
+            "removeSupport",
+            vec![Variant::String(s!("CHANMODES"))],
+        ));
+
+        assert_eq!(
+            network.get_channel_mode_type('l'),
+            ChannelModeType::NotAChanmode
+        );
+
+        network.sync(sync_message(
+            "addCap",
+            vec![Variant::String(s!("SASL")), Variant::String(s!("PLAIN"))],
+        ));
+        network.sync(sync_message(
+            "acknowledgeCap",
+            vec![Variant::String(s!("sasl"))],
+        ));
+
+        assert_eq!(network.caps.get("sasl").unwrap(), "PLAIN");
+        assert_eq!(network.caps_enabled, vec![s!("sasl")]);
+
+        network.sync(sync_message("removeCap", vec![Variant::String(s!("sasl"))]));
+
+        assert!(network.caps.is_empty());
+        assert!(network.caps_enabled.is_empty());
+
+        network.sync(sync_message(
+            "setServerList",
+            vec![Variant::VariantList(vec![Variant::String(s!(
+                "irc.example.com"
+            ))])],
+        ));
+
+        assert!(network.network_info.server_list.is_empty());
+
+        network.sync(sync_message("setConnected", vec![Variant::bool(false)]));
+
+        assert!(network.my_nick.is_empty());
+        assert!(network.irc_users.is_empty());
+        assert!(network.irc_channels.is_empty());
+    }
+
+    #[test]
+    fn network_sync_irc_user() {
+        let mut network = Network::default();
+        network.set_my_nick(s!("audron"));
+        network.add_irc_user(s!("Kitty!kitty@example.com"));
+
+        let user_message = |slot_name: &str, params: VariantList| crate::message::SyncMessage {
+            class_name: Class::IrcUser,
+            object_name: s!("1/kitty"),
+            slot_name: s!(slot_name),
+            params,
+        };
+
+        for channel in ["#test", "#rust"] {
+            network.sync_irc_user(
+                "kitty",
+                user_message("joinChannel", vec![Variant::String(s!(channel))]),
+            );
+        }
+        network.sync_irc_user("kitty", user_message("setAway", vec![Variant::bool(true)]));
+
+        assert!(network.irc_user("Kitty").unwrap().away);
+        assert_eq!(
+            network.irc_user("Kitty").unwrap().channels,
+            vec![s!("#test"), s!("#rust")]
+        );
+        assert!(network.irc_channels["#test"]
+            .user_modes
+            .contains_key("Kitty"));
+
+        network.sync_irc_user(
+            "Kitty",
+            user_message("setNick", vec![Variant::String(s!("Cat"))]),
+        );
+
+        assert!(!network.irc_users.contains_key("Kitty"));
+        assert_eq!(network.irc_users["Cat"].nick, "Cat");
+        assert!(network.irc_channels["#test"].user_modes.contains_key("Cat"));
+        assert!(network.irc_channels["#rust"].user_modes.contains_key("Cat"));
+
+        network.sync_irc_user(
+            "cat",
+            user_message("partChannel", vec![Variant::String(s!("#test"))]),
+        );
+
+        assert!(!network.irc_channels["#test"].user_modes.contains_key("Cat"));
+        assert_eq!(network.irc_users["Cat"].channels, vec![s!("#rust")]);
+
+        network.sync_irc_user("Cat", user_message("quit", vec![]));
+
+        assert!(network.irc_user("Cat").is_none());
+        assert!(network.irc_channels["#rust"].user_modes.is_empty());
+        assert!(network.irc_user("audron").is_some());
+    }
+
+    #[test]
+    fn network_join_part_irc_channel() {
+        let mut network = Network::default();
+        network.set_my_nick(s!("audron"));
+        network.add_irc_user(s!("Kitty!kitty@example.com"));
+        network.add_irc_channel(s!("#test"));
+        network.add_irc_channel(s!("#rust"));
+
+        network.join_irc_users(
+            "#test",
+            vec![s!("audron"), s!("kitty"), s!("Dog")],
+            vec![s!("o"), s!(""), s!("")],
+        );
+        network.join_irc_users(
+            "#rust",
+            vec![s!("audron"), s!("KITTY")],
+            vec![s!(""), s!("v")],
+        );
+
+        // Joins are case insensitive and unknown users are created
+        assert_eq!(network.irc_users.len(), 3);
+        assert_eq!(
+            network.irc_user("kitty").unwrap().channels,
+            vec![s!("#test"), s!("#rust")]
+        );
+        assert_eq!(network.irc_user("dog").unwrap().channels, vec![s!("#test")]);
+
+        network.part_irc_channel("kitty", "#test");
+
+        assert!(!network.irc_channels["#test"]
+            .user_modes
+            .contains_key("kitty"));
+        assert_eq!(
+            network.irc_user("Kitty").unwrap().channels,
+            vec![s!("#rust")]
+        );
+
+        // Leaving a channel ourselves drops it and the users only known from it
+        network.part_irc_channel("audron", "#test");
+
+        assert!(!network.irc_channels.contains_key("#test"));
+        assert!(network.irc_user("Dog").is_none());
+        assert!(network.irc_user("Kitty").is_some());
+        assert_eq!(
+            network.irc_user("audron").unwrap().channels,
+            vec![s!("#rust")]
+        );
+
+        network.part_irc_channel("audron", "#rust");
+
+        assert!(network.irc_channels.is_empty());
+        assert_eq!(network.irc_users.len(), 1);
+        assert!(network.irc_user("audron").unwrap().channels.is_empty());
+
+        // Users joined with a different casing are kept by their known nick
+        network.add_irc_user(s!("Kitty!kitty@example.com"));
+        network.add_irc_channel(s!("#test"));
+        network.join_irc_users("#test", vec![s!("KITTY")], vec![s!("v")]);
+        assert_eq!(network.irc_channels["#test"].user_modes["Kitty"], "v");
+
+        network.rename_irc_user("kitty", s!("Cat"));
+        assert_eq!(
+            network.irc_channels["#test"]
+                .user_modes
+                .keys()
+                .collect::<Vec<_>>(),
+            vec!["Cat"]
+        );
+
+        network.remove_irc_user("CAT");
+        assert!(network.irc_channels["#test"].user_modes.is_empty());
     }
 }
 
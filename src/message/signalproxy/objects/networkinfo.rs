@@ -1,4 +1,6 @@
-use crate::primitive::StringList;
+use crate::message::{Class, NetworkMap, Syncable};
+use crate::primitive::{IdentityId, NetworkId, StringList, Variant, VariantList, VariantMap};
+use crate::serialize::Serialize;
 
 use libquassel_derive::NetworkList;
 
@@ -6,6 +8,15 @@ use crate::message::objects::network::NetworkServer;
 
 #[derive(Default, Debug, Clone, PartialEq, NetworkList)]
 pub struct NetworkInfo {
+    /// Not part of the network InitData, set from the object name
+    #[network(skip, default)]
+    pub network_id: NetworkId,
+    /// Part of the network InitData as `identityId`, handled by [`Network`] as it is sent as a UserType
+    ///
+    /// [`Network`]: crate::message::objects::Network
+    #[network(skip, default)]
+    pub identity_id: IdentityId,
+
     #[network(rename = "networkName")]
     pub network_name: String,
 
@@ -13,6 +24,11 @@ pub struct NetworkInfo {
     pub server_list: Vec<NetworkServer>,
     #[network(rename = "perform")]
     pub perform: StringList,
+    /// Part of the network InitData as `skipCaps`, handled by [`Network`] as older cores do not send it
+    ///
+    /// [`Network`]: crate::message::objects::Network
+    #[network(skip, default)]
+    pub skip_caps: StringList,
 
     #[network(rename = "autoIdentifyService")]
     pub auto_identify_service: String,
@@ -65,6 +81,265 @@ pub struct NetworkInfo {
     // pub auto_away_active: bool,
 }
 
+impl NetworkInfo {
+    /// Start building a new network with the defaults of quassel
+    pub fn builder(network_name: &str) -> NetworkInfoBuilder {
+        NetworkInfoBuilder::new(network_name)
+    }
+
+    /// Wrap the network info in the `NetworkInfo` UserType as used by
+    /// `createNetwork` and `requestSetNetworkInfo`
+    pub fn to_user_type(&self) -> Result<Variant, failure::Error> {
+        let mut map = self.to_network_map();
+
+        map.insert(s!("NetworkId"), self.network_id.into());
+        map.insert(s!("Identity"), self.identity_id.into());
+        map.insert(
+            s!("ServerList"),
+            Variant::VariantList(
+                self.server_list
+                    .iter()
+                    .map(|server| {
+                        Ok(Variant::UserType(
+                            s!("Network::Server"),
+                            server.to_network_map().serialize()?,
+                        ))
+                    })
+                    .collect::<Result<VariantList, failure::Error>>()?,
+            ),
+        );
+
+        Ok(Variant::UserType(s!("NetworkInfo"), map.serialize()?))
+    }
+}
+
+/// The `NetworkInfo` UserType is a map with capitalized keys,
+/// unlike the network InitData
+impl NetworkMap for NetworkInfo {
+    type Item = VariantMap;
+
+    fn to_network_map(&self) -> Self::Item {
+        map! {
+            s!("NetworkId") => Variant::i32(self.network_id.0),
+            s!("NetworkName") => Variant::String(self.network_name.clone()),
+            s!("Identity") => Variant::i32(self.identity_id.0),
+            s!("CodecForServer") => Variant::ByteArray(self.codec_for_server.clone()),
+            s!("CodecForEncoding") => Variant::ByteArray(self.codec_for_encoding.clone()),
+            s!("CodecForDecoding") => Variant::ByteArray(self.codec_for_decoding.clone()),
+            s!("ServerList") => Variant::VariantList(
+                self.server_list
+                    .iter()
+                    .map(|server| Variant::VariantMap(server.to_network_map()))
+                    .collect(),
+            ),
+            s!("UseRandomServer") => Variant::bool(self.use_random_server),
+            s!("Perform") => Variant::StringList(self.perform.clone()),
+            s!("SkipCaps") => Variant::StringList(self.skip_caps.clone()),
+            s!("UseAutoIdentify") => Variant::bool(self.use_auto_identify),
+            s!("AutoIdentifyService") => Variant::String(self.auto_identify_service.clone()),
+            s!("AutoIdentifyPassword") => Variant::String(self.auto_identify_password.clone()),
+            s!("UseSasl") => Variant::bool(self.use_sasl),
+            s!("SaslAccount") => Variant::String(self.sasl_account.clone()),
+            s!("SaslPassword") => Variant::String(self.sasl_password.clone()),
+            s!("UseAutoReconnect") => Variant::bool(self.use_auto_reconnect),
+            s!("AutoReconnectInterval") => Variant::u32(self.auto_reconnect_interval),
+            s!("AutoReconnectRetries") => Variant::u16(self.auto_reconnect_retries),
+            s!("UnlimitedReconnectRetries") => Variant::bool(self.unlimited_reconnect_retries),
+            s!("RejoinChannels") => Variant::bool(self.rejoin_channels),
+            s!("UseCustomMessageRate") => Variant::bool(self.use_custom_message_rate),
+            s!("MessageRateBurstSize") => Variant::u32(self.msg_rate_burst_size),
+            s!("MessageRateDelay") => Variant::u32(self.msg_rate_message_delay),
+            s!("UnlimitedMessageRate") => Variant::bool(self.unlimited_message_rate),
+        }
+    }
+
+    /// Missing keys keep their default value
+    fn from_network_map(input: &mut Self::Item) -> Self {
+        fn get<T: TryFrom<Variant> + Default>(input: &mut VariantMap, key: &str) -> T {
+            input
+                .remove(key)
+                .and_then(|value| value.try_into().ok())
+                .unwrap_or_default()
+        }
+
+        let server_list: VariantList = get(input, "ServerList");
+
+        Self {
+            network_id: get(input, "NetworkId"),
+            identity_id: get(input, "Identity"),
+            network_name: get(input, "NetworkName"),
+            server_list: server_list
+                .into_iter()
+                .filter_map(|server| match server {
+                    Variant::VariantMap(mut server) => {
+                        Some(NetworkServer::from_network_map(&mut server))
+                    }
+                    server => {
+                        log::warn!("ignoring malformed server {:?}", server);
+                        None
+                    }
+                })
+                .collect(),
+            perform: get(input, "Perform"),
+            skip_caps: get(input, "SkipCaps"),
+            auto_identify_service: get(input, "AutoIdentifyService"),
+            auto_identify_password: get(input, "AutoIdentifyPassword"),
+            sasl_account: get(input, "SaslAccount"),
+            sasl_password: get(input, "SaslPassword"),
+            codec_for_server: get(input, "CodecForServer"),
+            codec_for_encoding: get(input, "CodecForEncoding"),
+            codec_for_decoding: get(input, "CodecForDecoding"),
+            msg_rate_burst_size: get(input, "MessageRateBurstSize"),
+            msg_rate_message_delay: get(input, "MessageRateDelay"),
+            auto_reconnect_interval: get(input, "AutoReconnectInterval"),
+            auto_reconnect_retries: get(input, "AutoReconnectRetries"),
+            rejoin_channels: get(input, "RejoinChannels"),
+            use_random_server: get(input, "UseRandomServer"),
+            use_auto_identify: get(input, "UseAutoIdentify"),
+            use_sasl: get(input, "UseSasl"),
+            use_auto_reconnect: get(input, "UseAutoReconnect"),
+            unlimited_reconnect_retries: get(input, "UnlimitedReconnectRetries"),
+            use_custom_message_rate: get(input, "UseCustomMessageRate"),
+            unlimited_message_rate: get(input, "UnlimitedMessageRate"),
+        }
+    }
+}
+
+impl Syncable for NetworkInfo {
+    const CLASS: Class = Class::NetworkInfo;
+}
+
+#[cfg(feature = "client")]
+impl NetworkInfo {
+    /// Client -> Server: Ask the core to create this network and join the given channels
+    ///
+    /// The id is assigned by the core, which answers with a `networkCreated` RPC.
+    pub fn create_network(&self, channels: StringList) {
+        self.send_rpc(crate::message::RpcCall::CreateNetwork(
+            crate::message::CreateNetwork {
+                network_info: self.clone(),
+                channels,
+            },
+        ));
+    }
+}
+
+/// Builder for a [`NetworkInfo`]
+#[derive(Debug, Clone)]
+pub struct NetworkInfoBuilder {
+    info: NetworkInfo,
+}
+
+impl NetworkInfoBuilder {
+    /// Creates a new builder with the defaults of quassel
+    pub fn new(network_name: &str) -> NetworkInfoBuilder {
+        NetworkInfoBuilder {
+            info: NetworkInfo {
+                network_name: network_name.to_string(),
+                auto_identify_service: s!("NickServ"),
+                msg_rate_burst_size: 5,
+                msg_rate_message_delay: 2200,
+                auto_reconnect_interval: 60,
+                auto_reconnect_retries: 20,
+                rejoin_channels: true,
+                use_auto_reconnect: true,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Sets the id of an existing network, needed for `requestSetNetworkInfo`
+    pub fn network_id(&mut self, id: NetworkId) -> &mut Self {
+        self.info.network_id = id;
+        self
+    }
+
+    pub fn identity(&mut self, id: IdentityId) -> &mut Self {
+        self.info.identity_id = id;
+        self
+    }
+
+    /// Adds a server to connect to, servers are tried in order
+    pub fn server(&mut self, server: NetworkServer) -> &mut Self {
+        self.info.server_list.push(server);
+        self
+    }
+
+    pub fn use_random_server(&mut self, val: bool) -> &mut Self {
+        self.info.use_random_server = val;
+        self
+    }
+
+    /// Adds a command to run after connecting
+    pub fn perform(&mut self, command: &str) -> &mut Self {
+        self.info.perform.push(command.to_string());
+        self
+    }
+
+    /// Adds a capability that should not be negotiated
+    pub fn skip_cap(&mut self, cap: &str) -> &mut Self {
+        self.info.skip_caps.push(cap.to_string());
+        self
+    }
+
+    /// Enables identifying to the given service with the password
+    pub fn auto_identify(&mut self, service: &str, password: &str) -> &mut Self {
+        self.info.use_auto_identify = true;
+        self.info.auto_identify_service = service.to_string();
+        self.info.auto_identify_password = password.to_string();
+        self
+    }
+
+    /// Enables SASL with the account and password
+    pub fn sasl(&mut self, account: &str, password: &str) -> &mut Self {
+        self.info.use_sasl = true;
+        self.info.sasl_account = account.to_string();
+        self.info.sasl_password = password.to_string();
+        self
+    }
+
+    /// Sets the interval in seconds and number of retries for reconnecting,
+    /// retries are unlimited if `retries` is `None`
+    pub fn auto_reconnect(&mut self, interval: u32, retries: Option<u16>) -> &mut Self {
+        self.info.use_auto_reconnect = true;
+        self.info.auto_reconnect_interval = interval;
+        self.info.unlimited_reconnect_retries = retries.is_none();
+        self.info.auto_reconnect_retries = retries.unwrap_or(self.info.auto_reconnect_retries);
+        self
+    }
+
+    pub fn rejoin_channels(&mut self, val: bool) -> &mut Self {
+        self.info.rejoin_channels = val;
+        self
+    }
+
+    /// Sets a custom message rate, with a delay in milliseconds
+    pub fn message_rate(&mut self, burst_size: u32, delay: u32) -> &mut Self {
+        self.info.use_custom_message_rate = true;
+        self.info.msg_rate_burst_size = burst_size;
+        self.info.msg_rate_message_delay = delay;
+        self
+    }
+
+    pub fn unlimited_message_rate(&mut self, val: bool) -> &mut Self {
+        self.info.unlimited_message_rate = val;
+        self
+    }
+
+    /// Sets the codec used for the server, encoding and decoding
+    pub fn codec(&mut self, codec: &str) -> &mut Self {
+        self.info.codec_for_server = codec.to_string();
+        self.info.codec_for_encoding = codec.to_string();
+        self.info.codec_for_decoding = codec.to_string();
+        self
+    }
+
+    /// Create the configured `NetworkInfo`
+    pub fn build(&self) -> NetworkInfo {
+        self.info.clone()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::primitive::{Variant, VariantList};
@@ -125,9 +400,12 @@ mod tests {
 
     fn get_runtime() -> NetworkInfo {
         NetworkInfo {
+            network_id: NetworkId(0),
+            identity_id: IdentityId(0),
             network_name: s!("snoonet"),
             server_list: vec![],
             perform: vec![s!("")],
+            skip_caps: vec![],
             auto_identify_service: s!("NickServ"),
             auto_identify_password: s!(""),
             sasl_account: s!(""),
@@ -163,4 +441,23 @@ mod tests {
             get_runtime()
         )
     }
+
+    #[test]
+    fn networkinfo_skips_malformed_servers() {
+        let server = NetworkServer::builder("irc.libera.chat", 6697).build();
+
+        let mut map = get_runtime().to_network_map();
+        map.insert(
+            s!("ServerList"),
+            Variant::VariantList(vec![
+                Variant::String(s!("irc.example.com")),
+                Variant::VariantMap(server.to_network_map()),
+            ]),
+        );
+
+        assert_eq!(
+            NetworkInfo::from_network_map(&mut map).server_list,
+            vec![server]
+        );
+    }
 }
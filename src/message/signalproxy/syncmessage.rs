@@ -5,6 +5,7 @@ use crate::{deserialize::Deserialize, serialize::Serialize};
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Class {
     AliasManager,
+    BacklogManager,
     BufferSyncer,
     BufferViewConfig,
     BufferViewManager,
@@ -18,6 +19,7 @@ pub enum Class {
     NetworkInfo,
     NetworkConfig,
     IrcChannel,
+    IrcListHelper,
     IrcUser,
     Unknown,
 }
@@ -32,6 +34,7 @@ impl From<&str> for Class {
     fn from(class: &str) -> Self {
         match class {
             "AliasManager" => Self::AliasManager,
+            "BacklogManager" => Self::BacklogManager,
             "BufferSyncer" => Self::BufferSyncer,
             "BufferViewConfig" => Self::BufferViewConfig,
             "BufferViewManager" => Self::BufferViewManager,
@@ -45,6 +48,7 @@ impl From<&str> for Class {
             "NetworkInfo" => Self::NetworkInfo,
             "NetworkConfig" => Self::NetworkConfig,
             "IrcChannel" => Self::IrcChannel,
+            "IrcListHelper" => Self::IrcListHelper,
             "IrcUser" => Self::IrcUser,
             _ => Self::Unknown,
         }
@@ -55,6 +59,7 @@ impl Class {
     pub fn as_str(&self) -> &str {
         match self {
             Class::AliasManager => "AliasManager",
+            Class::BacklogManager => "BacklogManager",
             Class::BufferSyncer => "BufferSyncer",
             Class::BufferViewConfig => "BufferViewConfig",
             Class::BufferViewManager => "BufferViewManager",
@@ -68,6 +73,7 @@ impl Class {
             Class::NetworkInfo => "NetworkInfo",
             Class::NetworkConfig => "NetworkConfig",
             Class::IrcChannel => "IrcChannel",
+            Class::IrcListHelper => "IrcListHelper",
             Class::IrcUser => "IrcUser",
             Class::Unknown => "Unknown",
         }
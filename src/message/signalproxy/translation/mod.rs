@@ -84,7 +84,7 @@ VariantMap({
 })
 ```
 **/
-use crate::primitive::{Variant, VariantList};
+use crate::primitive::{Variant, VariantList, VariantMap};
 
 #[deprecated(since="0.1.0", note="please use NetworkMap and NetworkList implementations")]
 pub trait Network {
@@ -110,3 +110,36 @@ pub trait NetworkList {
     fn to_network_list(&self) -> VariantList;
     fn from_network_list(input: &mut VariantList) -> Self;
 }
+
+/// Convert the Structure of Arrays representation of a single object to its VariantList representation
+///
+/// Objects like `IrcUser` are usually sent as part of their `Network` but can also be
+/// initialized on their own, in which case they use the VariantList representation.
+pub(crate) fn maplist_to_list(map: VariantMap) -> VariantList {
+    let mut fields: Vec<(String, Variant)> = map.into_iter().collect();
+    fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    fields
+        .into_iter()
+        .flat_map(|(name, value)| {
+            let value = match value {
+                Variant::VariantList(mut list) if list.len() == 1 => list.remove(0),
+                value => value,
+            };
+
+            [Variant::ByteArray(name), value]
+        })
+        .collect()
+}
+
+/// Convert the VariantList representation of a single object to its Structure of Arrays representation
+pub(crate) fn list_to_maplist(list: &VariantList) -> VariantMap {
+    list.chunks_exact(2)
+        .filter_map(|field| match &field[0] {
+            Variant::ByteArray(name) => {
+                Some((name.clone(), Variant::VariantList(vec![field[1].clone()])))
+            }
+            _ => None,
+        })
+        .collect()
+}
@@ -1,23 +1,112 @@
-use crate::{deserialize::*, serialize::*};
+use std::sync::RwLock;
+
+use crate::{deserialize::*, error::ProtocolError, serialize::*};
 
 use time::{OffsetDateTime, PrimitiveDateTime, UtcOffset};
 
-// The DateTime struct represents a DateTime as received in IRC
-//
-// DateTime is, like all other struct based types, serialized sequentially.
-// #[derive(Clone, Debug, std::cmp::PartialEq)]
-// pub struct DateTime {
-//     /// Day in Julian calendar, unknown if signed or unsigned
-//     julian_day: i32,
-//     /// Milliseconds since start of day
-//     millis_of_day: i32,
-//     /// Timezone of DateTime, 0x00 is local, 0x01 is UTC
-//     zone: u8,
-// }
-
-pub type DateTime = OffsetDateTime;
 pub use time::{Date, Time};
 
+/// The DateTime struct represents a QDateTime as received in IRC
+///
+/// DateTime is, like all other struct based types, serialized sequentially:
+/// the day in the julian calendar as i32, the milliseconds since the start of the day as i32,
+/// the [`TimeSpec`] as u8 and, if the TimeSpec is [`TimeSpec::OffsetFromUTC`], the offset in seconds as i32.
+///
+/// The wall clock time and TimeSpec are kept as they where received,
+/// so a valid DateTime is serialized to the exact same bytes it was parsed from.
+/// A julian day or milliseconds of `-1` represents a null QDateTime,
+/// which is always serialized with both set to `-1`.
+#[derive(Copy, Clone, Debug, std::cmp::PartialEq)]
+pub struct DateTime {
+    /// Wall clock time as transmitted, `None` for a null QDateTime
+    datetime: Option<PrimitiveDateTime>,
+    spec: TimeSpec,
+    /// Only meaningful for [`TimeSpec::OffsetFromUTC`]
+    offset: UtcOffset,
+}
+
+impl DateTime {
+    /// Create a new DateTime from a wall clock time in the given [`TimeSpec`]
+    ///
+    /// For [`TimeSpec::OffsetFromUTC`] use the [`From<OffsetDateTime>`] implementation
+    /// to also set the offset, here it defaults to UTC.
+    pub fn new(datetime: PrimitiveDateTime, spec: TimeSpec) -> Self {
+        Self {
+            datetime: Some(datetime),
+            spec,
+            offset: UtcOffset::UTC,
+        }
+    }
+
+    /// A null QDateTime as used for unset values
+    pub fn null() -> Self {
+        Self {
+            datetime: None,
+            spec: TimeSpec::LocalUnknown,
+            offset: UtcOffset::UTC,
+        }
+    }
+
+    /// The unix epoch in UTC
+    pub fn unix_epoch() -> Self {
+        OffsetDateTime::unix_epoch().into()
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.datetime.is_none()
+    }
+
+    pub fn spec(&self) -> TimeSpec {
+        self.spec
+    }
+
+    /// The wall clock time as transmitted, `None` if this is a null DateTime
+    pub fn wall_clock(&self) -> Option<PrimitiveDateTime> {
+        self.datetime
+    }
+
+    /// Resolve the DateTime to an [`OffsetDateTime`]
+    ///
+    /// Local times are resolved using the current [`LocalZone`] policy.
+    /// Returns `None` if this is a null DateTime.
+    pub fn to_offset_datetime(&self) -> Option<OffsetDateTime> {
+        self.to_offset_datetime_in(LocalZone::current())
+    }
+
+    /// Resolve the DateTime to an [`OffsetDateTime`], resolving local times in the given zone
+    ///
+    /// Returns `None` if this is a null DateTime.
+    pub fn to_offset_datetime_in(&self, zone: LocalZone) -> Option<OffsetDateTime> {
+        let datetime = self.datetime?;
+
+        let offset = match self.spec {
+            TimeSpec::LocalUnknown | TimeSpec::LocalStandard | TimeSpec::LocalDST => {
+                zone.offset_at(datetime)
+            }
+            TimeSpec::UTC => UtcOffset::UTC,
+            TimeSpec::OffsetFromUTC => self.offset,
+        };
+
+        Some(datetime.assume_offset(offset))
+    }
+}
+
+impl Default for DateTime {
+    fn default() -> Self {
+        Self::null()
+    }
+}
+
+impl From<OffsetDateTime> for DateTime {
+    fn from(datetime: OffsetDateTime) -> Self {
+        Self {
+            datetime: Some(PrimitiveDateTime::new(datetime.date(), datetime.time())),
+            spec: TimeSpec::OffsetFromUTC,
+            offset: datetime.offset(),
+        }
+    }
+}
+
 /// TimeSpec specifies whether the time is a local time, daylightsaving local time or a form of UTC Offset
 #[repr(i8)]
 #[derive(Copy, Clone, Debug, std::cmp::PartialEq)]
@@ -29,43 +118,113 @@ pub enum TimeSpec {
     OffsetFromUTC = 0x03,
 }
 
-impl From<i8> for TimeSpec {
-    fn from(val: i8) -> Self {
+impl TryFrom<i8> for TimeSpec {
+    type Error = ProtocolError;
+
+    fn try_from(val: i8) -> Result<Self, Self::Error> {
         match val {
-            -0x01 => TimeSpec::LocalUnknown,
-            0x00 => TimeSpec::LocalStandard,
-            0x01 => TimeSpec::LocalDST,
-            0x02 => TimeSpec::UTC,
-            0x03 => TimeSpec::OffsetFromUTC,
-            _ => unimplemented!(),
+            -0x01 => Ok(TimeSpec::LocalUnknown),
+            0x00 => Ok(TimeSpec::LocalStandard),
+            0x01 => Ok(TimeSpec::LocalDST),
+            0x02 => Ok(TimeSpec::UTC),
+            0x03 => Ok(TimeSpec::OffsetFromUTC),
+            _ => Err(ProtocolError::UnknownTimeSpec(val)),
+        }
+    }
+}
+
+/// Policy used to resolve DateTimes that are sent in the local time of the core
+///
+/// The protocol does not transmit the timezone of the core for local times,
+/// so by default the local offset of the machine we are running on is assumed.
+#[derive(Copy, Clone, Debug, std::cmp::PartialEq)]
+pub enum LocalZone {
+    /// Use the local offset of this machine, falling back to UTC if it can't be determined
+    System,
+    /// Treat local times as UTC
+    Utc,
+    /// Use a fixed offset, for when the timezone of the core is known
+    Fixed(UtcOffset),
+}
+
+static LOCAL_ZONE: RwLock<LocalZone> = RwLock::new(LocalZone::System);
+
+impl LocalZone {
+    /// Set the global policy used when resolving local times
+    pub fn set(zone: LocalZone) {
+        *LOCAL_ZONE.write().unwrap() = zone;
+    }
+
+    /// Get the currently configured global policy
+    pub fn current() -> LocalZone {
+        *LOCAL_ZONE.read().unwrap()
+    }
+
+    /// The offset of the zone at the given UTC time
+    pub(crate) fn offset_at(&self, datetime: PrimitiveDateTime) -> UtcOffset {
+        match self {
+            LocalZone::System => UtcOffset::try_local_offset_at(datetime.assume_utc())
+                .unwrap_or_else(|_| {
+                    log::warn!("could not get local offset defaulting to utc");
+                    UtcOffset::UTC
+                }),
+            LocalZone::Utc => UtcOffset::UTC,
+            LocalZone::Fixed(offset) => *offset,
         }
     }
 }
 
-impl Serialize for OffsetDateTime {
+/// Convert a time to the milliseconds since the start of the day
+fn millis_of_day(time: Time) -> i32 {
+    let hour: i32 = time.hour() as i32;
+    let minute: i32 = time.minute() as i32;
+    let second: i32 = time.second() as i32;
+    let milli: i32 = time.millisecond() as i32;
+
+    milli + (second * 1000) + (minute * 60000) + (hour * 60 * 60000)
+}
+
+/// Convert the milliseconds since the start of the day to a time
+fn time_from_millis(millis_of_day: i32) -> Result<Time, failure::Error> {
+    let hour = millis_of_day / 60 / 60000;
+    let minute = (millis_of_day - (hour * 60 * 60000)) / 60000;
+    let seconds = (millis_of_day - (hour * 60 * 60000) - (minute * 60000)) / 1000;
+    let millis = millis_of_day - (hour * 60 * 60000) - (minute * 60000) - (seconds * 1000);
+
+    Ok(Time::try_from_hms_milli(
+        hour as u8,
+        minute as u8,
+        seconds as u8,
+        millis as u16,
+    )?)
+}
+
+impl Serialize for DateTime {
     fn serialize(&self) -> Result<Vec<u8>, failure::Error> {
         let mut values: Vec<u8> = Vec::new();
 
-        values.extend(i32::serialize(&(self.date().julian_day() as i32))?);
-
-        let time: i32 = {
-            let hour: i32 = self.time().hour() as i32;
-            let minute: i32 = self.time().minute() as i32;
-            let second: i32 = self.time().second() as i32;
-            let milli: i32 = self.time().millisecond() as i32;
+        match self.datetime {
+            Some(datetime) => {
+                values.extend(i32::serialize(&(datetime.date().julian_day() as i32))?);
+                values.extend(i32::serialize(&millis_of_day(datetime.time()))?);
+            }
+            None => {
+                values.extend(i32::serialize(&-1)?);
+                values.extend(i32::serialize(&-1)?);
+            }
+        }
 
-            milli + (second * 1000) + (minute * 60000) + (hour * 60 * 60000)
-        };
+        values.extend(u8::serialize(&(self.spec as u8))?);
 
-        values.extend(i32::serialize(&time)?);
-        values.extend(u8::serialize(&(TimeSpec::OffsetFromUTC as u8))?);
-        values.extend(i32::serialize(&self.offset().as_seconds())?);
+        if self.spec == TimeSpec::OffsetFromUTC {
+            values.extend(i32::serialize(&self.offset.as_seconds())?);
+        }
 
         Ok(values)
     }
 }
 
-impl Deserialize for OffsetDateTime {
+impl Deserialize for DateTime {
     fn parse(b: &[u8]) -> Result<(usize, Self), failure::Error> {
         let (_, julian_day) = i32::parse(&b[0..4])?;
         let (_, millis_of_day) = i32::parse(&b[4..8])?;
@@ -73,129 +232,187 @@ impl Deserialize for OffsetDateTime {
 
         let mut pos = 9;
 
-        let zone = TimeSpec::from(zone as i8);
-
-        // Default to unix epoch when one of these is set to -1
-        if julian_day == -1 || millis_of_day == -1 {
-            return Ok((pos, OffsetDateTime::unix_epoch()));
-        }
+        let spec = TimeSpec::try_from(zone as i8)?;
 
-        let offset: UtcOffset;
-        match zone {
-            TimeSpec::LocalUnknown | TimeSpec::LocalStandard | TimeSpec::LocalDST => {
-                offset = UtcOffset::try_current_local_offset().unwrap_or_else(|_| {
-                    log::warn!("could not get local offset defaulting to utc");
-                    UtcOffset::UTC
-                })
-            }
-            TimeSpec::UTC => offset = UtcOffset::UTC,
+        let offset = match spec {
             TimeSpec::OffsetFromUTC => {
-                let (_, tmp_offset) = i32::parse(&b[9..13])?;
+                let (_, offset) = i32::parse(&b[9..13])?;
                 pos += 4;
-                offset = UtcOffset::seconds(tmp_offset)
+                UtcOffset::seconds(offset)
             }
+            _ => UtcOffset::UTC,
+        };
+
+        if julian_day == -1 || millis_of_day == -1 {
+            return Ok((
+                pos,
+                Self {
+                    datetime: None,
+                    spec,
+                    offset,
+                },
+            ));
         }
 
         let date = Date::from_julian_day(julian_day as i64);
-
-        let hour = millis_of_day / 60 / 60000;
-        let minute = (millis_of_day - (hour * 60 * 60000)) / 60000;
-        let seconds = (millis_of_day - (hour * 60 * 60000) - (minute * 60000)) / 1000;
-        let millis = millis_of_day - (hour * 60 * 60000) - (minute * 60000) - (seconds * 1000);
-
-        let time =
-            Time::try_from_hms_milli(hour as u8, minute as u8, seconds as u8, millis as u16)?;
-        let primitivedatetime = PrimitiveDateTime::new(date, time);
-        let datetime = primitivedatetime.assume_offset(offset);
-
-        Ok((pos, datetime))
+        let time = time_from_millis(millis_of_day)?;
+
+        Ok((
+            pos,
+            Self {
+                datetime: Some(PrimitiveDateTime::new(date, time)),
+                spec,
+                offset,
+            },
+        ))
     }
 }
 
-impl Serialize for Date {
+/// A QDate, `None` represents a null QDate which is transmitted as a julian day of `-1`
+impl Serialize for Option<Date> {
     fn serialize(&self) -> Result<Vec<std::primitive::u8>, failure::Error> {
-        let mut values: Vec<u8> = Vec::new();
-
-        values.extend(i32::serialize(&(self.julian_day() as i32))?);
+        let julian_day = match self {
+            Some(date) => date.julian_day() as i32,
+            None => -1,
+        };
 
-        Ok(values)
+        i32::serialize(&julian_day)
     }
 }
 
-impl Deserialize for Date {
+impl Deserialize for Option<Date> {
     fn parse(b: &[std::primitive::u8]) -> Result<(std::primitive::usize, Self), failure::Error> {
         let (_, julian_day) = i32::parse(&b[0..4])?;
-        let date = Date::from_julian_day(julian_day as i64);
 
-        Ok((4, date))
+        match julian_day {
+            -1 => Ok((4, None)),
+            julian_day => Ok((4, Some(Date::from_julian_day(julian_day as i64)))),
+        }
     }
 }
 
-impl Serialize for Time {
+/// A QTime, `None` represents a null QTime which is transmitted as `-1` milliseconds
+impl Serialize for Option<Time> {
     fn serialize(&self) -> Result<Vec<std::primitive::u8>, failure::Error> {
-        let mut values: Vec<u8> = Vec::new();
-
-        let time: i32 = {
-            let hour: i32 = self.hour() as i32;
-            let minute: i32 = self.minute() as i32;
-            let second: i32 = self.second() as i32;
-            let milli: i32 = self.millisecond() as i32;
-
-            milli + (second * 1000) + (minute * 60000) + (hour * 60 * 60000)
+        let millis = match self {
+            Some(time) => millis_of_day(*time),
+            None => -1,
         };
 
-        values.extend(i32::serialize(&time)?);
-
-        Ok(values)
+        i32::serialize(&millis)
     }
 }
 
-impl Deserialize for Time {
+impl Deserialize for Option<Time> {
     fn parse(b: &[std::primitive::u8]) -> Result<(std::primitive::usize, Self), failure::Error> {
-        let (_, millis_of_day) = i32::parse(&b[0..4])?;
+        let (_, millis) = i32::parse(&b[0..4])?;
 
-        let hour = millis_of_day / 60 / 60000;
-        let minute = (millis_of_day - (hour * 60 * 60000)) / 60000;
-        let seconds = (millis_of_day - (hour * 60 * 60000) - (minute * 60000)) / 1000;
-        let millis = millis_of_day - (hour * 60 * 60000) - (minute * 60000) - (seconds * 1000);
+        match millis {
+            -1 => Ok((4, None)),
+            millis => Ok((4, Some(time_from_millis(millis)?))),
+        }
+    }
+}
 
-        let time =
-            Time::try_from_hms_milli(hour as u8, minute as u8, seconds as u8, millis as u16)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        Ok((4, time))
+    fn get_datetime() -> DateTime {
+        OffsetDateTime::parse("2020-02-19 13:00 +0200", "%Y-%m-%d %R %z")
+            .unwrap()
+            .into()
     }
-}
 
-#[test]
-pub fn datetime_serialize() {
-    let datetime = DateTime::parse("2020-02-19 13:00 +0200", "%Y-%m-%d %R %z").unwrap();
+    #[test]
+    pub fn datetime_serialize() {
+        let sers = get_datetime().serialize().unwrap();
+        let bytes = vec![0, 37, 133, 19, 2, 202, 28, 128, 3, 0, 0, 28, 32];
 
-    let sers = datetime.serialize().unwrap();
-    let bytes = vec![0, 37, 133, 19, 2, 202, 28, 128, 3, 0, 0, 28, 32];
+        assert_eq!(sers, bytes)
+    }
 
-    assert_eq!(sers, bytes)
-}
+    #[test]
+    pub fn datetime_deserialize() {
+        let bytes = vec![0, 37, 133, 19, 2, 202, 28, 128, 3, 0, 0, 28, 32];
+        let (_, res): (usize, DateTime) = Deserialize::parse(&bytes).unwrap();
 
-#[test]
-pub fn datetime_deserialize() {
-    let datetime = DateTime::parse("2020-02-19 13:00 +0200", "%Y-%m-%d %R %z").unwrap();
+        assert_eq!(res, get_datetime())
+    }
 
-    let bytes = vec![0, 37, 133, 19, 2, 202, 28, 128, 3, 0, 0, 28, 32];
-    let (_, res): (usize, DateTime) = Deserialize::parse(&bytes).unwrap();
+    #[test]
+    pub fn datetime_deserialize_null() {
+        let bytes = vec![0, 37, 133, 19, 0xff, 0xff, 0xff, 0xff, 3, 0, 0, 28, 32];
+        let (len, res): (usize, DateTime) = Deserialize::parse(&bytes).unwrap();
 
-    assert_eq!(res, datetime)
-}
+        assert_eq!(len, 13);
+        assert!(res.is_null());
+        assert_eq!(res.to_offset_datetime(), None);
 
-#[test]
-pub fn datetime_deserialize_epoch() {
-    let datetime = DateTime::unix_epoch();
+        let bytes = vec![0xff, 0xff, 0xff, 0xff, 2, 202, 28, 128, 2];
+        let (len, res): (usize, DateTime) = Deserialize::parse(&bytes).unwrap();
 
-    let bytes = vec![0, 37, 133, 19, 0xff, 0xff, 0xff, 0xff, 3, 0, 0, 28, 32];
-    let (_, res): (usize, DateTime) = Deserialize::parse(&bytes).unwrap();
+        assert_eq!(len, 9);
+        assert!(res.is_null());
+        assert_eq!(res.spec(), TimeSpec::UTC);
 
-    let bytes = vec![0xff, 0xff, 0xff, 0xff, 2, 202, 28, 128, 3, 0, 0, 28, 32];
-    let (_, res2): (usize, DateTime) = Deserialize::parse(&bytes).unwrap();
+        // Half null values are normalized
+        assert_eq!(res.serialize().unwrap()[0..8], [0xff; 8]);
+    }
+
+    #[test]
+    pub fn datetime_null_roundtrip() {
+        let bytes = vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+        let (_, res): (usize, DateTime) = Deserialize::parse(&bytes).unwrap();
+
+        assert_eq!(res, DateTime::null());
+        assert_eq!(res.serialize().unwrap(), bytes);
+    }
 
-    assert_eq!(res, datetime);
-    assert_eq!(res2, datetime)
+    #[test]
+    pub fn datetime_timespec_roundtrip() {
+        for spec in [0x00, 0x01, 0x02, 0xff] {
+            let bytes = vec![0, 37, 133, 19, 2, 202, 28, 128, spec];
+            let (len, res): (usize, DateTime) = Deserialize::parse(&bytes).unwrap();
+
+            assert_eq!(len, 9);
+            assert_eq!(res.spec() as u8, spec);
+            assert_eq!(res.serialize().unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    pub fn datetime_unknown_timespec() {
+        let bytes = vec![0, 37, 133, 19, 2, 202, 28, 128, 4];
+        assert!(DateTime::parse(&bytes).is_err());
+    }
+
+    #[test]
+    pub fn datetime_local_zone() {
+        let bytes = vec![0, 37, 133, 19, 2, 202, 28, 128, 0];
+        let (_, res): (usize, DateTime) = Deserialize::parse(&bytes).unwrap();
+
+        assert_eq!(
+            res.to_offset_datetime_in(LocalZone::Fixed(UtcOffset::hours(2)))
+                .unwrap(),
+            OffsetDateTime::parse("2020-02-19 13:00 +0200", "%Y-%m-%d %R %z").unwrap()
+        );
+        assert_eq!(
+            res.to_offset_datetime_in(LocalZone::Utc).unwrap(),
+            OffsetDateTime::parse("2020-02-19 13:00 +0000", "%Y-%m-%d %R %z").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn date_time_null() {
+        let (_, date): (usize, Option<Date>) =
+            Deserialize::parse(&[0xff, 0xff, 0xff, 0xff]).unwrap();
+        let (_, time): (usize, Option<Time>) =
+            Deserialize::parse(&[0xff, 0xff, 0xff, 0xff]).unwrap();
+
+        assert_eq!(date, None);
+        assert_eq!(time, None);
+        assert_eq!(date.serialize().unwrap(), [0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(time.serialize().unwrap(), [0xff, 0xff, 0xff, 0xff]);
+    }
 }
@@ -0,0 +1,150 @@
+use failure::Error;
+
+use crate::{deserialize::*, serialize::*};
+
+use super::Variant;
+
+/// Implements an id that is transmitted as an i32 based UserType
+macro_rules! user_type_id {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name(pub i32);
+
+        impl From<i32> for $name {
+            fn from(id: i32) -> Self {
+                Self(id)
+            }
+        }
+
+        impl From<$name> for i32 {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+
+        impl From<$name> for Variant {
+            fn from(id: $name) -> Self {
+                Variant::UserType(stringify!($name).to_string(), id.0.to_be_bytes().to_vec())
+            }
+        }
+
+        impl TryFrom<Variant> for $name {
+            type Error = String;
+
+            fn try_from(input: Variant) -> Result<Self, Self::Error> {
+                match input {
+                    Variant::i32(id) => Ok(Self(id)),
+                    Variant::UserType(name, bytes) if name == stringify!($name) => {
+                        let (_, id) = i32::parse(&bytes).map_err(|err| err.to_string())?;
+                        Ok(Self(id))
+                    }
+                    v => Err(format!(
+                        "variant::from: wrong variant, expected: {}, got: {:?}",
+                        stringify!($name),
+                        v
+                    )),
+                }
+            }
+        }
+    };
+}
+
+user_type_id!(
+    /// Id of a buffer, transmitted as the `BufferId` UserType
+    BufferId
+);
+
+user_type_id!(
+    /// Id of an identity, transmitted as the `IdentityId` UserType
+    IdentityId
+);
+
+user_type_id!(
+    /// Id of a network, transmitted as the `NetworkId` UserType
+    NetworkId
+);
+
+/// Id of a message, transmitted as the `MsgId` UserType
+///
+/// Serialized as i64 if the long-message-id feature is enabled, as i32 otherwise.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MsgId(pub i64);
+
+impl MsgId {
+    fn to_bytes(self) -> Result<Vec<u8>, Error> {
+        #[cfg(feature = "long-message-id")]
+        return self.0.serialize();
+        #[cfg(not(feature = "long-message-id"))]
+        return (self.0 as i32).serialize();
+    }
+}
+
+impl From<i64> for MsgId {
+    fn from(id: i64) -> Self {
+        Self(id)
+    }
+}
+
+impl From<i32> for MsgId {
+    fn from(id: i32) -> Self {
+        Self(id as i64)
+    }
+}
+
+impl From<MsgId> for i64 {
+    fn from(id: MsgId) -> Self {
+        id.0
+    }
+}
+
+impl From<MsgId> for Variant {
+    fn from(id: MsgId) -> Self {
+        Variant::UserType(s!("MsgId"), id.to_bytes().unwrap())
+    }
+}
+
+impl TryFrom<Variant> for MsgId {
+    type Error = String;
+
+    fn try_from(input: Variant) -> Result<Self, Self::Error> {
+        match input {
+            Variant::i64(id) => Ok(Self(id)),
+            Variant::i32(id) => Ok(Self(id as i64)),
+            Variant::UserType(name, bytes) if name == "MsgId" => {
+                #[cfg(feature = "long-message-id")]
+                let id = i64::parse(&bytes).map_err(|err| err.to_string())?.1;
+                #[cfg(not(feature = "long-message-id"))]
+                let id = i64::from(i32::parse(&bytes).map_err(|err| err.to_string())?.1);
+
+                Ok(Self(id))
+            }
+            v => Err(format!(
+                "variant::from: wrong variant, expected: MsgId, got: {:?}",
+                v
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_roundtrip() {
+        let buffer_id: Variant = BufferId(5).into();
+        let bytes = buffer_id.serialize().unwrap();
+        let (_, parsed) = Variant::parse(&bytes).unwrap();
+        assert_eq!(BufferId::try_from(parsed), Ok(BufferId(5)));
+        assert_eq!(BufferId::try_from(buffer_id), Ok(BufferId(5)));
+
+        let msg_id: Variant = MsgId(1337).into();
+        let bytes = msg_id.serialize().unwrap();
+        let (_, parsed) = Variant::parse(&bytes).unwrap();
+        assert_eq!(MsgId::try_from(parsed), Ok(MsgId(1337)));
+        assert_eq!(MsgId::try_from(msg_id), Ok(MsgId(1337)));
+
+        assert!(BufferId::try_from(Variant::String(s!("5"))).is_err());
+    }
+}
@@ -1,6 +1,7 @@
 use std::{collections::HashMap, vec::Vec};
 
 use failure::Error;
+use time::OffsetDateTime;
 
 use crate::{deserialize::*, serialize::*};
 
@@ -32,8 +33,8 @@ pub struct Message {
     pub timestamp: i32,
     /// The message type as it's own type serialized as i32
     pub msg_type: MessageType,
-    /// The flags
-    pub flags: i8,
+    /// The flags of the message, like if it was sent by ourselves or is a highlight
+    pub flags: MessageFlags,
     /// The buffer the message belongs to, usually everything but BufferId is set to NULL
     pub buffer: BufferInfo,
     /// The sender as nick!ident@host
@@ -69,7 +70,7 @@ impl Serialize for Message {
         values.append(&mut i32::serialize(&(self.timestamp as i32))?);
 
         values.append(&mut i32::serialize(&(self.msg_type.bits()))?);
-        values.append(&mut i8::serialize(&(self.flags as i8))?);
+        values.append(&mut u8::serialize(&self.flags.bits())?);
         values.append(&mut BufferInfo::serialize(&self.buffer)?);
         values.append(&mut String::serialize_utf8(&self.sender)?);
 
@@ -116,7 +117,7 @@ impl Deserialize for Message {
 
         let (parsed, msg_type) = i32::parse(&b[pos..])?;
         pos += parsed;
-        let (parsed, flags) = i8::parse(&b[pos..])?;
+        let (parsed, flags) = u8::parse(&b[pos..])?;
         pos += parsed;
         let (parsed, buffer) = BufferInfo::parse(&b[pos..])?;
         pos += parsed;
@@ -155,8 +156,8 @@ impl Deserialize for Message {
             Self {
                 msg_id,
                 timestamp,
-                msg_type: MessageType::from_bits(msg_type).unwrap(),
-                flags,
+                msg_type: MessageType::from_bits_retain(msg_type),
+                flags: MessageFlags::from_bits_retain(flags),
                 buffer,
                 sender,
                 #[cfg(feature = "sender-prefixes")]
@@ -171,6 +172,96 @@ impl Deserialize for Message {
     }
 }
 
+impl Message {
+    /// The message was sent by ourselves
+    pub fn is_self(&self) -> bool {
+        self.flags.contains(MessageFlags::SELF)
+    }
+
+    /// The message highlights us
+    pub fn is_highlight(&self) -> bool {
+        self.flags.contains(MessageFlags::HIGHLIGHT)
+    }
+
+    /// The message was received as part of the backlog
+    pub fn is_backlog(&self) -> bool {
+        self.flags.contains(MessageFlags::BACKLOG)
+    }
+
+    /// The timestamp of the message as UTC [`OffsetDateTime`]
+    pub fn time(&self) -> OffsetDateTime {
+        #[cfg(feature = "long-time")]
+        return OffsetDateTime::from_unix_timestamp_nanos(self.timestamp as i128 * 1_000_000);
+        #[cfg(not(feature = "long-time"))]
+        return OffsetDateTime::from_unix_timestamp(self.timestamp as i64);
+    }
+}
+
+/// Builders for [`Message`]s in tests that work with any combination of features
+#[cfg(test)]
+impl Message {
+    /// A PLAIN message from `nick!user@host` in the channel `#test` of buffer and network 1
+    pub(crate) fn test(msg_id: i32, content: &str) -> Self {
+        Self {
+            msg_id: msg_id.into(),
+            timestamp: 0,
+            msg_type: MessageType::PLAIN,
+            flags: MessageFlags::NONE,
+            buffer: BufferInfo {
+                id: 1,
+                network_id: 1,
+                buffer_type: super::BufferType::Channel,
+                name: s!("#test"),
+            },
+            sender: s!("nick!user@host"),
+            #[cfg(feature = "sender-prefixes")]
+            sender_prefixes: s!(""),
+            #[cfg(feature = "rich-messages")]
+            real_name: s!(""),
+            #[cfg(feature = "rich-messages")]
+            avatar_url: s!(""),
+            content: content.to_string(),
+        }
+        .at(1609846597)
+    }
+
+    /// Set the timestamp in seconds since EPOCH, in the resolution used with the enabled features
+    pub(crate) fn at(mut self, seconds: i64) -> Self {
+        #[cfg(feature = "long-time")]
+        {
+            self.timestamp = seconds * 1000;
+        }
+        #[cfg(not(feature = "long-time"))]
+        {
+            self.timestamp = seconds as i32;
+        }
+
+        self
+    }
+
+    /// Set the prefix modes of the sender, ignored without the `sender-prefixes` feature
+    #[allow(unused_variables, unused_mut)]
+    pub(crate) fn with_sender_prefixes(mut self, prefixes: &str) -> Self {
+        #[cfg(feature = "sender-prefixes")]
+        {
+            self.sender_prefixes = prefixes.to_string();
+        }
+
+        self
+    }
+
+    /// Set the realName of the sender, ignored without the `rich-messages` feature
+    #[allow(unused_variables, unused_mut)]
+    pub(crate) fn with_real_name(mut self, real_name: &str) -> Self {
+        #[cfg(feature = "rich-messages")]
+        {
+            self.real_name = real_name.to_string();
+        }
+
+        self
+    }
+}
+
 // #[repr(i32)]
 // #[derive(Copy, Clone, Debug, std::cmp::PartialEq, FromPrimitive, ToPrimitive)]
 // pub enum MessageType {
@@ -198,6 +289,7 @@ impl Deserialize for Message {
 use bitflags::bitflags;
 
 bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
     pub struct MessageType: i32 {
         const NONE = 0x00000000;
         const PLAIN = 0x00000001;
@@ -222,6 +314,28 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// Flags of a [`Message`], serialized as u8
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct MessageFlags: u8 {
+        const NONE = 0x00;
+        /// Sent by ourselves
+        const SELF = 0x01;
+        /// Highlights us
+        const HIGHLIGHT = 0x02;
+        /// Redirected from another buffer, e.g. a notice shown in the current buffer
+        const REDIRECTED = 0x04;
+        /// Sent by the server
+        const SERVER_MSG = 0x08;
+        /// Sent to a status prefix of the channel, like `@#channel`
+        const STATUS_MSG = 0x10;
+        /// Matched by an ignore rule
+        const IGNORED = 0x20;
+        /// Received as part of the backlog
+        const BACKLOG = 0x80;
+    }
+}
+
 impl<T> crate::message::NetworkList for HashMap<T, MessageType>
 where
     T: std::convert::TryFrom<Variant> + Into<Variant> + Clone + std::hash::Hash + std::cmp::Eq,
@@ -250,7 +364,7 @@ where
                 },
                 {
                     let typ = v.try_into().expect("failed to get from variant");
-                    MessageType::from_bits(typ).expect("failed to get messagetype from i32")
+                    MessageType::from_bits_retain(typ)
                 },
             );
         });
@@ -271,7 +385,7 @@ mod tests {
             msg_id: 1,
             timestamp: 1609846597,
             msg_type: MessageType::PLAIN,
-            flags: 0,
+            flags: MessageFlags::NONE,
             buffer: BufferInfo {
                 id: 1,
                 network_id: 1,
@@ -305,7 +419,7 @@ mod tests {
             msg_id: 1,
             timestamp: 1609846597,
             msg_type: MessageType::PLAIN,
-            flags: 0,
+            flags: MessageFlags::NONE,
             buffer: BufferInfo {
                 id: 1,
                 network_id: 1,
@@ -331,4 +445,53 @@ mod tests {
 
         assert_eq!(Message::parse(&bytes).unwrap(), (133, message))
     }
+
+    #[test]
+    fn message_flags_roundtrip() {
+        let message = Message {
+            msg_id: 1,
+            timestamp: 1609846597,
+            msg_type: MessageType::PLAIN,
+            flags: MessageFlags::SELF | MessageFlags::BACKLOG,
+            buffer: BufferInfo {
+                id: 1,
+                network_id: 1,
+                buffer_type: BufferType::Channel,
+                name: "#test".to_string(),
+            },
+            sender: "test".to_string(),
+            content: "this is a test message".to_string(),
+            sender_prefixes: "".to_string(),
+            real_name: "".to_string(),
+            avatar_url: "".to_string(),
+        };
+
+        // The flags follow the message id, timestamp and type
+        let flags_pos =
+            std::mem::size_of_val(&message.msg_id) + std::mem::size_of_val(&message.timestamp) + 4;
+
+        let bytes = message.serialize().unwrap();
+        assert_eq!(bytes[flags_pos], 0x81);
+
+        let (_, res) = Message::parse(&bytes).unwrap();
+        assert_eq!(res, message);
+        assert!(res.is_self());
+        assert!(res.is_backlog());
+        assert!(!res.is_highlight());
+
+        // Unknown flags are kept
+        let mut bytes = bytes;
+        bytes[flags_pos] |= 0x40;
+
+        let (_, res) = Message::parse(&bytes).unwrap();
+        assert_eq!(res.flags.bits(), 0xc1);
+        assert_eq!(res.serialize().unwrap(), bytes);
+
+        // Unknown types of newer cores are kept as well
+        bytes[flags_pos - 4] = 0x01;
+
+        let (_, res) = Message::parse(&bytes).unwrap();
+        assert_eq!(res.msg_type.bits(), 0x01000000 | MessageType::PLAIN.bits());
+        assert_eq!(res.serialize().unwrap(), bytes);
+    }
 }
@@ -1,5 +1,6 @@
 mod bufferinfo;
 mod datetime;
+mod ids;
 mod message;
 mod signedint;
 mod string;
@@ -11,6 +12,7 @@ mod variantmap;
 
 pub use bufferinfo::*;
 pub use datetime::*;
+pub use ids::*;
 pub use message::*;
 pub use signedint::*;
 pub use string::*;
@@ -30,8 +30,8 @@ pub enum Variant {
     UserType(String, Vec<u8>),
     BufferInfo(BufferInfo),
     Message(Message),
-    Time(Time),
-    Date(Date),
+    Time(Option<Time>),
+    Date(Option<Date>),
     DateTime(DateTime),
     VariantMap(VariantMap),
     VariantList(VariantList),
@@ -248,12 +248,12 @@ impl Serialize for Variant {
             Variant::BufferInfo(v) => {
                 let bytes = BufferInfo::serialize(v)?;
                 let user = Variant::UserType("BufferInfo".to_string(), bytes);
-                Variant::serialize(&user).unwrap();
+                res.append(&mut Variant::serialize(&user)?);
             }
             Variant::Message(v) => {
                 let bytes = Message::serialize(v)?;
                 let user = Variant::UserType("Message".to_string(), bytes);
-                Variant::serialize(&user).unwrap();
+                res.append(&mut Variant::serialize(&user)?);
             }
             Variant::DateTime(v) => {
                 res.extend(primitive::QDATETIME.to_be_bytes().iter());
@@ -320,17 +320,17 @@ impl Deserialize for Variant {
             primitive::QDATETIME => {
                 trace!(target: "primitive::Variant", "Parsing Variant: DateTime");
                 let (vlen, value): (usize, DateTime) = Deserialize::parse(&b[len..])?;
-                return Ok((len + vlen, Variant::DateTime(value.clone())));
+                return Ok((len + vlen, Variant::DateTime(value)));
             }
             primitive::QDATE => {
                 trace!(target: "primitive::Variant", "Parsing Variant: Date");
-                let (vlen, value): (usize, Date) = Deserialize::parse(&b[len..])?;
-                return Ok((len + vlen, Variant::Date(value.clone())));
+                let (vlen, value): (usize, Option<Date>) = Deserialize::parse(&b[len..])?;
+                return Ok((len + vlen, Variant::Date(value)));
             }
             primitive::QTIME => {
                 trace!(target: "primitive::Variant", "Parsing Variant: Time");
-                let (vlen, value): (usize, Time) = Deserialize::parse(&b[len..])?;
-                return Ok((len + vlen, Variant::Time(value.clone())));
+                let (vlen, value): (usize, Option<Time>) = Deserialize::parse(&b[len..])?;
+                return Ok((len + vlen, Variant::Time(value)));
             }
             primitive::BOOL => {
                 let (vlen, value) = bool::parse(&b[len..])?;
@@ -634,6 +634,19 @@ mod tests {
         assert_eq!(res, test_buffer_info);
     }
 
+    #[test]
+    pub fn buffer_info_variant_roundtrip() {
+        let test_buffer_info = Variant::BufferInfo(BufferInfo {
+            id: 1,
+            network_id: 2,
+            buffer_type: primitive::BufferType::Channel,
+            name: "#test".to_string(),
+        });
+
+        let bytes = test_buffer_info.serialize().unwrap();
+        assert_eq!(Variant::parse(&bytes).unwrap(), (bytes.len(), test_buffer_info));
+    }
+
     #[test]
     fn char_serialize() {
         assert_eq!(
@@ -716,10 +729,13 @@ mod tests {
 
     #[test]
     fn datetime_serialize() {
-        let datetime =
-            Variant::DateTime(DateTime::parse("2020-02-19 13:00 +0200", "%Y-%m-%d %R %z").unwrap());
-        let date = Variant::Date(Date::parse("2020-02-19", "%Y-%m-%d").unwrap());
-        let time = Variant::Time(Time::parse("13:00", "%R").unwrap());
+        let datetime = Variant::DateTime(
+            time::OffsetDateTime::parse("2020-02-19 13:00 +0200", "%Y-%m-%d %R %z")
+                .unwrap()
+                .into(),
+        );
+        let date = Variant::Date(Some(Date::parse("2020-02-19", "%Y-%m-%d").unwrap()));
+        let time = Variant::Time(Some(Time::parse("13:00", "%R").unwrap()));
 
         assert_eq!(
             datetime.serialize().unwrap(),
@@ -739,10 +755,13 @@ mod tests {
 
     #[test]
     fn datetime_deserialize() {
-        let datetime =
-            Variant::DateTime(DateTime::parse("2020-02-19 13:00 +0200", "%Y-%m-%d %R %z").unwrap());
-        let date = Variant::Date(Date::parse("2020-02-19", "%Y-%m-%d").unwrap());
-        let time = Variant::Time(Time::parse("13:00", "%R").unwrap());
+        let datetime = Variant::DateTime(
+            time::OffsetDateTime::parse("2020-02-19 13:00 +0200", "%Y-%m-%d %R %z")
+                .unwrap()
+                .into(),
+        );
+        let date = Variant::Date(Some(Date::parse("2020-02-19", "%Y-%m-%d").unwrap()));
+        let time = Variant::Time(Some(Time::parse("13:00", "%R").unwrap()));
 
         assert_eq!(
             (18, datetime),
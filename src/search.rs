@@ -0,0 +1,371 @@
+//! In-process full-text search over received messages
+//!
+//! The [`SearchIndex`] is an inverted index over the words of the message content.
+//! Messages are added one by one as they arrive, like from a `DisplayMessage` RPC or a backlog reply.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    ops::Range,
+};
+
+use time::OffsetDateTime;
+
+use crate::formatting;
+use crate::hostmask::{wildcard_match, Hostmask};
+use crate::primitive::{BufferId, Message, MessageType, MsgId};
+
+/// Location of a message in the index
+type Key = (BufferId, MsgId);
+
+/// What is kept about a message to filter and highlight results
+#[derive(Clone, Debug)]
+struct Document {
+    network_id: i32,
+    msg_type: MessageType,
+    time: OffsetDateTime,
+    nick: String,
+    tokens: Vec<(String, Range<usize>)>,
+}
+
+/// A search, all set filters have to match
+///
+/// Every word of `text` has to be contained in the message, a word ending in `*`
+/// matches all words starting with it. Matching is case insensitive and `text` is split
+/// into words like the content, so punctuation is ignored.
+#[derive(Clone, Debug, Default)]
+pub struct SearchQuery {
+    pub text: String,
+    /// Wildcard matched against the nick of the sender
+    pub sender: Option<String>,
+    /// Only search these buffers, all buffers if empty
+    pub buffers: Vec<BufferId>,
+    pub network_id: Option<i32>,
+    /// Only messages of one of these types
+    pub msg_types: Option<MessageType>,
+    /// Only messages sent at or after this time
+    pub since: Option<OffsetDateTime>,
+    /// Only messages sent before this time
+    pub until: Option<OffsetDateTime>,
+    /// Maximum number of hits, unlimited if 0
+    pub limit: usize,
+}
+
+impl SearchQuery {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+/// A message matching a [`SearchQuery`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchHit {
+    pub buffer_id: BufferId,
+    pub msg_id: MsgId,
+    /// Byte ranges of the matched words in the content stripped of formatting
+    /// as returned by [`formatting::strip`]
+    pub highlights: Vec<Range<usize>>,
+}
+
+/// Inverted index over the content of messages
+#[derive(Clone, Debug, Default)]
+pub struct SearchIndex {
+    documents: HashMap<Key, Document>,
+    terms: BTreeMap<String, BTreeSet<Key>>,
+}
+
+impl SearchIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a message to the index, replacing a message with the same buffer and id
+    pub fn insert(&mut self, message: &Message) {
+        let key = (BufferId(message.buffer.id), MsgId::from(message.msg_id));
+        self.remove(key.0, key.1);
+
+        let tokens = tokenize(&formatting::strip(&message.content));
+        for (token, _) in &tokens {
+            self.terms.entry(token.clone()).or_default().insert(key);
+        }
+
+        self.documents.insert(
+            key,
+            Document {
+                network_id: message.buffer.network_id,
+                msg_type: message.msg_type,
+                time: message.time(),
+                nick: Hostmask::parse(&message.sender).nick,
+                tokens,
+            },
+        );
+    }
+
+    /// Remove a message from the index
+    pub fn remove(&mut self, buffer_id: BufferId, msg_id: MsgId) {
+        if let Some(document) = self.documents.remove(&(buffer_id, msg_id)) {
+            for (token, _) in document.tokens {
+                if let Some(keys) = self.terms.get_mut(&token) {
+                    keys.remove(&(buffer_id, msg_id));
+                    if keys.is_empty() {
+                        self.terms.remove(&token);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Remove all messages of a buffer, like after the buffer was removed
+    pub fn remove_buffer(&mut self, buffer_id: BufferId) {
+        let keys: Vec<Key> = self
+            .documents
+            .keys()
+            .filter(|(id, _)| *id == buffer_id)
+            .copied()
+            .collect();
+
+        for (buffer_id, msg_id) in keys {
+            self.remove(buffer_id, msg_id);
+        }
+    }
+
+    /// Number of indexed messages
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// Search the index, hits are sorted from the newest to the oldest message
+    pub fn search(&self, query: &SearchQuery) -> Vec<SearchHit> {
+        let words = query_words(&query.text);
+
+        // Messages containing every word, every message if there are no words
+        let mut candidates: Option<BTreeSet<Key>> = None;
+        for word in &words {
+            let keys = self.lookup(word);
+            candidates = Some(match candidates {
+                Some(candidates) => candidates.intersection(&keys).copied().collect(),
+                None => keys,
+            });
+        }
+
+        let candidates =
+            candidates.unwrap_or_else(|| self.documents.keys().copied().collect::<BTreeSet<Key>>());
+
+        let mut hits: Vec<SearchHit> = candidates
+            .into_iter()
+            .filter_map(|key| {
+                let document = self.documents.get(&key)?;
+                if !query_matches(query, key, document) {
+                    return None;
+                }
+
+                let highlights = document
+                    .tokens
+                    .iter()
+                    .filter(|(token, _)| words.iter().any(|word| word_matches(word, token)))
+                    .map(|(_, range)| range.clone())
+                    .collect();
+
+                Some(SearchHit {
+                    buffer_id: key.0,
+                    msg_id: key.1,
+                    highlights,
+                })
+            })
+            .collect();
+
+        hits.sort_by(|a, b| b.msg_id.cmp(&a.msg_id).then(a.buffer_id.cmp(&b.buffer_id)));
+        if query.limit > 0 {
+            hits.truncate(query.limit);
+        }
+
+        hits
+    }
+
+    /// Messages containing the word, a trailing `*` matches by prefix
+    fn lookup(&self, word: &str) -> BTreeSet<Key> {
+        match word.strip_suffix('*') {
+            Some(prefix) => self
+                .terms
+                .range(prefix.to_string()..)
+                .take_while(|(term, _)| term.starts_with(prefix))
+                .flat_map(|(_, keys)| keys.iter().copied())
+                .collect(),
+            None => self.terms.get(word).cloned().unwrap_or_default(),
+        }
+    }
+}
+
+/// Check the filters of the query against a message
+fn query_matches(query: &SearchQuery, key: Key, document: &Document) -> bool {
+    (query.buffers.is_empty() || query.buffers.contains(&key.0))
+        && query
+            .network_id
+            .is_none_or(|network_id| document.network_id == network_id)
+        && query
+            .msg_types
+            .is_none_or(|msg_types| document.msg_type.intersects(msg_types))
+        && query.since.is_none_or(|since| document.time >= since)
+        && query.until.is_none_or(|until| document.time < until)
+        && query
+            .sender
+            .as_ref()
+            .is_none_or(|sender| wildcard_match(sender, &document.nick))
+}
+
+fn word_matches(word: &str, token: &str) -> bool {
+    match word.strip_suffix('*') {
+        Some(prefix) => token.starts_with(prefix),
+        None => token == word,
+    }
+}
+
+/// Split the text of a query into words like the indexed content
+///
+/// A `*` directly following a word is kept for prefix matching.
+fn query_words(text: &str) -> Vec<String> {
+    tokenize(text)
+        .into_iter()
+        .map(|(mut word, range)| {
+            if text[range.end..].starts_with('*') {
+                word.push('*');
+            }
+
+            word
+        })
+        .collect()
+}
+
+/// Split text into lowercase words with their byte ranges
+///
+/// Words are runs of alphanumeric characters, everything else separates words.
+fn tokenize(text: &str) -> Vec<(String, Range<usize>)> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+
+    for (pos, c) in text
+        .char_indices()
+        .chain(std::iter::once((text.len(), ' ')))
+    {
+        match (start, c.is_alphanumeric()) {
+            (None, true) => start = Some(pos),
+            (Some(begin), false) => {
+                tokens.push((text[begin..pos].to_lowercase(), begin..pos));
+                start = None;
+            }
+            _ => (),
+        }
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(buffer_id: i32, msg_id: i32, sender: &str, content: &str) -> Message {
+        let mut message = Message {
+            sender: sender.to_string(),
+            ..Message::test(msg_id, content).at(1609846597 + msg_id as i64 * 60)
+        };
+        message.buffer.id = buffer_id;
+        message.buffer.network_id = buffer_id * 10;
+
+        message
+    }
+
+    fn get_index() -> SearchIndex {
+        let mut index = SearchIndex::new();
+        index.insert(&message(1, 1, "alice!a@host", "Hello World"));
+        index.insert(&message(1, 2, "bob!b@host", "hello again, world!"));
+        index.insert(&message(2, 3, "alice!a@host", "\x02Rust\x02 is neat"));
+        index.insert(&message(2, 4, "carol!c@host", "Rusty nails"));
+        index
+    }
+
+    fn ids(hits: &[SearchHit]) -> Vec<i64> {
+        hits.iter().map(|hit| hit.msg_id.0).collect()
+    }
+
+    #[test]
+    fn search_words() {
+        let index = get_index();
+
+        let hits = index.search(&SearchQuery::new("world HELLO"));
+        assert_eq!(ids(&hits), vec![2, 1]);
+        assert_eq!(hits[0].highlights, vec![0..5, 13..18]);
+        assert_eq!(hits[1].buffer_id, BufferId(1));
+
+        let hits = index.search(&SearchQuery::new("rust*"));
+        assert_eq!(ids(&hits), vec![4, 3]);
+        assert_eq!(hits[1].highlights, vec![0..4]);
+
+        assert!(index.search(&SearchQuery::new("hello rust")).is_empty());
+    }
+
+    #[test]
+    fn search_punctuation() {
+        let index = get_index();
+
+        let hits = index.search(&SearchQuery::new("world! \"hello,\""));
+        assert_eq!(ids(&hits), vec![2, 1]);
+        assert_eq!(hits[0].highlights, vec![0..5, 13..18]);
+
+        assert_eq!(ids(&index.search(&SearchQuery::new("(rust*)"))), vec![4, 3]);
+        assert_eq!(
+            ids(&index.search(&SearchQuery::new("again,world"))),
+            vec![2]
+        );
+    }
+
+    #[test]
+    fn search_filters() {
+        let index = get_index();
+
+        let query = SearchQuery {
+            sender: Some(s!("ALICE")),
+            ..Default::default()
+        };
+        assert_eq!(ids(&index.search(&query)), vec![3, 1]);
+
+        let query = SearchQuery {
+            buffers: vec![BufferId(2)],
+            limit: 1,
+            ..Default::default()
+        };
+        assert_eq!(ids(&index.search(&query)), vec![4]);
+
+        let query = SearchQuery {
+            network_id: Some(10),
+            since: Some(message(1, 2, "", "").time()),
+            ..SearchQuery::new("hello")
+        };
+        assert_eq!(ids(&index.search(&query)), vec![2]);
+
+        let query = SearchQuery {
+            msg_types: Some(MessageType::NOTICE),
+            ..Default::default()
+        };
+        assert!(index.search(&query).is_empty());
+    }
+
+    #[test]
+    fn search_update() {
+        let mut index = get_index();
+
+        index.insert(&message(1, 1, "alice!a@host", "replaced"));
+        assert_eq!(ids(&index.search(&SearchQuery::new("hello"))), vec![2]);
+        assert_eq!(ids(&index.search(&SearchQuery::new("replaced"))), vec![1]);
+
+        index.remove_buffer(BufferId(1));
+        assert_eq!(index.len(), 2);
+        assert!(index.search(&SearchQuery::new("replaced")).is_empty());
+    }
+}
@@ -0,0 +1,636 @@
+//! Paginated loading of backlog on top of the [`BacklogManager`]
+//!
+//! The [`BacklogPager`] keeps track of which message ids of a buffer are already known
+//! so older pages can be requested on demand and gaps left by a reconnect can be filled.
+//! A [`Session`] fills these gaps by itself once the [`BufferSyncer`] of a new connection arrived.
+//!
+//! [`Session`]: crate::session::Session
+//! [`BufferSyncer`]: crate::message::objects::BufferSyncer
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    ops::Range,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use crate::message::objects::{BacklogManager, PendingBacklog};
+use crate::primitive::{BufferId, Message, MsgId};
+
+/// Sorted, non overlapping ranges of message ids for which all messages are known
+///
+/// Message ids are global to the core, so a range does not mean that
+/// every id in it belongs to the buffer, just that no message of the buffer is missing in it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MsgIdRanges {
+    ranges: Vec<Range<MsgId>>,
+}
+
+impl MsgIdRanges {
+    /// Mark a range of ids as known, merging it with overlapping and adjacent ranges
+    pub fn insert(&mut self, range: Range<MsgId>) {
+        if range.is_empty() {
+            return;
+        }
+
+        self.ranges.push(range);
+        self.ranges.sort_by_key(|range| range.start);
+
+        let mut merged: Vec<Range<MsgId>> = Vec::with_capacity(self.ranges.len());
+        for range in self.ranges.drain(..) {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+
+        self.ranges = merged;
+    }
+
+    /// The id lies in a known range
+    pub fn contains(&self, id: MsgId) -> bool {
+        self.ranges.iter().any(|range| range.contains(&id))
+    }
+
+    pub fn ranges(&self) -> &[Range<MsgId>] {
+        &self.ranges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Start of the oldest known range
+    pub fn oldest(&self) -> Option<MsgId> {
+        self.ranges.first().map(|range| range.start)
+    }
+
+    /// End of the newest known range, this id itself is not known
+    pub fn newest(&self) -> Option<MsgId> {
+        self.ranges.last().map(|range| range.end)
+    }
+
+    /// The missing ranges between the known ranges
+    pub fn gaps(&self) -> Vec<Range<MsgId>> {
+        self.ranges
+            .windows(2)
+            .map(|ranges| ranges[0].end..ranges[1].start)
+            .collect()
+    }
+}
+
+/// What is known about the backlog of a single buffer
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BufferBacklog {
+    pub known: MsgIdRanges,
+    /// The oldest message of the buffer is known
+    pub complete: bool,
+    /// The newest range is up to date with the core and is extended by [`BacklogPager::record`],
+    /// a buffer is live from its first message of the current connection until [`BacklogPager::disconnected`]
+    pub live: bool,
+}
+
+/// Number of messages requested per page when filling gaps after a reconnect
+pub const GAP_FILL_LIMIT: i32 = 100;
+
+/// Loads backlog page by page and tracks known and missing message ids per buffer
+///
+/// Cloning the pager shares the tracked state.
+#[derive(Clone, Debug, Default)]
+pub struct BacklogPager {
+    buffers: Arc<Mutex<HashMap<BufferId, BufferBacklog>>>,
+    /// Pages requested by [`BacklogPager::reconnected`] that nobody waits on
+    filling: Arc<Mutex<Vec<BacklogPage>>>,
+}
+
+impl BacklogPager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request up to `limit` messages older than `before`, or the newest messages if `before` is `None`
+    pub fn fetch_before(
+        &self,
+        manager: &mut BacklogManager,
+        buffer_id: BufferId,
+        before: Option<MsgId>,
+        limit: i32,
+    ) -> BacklogPage {
+        self.fetch(manager, buffer_id, None, before, limit)
+    }
+
+    /// Request up to `limit` messages older than the oldest known message of the buffer
+    ///
+    /// Returns `None` if the start of the buffer was already reached.
+    pub fn fetch_previous(
+        &self,
+        manager: &mut BacklogManager,
+        buffer_id: BufferId,
+        limit: i32,
+    ) -> Option<BacklogPage> {
+        let before = match self.buffers.lock().unwrap().get(&buffer_id) {
+            Some(buffer) if buffer.complete => return None,
+            Some(buffer) => buffer.known.oldest(),
+            None => None,
+        };
+
+        Some(self.fetch_before(manager, buffer_id, before, limit))
+    }
+
+    /// Request up to `limit` messages with ids in `first..last`, `None` leaves that side open
+    ///
+    /// If more than `limit` messages are in the range the core sends the newest ones.
+    pub fn fetch(
+        &self,
+        manager: &mut BacklogManager,
+        buffer_id: BufferId,
+        first: Option<MsgId>,
+        last: Option<MsgId>,
+        limit: i32,
+    ) -> BacklogPage {
+        let pending = manager.request_backlog(
+            buffer_id,
+            first.unwrap_or(MsgId(-1)),
+            last.unwrap_or(MsgId(-1)),
+            limit,
+            0,
+        );
+
+        BacklogPage {
+            pending,
+            buffers: self.buffers.clone(),
+            buffer_id,
+            first,
+            last,
+            limit,
+        }
+    }
+
+    /// Request the messages missing since the connection was lost
+    ///
+    /// For every tracked buffer this requests the gaps between known ranges and, if the buffer
+    /// is no longer live, everything newer than the newest known message, which makes it live again.
+    /// Buffers that are not tracked yet are filled from their entry in `last_seen`,
+    /// the `last_seen_msg` of the `BufferSyncer`, up to the newest message.
+    /// A gap bigger than `limit` is only partially filled, call this again
+    /// once the pages resolved until no more pages are returned.
+    pub fn fill_gaps(
+        &self,
+        manager: &mut BacklogManager,
+        last_seen: &HashMap<i32, i64>,
+        limit: i32,
+    ) -> Vec<BacklogPage> {
+        let mut gaps: Vec<(BufferId, MsgId, Option<MsgId>)> = Vec::new();
+        let buffers = self.buffers.lock().unwrap();
+
+        for (buffer_id, last_seen) in last_seen {
+            if *last_seen > 0 && !buffers.contains_key(&BufferId(*buffer_id)) {
+                gaps.push((BufferId(*buffer_id), MsgId(*last_seen), None));
+            }
+        }
+
+        for (buffer_id, buffer) in buffers.iter() {
+            gaps.extend(
+                buffer
+                    .known
+                    .gaps()
+                    .into_iter()
+                    .map(|gap| (*buffer_id, gap.start, Some(gap.end))),
+            );
+
+            if let (false, Some(newest)) = (buffer.live, buffer.known.newest()) {
+                gaps.push((*buffer_id, newest, None));
+            }
+        }
+
+        drop(buffers);
+
+        gaps.sort_by_key(|(buffer_id, start, _)| (*buffer_id, *start));
+        gaps.into_iter()
+            .map(|(buffer_id, start, end)| self.fetch(manager, buffer_id, Some(start), end, limit))
+            .collect()
+    }
+
+    /// Fill the gaps left by a reconnect in the background, see [`BacklogPager::fill_gaps`]
+    ///
+    /// The pages are completed by [`BacklogPager::poll`] as the core responds,
+    /// gaps bigger than `limit` are requested page by page until they are filled.
+    pub fn reconnected(
+        &self,
+        manager: &mut BacklogManager,
+        last_seen: &HashMap<i32, i64>,
+        limit: i32,
+    ) {
+        let pages = self.fill_gaps(manager, last_seen, limit);
+        self.filling.lock().unwrap().extend(pages);
+    }
+
+    /// Complete the pages requested by [`BacklogPager::reconnected`] the core responded to
+    pub fn poll(&self, manager: &mut BacklogManager) {
+        let mut filling = self.filling.lock().unwrap();
+        let mut next = Vec::new();
+
+        filling.retain(|page| match page.pending.try_take() {
+            Some(messages) => {
+                let messages = page.complete(messages);
+
+                // Only the newest messages of the gap were sent, request the rest
+                if page.is_full(&messages) {
+                    let oldest = MsgId::from(messages[0].msg_id);
+                    next.push(self.fetch(
+                        manager,
+                        page.buffer_id,
+                        page.first,
+                        Some(oldest),
+                        page.limit,
+                    ));
+                }
+
+                false
+            }
+            None => true,
+        });
+
+        filling.extend(next);
+    }
+
+    /// Number of pages requested by [`BacklogPager::reconnected`] that are not complete yet
+    pub fn filling(&self) -> usize {
+        self.filling.lock().unwrap().len()
+    }
+
+    /// Record a message that was received live from the core
+    ///
+    /// If the buffer is live the newest known range is extended up to the message.
+    /// Otherwise this is the first message of the buffer since connecting,
+    /// it starts a new range and makes the buffer live.
+    pub fn record(&self, message: &Message) {
+        let id = MsgId::from(message.msg_id);
+        let mut buffers = self.buffers.lock().unwrap();
+        let buffer = buffers.entry(BufferId(message.buffer.id)).or_default();
+
+        match buffer.known.newest() {
+            Some(newest) if buffer.live && newest <= id => {
+                buffer.known.insert(newest..MsgId(id.0 + 1))
+            }
+            _ => buffer.known.insert(id..MsgId(id.0 + 1)),
+        }
+
+        buffer.live = true;
+    }
+
+    /// The connection to the core was lost, live messages no longer extend the known ranges
+    pub fn disconnected(&self) {
+        for buffer in self.buffers.lock().unwrap().values_mut() {
+            buffer.live = false;
+        }
+    }
+
+    /// What is known about the backlog of the buffer
+    pub fn buffer(&self, buffer_id: BufferId) -> Option<BufferBacklog> {
+        self.buffers.lock().unwrap().get(&buffer_id).cloned()
+    }
+
+    /// Forget everything known about the buffer
+    pub fn remove_buffer(&self, buffer_id: BufferId) {
+        self.buffers.lock().unwrap().remove(&buffer_id);
+    }
+}
+
+/// A requested page of backlog, resolves with the messages sorted from oldest to newest
+///
+/// The known ranges of the [`BacklogPager`] are updated once the page resolved.
+#[derive(Debug)]
+pub struct BacklogPage {
+    pending: PendingBacklog,
+    buffers: Arc<Mutex<HashMap<BufferId, BufferBacklog>>>,
+    buffer_id: BufferId,
+    first: Option<MsgId>,
+    last: Option<MsgId>,
+    limit: i32,
+}
+
+impl BacklogPage {
+    /// Block until the core responded
+    pub fn wait(self) -> Vec<Message> {
+        let messages = self.pending.wait();
+        self.complete(messages)
+    }
+
+    /// With more messages in the range than the limit the core only sends the newest
+    fn is_full(&self, messages: &[Message]) -> bool {
+        self.limit > 0 && !messages.is_empty() && messages.len() >= self.limit as usize
+    }
+
+    fn complete(&self, mut messages: Vec<Message>) -> Vec<Message> {
+        messages.sort_by_key(|message| message.msg_id);
+
+        let full = self.is_full(&messages);
+        let oldest = messages.first().map(|message| MsgId::from(message.msg_id));
+        let newest = messages
+            .last()
+            .map(|message| MsgId::from(message.msg_id + 1));
+
+        let start = match (full, oldest) {
+            (true, Some(oldest)) => oldest,
+            _ => self.first.unwrap_or(MsgId(0)),
+        };
+        let end = self.last.or(newest).unwrap_or(start);
+
+        let mut buffers = self.buffers.lock().unwrap();
+        let buffer = buffers.entry(self.buffer_id).or_default();
+
+        buffer.known.insert(start..end);
+        if !full && self.first.is_none() {
+            buffer.complete = true;
+        }
+        if self.last.is_none() {
+            buffer.live = true;
+        }
+
+        messages
+    }
+}
+
+impl Future for BacklogPage {
+    type Output = Vec<Message>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match Pin::new(&mut self.pending).poll(cx) {
+            Poll::Ready(messages) => Poll::Ready(self.complete(messages)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::message::objects::BacklogRequest;
+    use crate::primitive::MessageFlags;
+
+    use super::*;
+
+    fn message(buffer_id: i32, msg_id: i32) -> Message {
+        let mut message = Message {
+            flags: MessageFlags::BACKLOG,
+            ..Message::test(msg_id, "backlog")
+        };
+        message.buffer.id = buffer_id;
+
+        message
+    }
+
+    fn respond(manager: &mut BacklogManager, first: i64, last: i64, limit: i32, ids: &[i32]) {
+        respond_buffer(manager, 1, first, last, limit, ids)
+    }
+
+    fn respond_buffer(
+        manager: &mut BacklogManager,
+        buffer_id: i32,
+        first: i64,
+        last: i64,
+        limit: i32,
+        ids: &[i32],
+    ) {
+        let request = BacklogRequest::Buffer {
+            buffer_id: BufferId(buffer_id),
+            first: MsgId(first),
+            last: MsgId(last),
+            limit,
+            additional: 0,
+        };
+
+        // The core sends the newest messages first
+        let messages = ids.iter().rev().map(|id| message(buffer_id, *id)).collect();
+        assert!(manager.receive_backlog(&request, messages));
+    }
+
+    fn ids(messages: &[Message]) -> Vec<i64> {
+        messages
+            .iter()
+            .map(|message| MsgId::from(message.msg_id).0)
+            .collect()
+    }
+
+    #[test]
+    fn msgidranges_insert() {
+        let mut ranges = MsgIdRanges::default();
+        ranges.insert(MsgId(10)..MsgId(20));
+        ranges.insert(MsgId(30)..MsgId(40));
+        ranges.insert(MsgId(5)..MsgId(5));
+        assert_eq!(ranges.gaps(), vec![MsgId(20)..MsgId(30)]);
+        assert!(ranges.contains(MsgId(10)));
+        assert!(!ranges.contains(MsgId(20)));
+
+        ranges.insert(MsgId(20)..MsgId(25));
+        ranges.insert(MsgId(24)..MsgId(30));
+        assert_eq!(ranges.ranges(), &[MsgId(10)..MsgId(40)]);
+        assert_eq!(ranges.oldest(), Some(MsgId(10)));
+        assert_eq!(ranges.newest(), Some(MsgId(40)));
+        assert!(ranges.gaps().is_empty());
+    }
+
+    #[test]
+    fn backlogpager_pages() {
+        crate::message::init_test_sync_proxy();
+
+        let mut manager = BacklogManager::default();
+        let pager = BacklogPager::new();
+
+        let page = pager.fetch_before(&mut manager, BufferId(1), None, 3);
+        respond(&mut manager, -1, -1, 3, &[7, 9, 12]);
+        assert_eq!(ids(&page.wait()), vec![7, 9, 12]);
+
+        let buffer = pager.buffer(BufferId(1)).unwrap();
+        assert_eq!(buffer.known.ranges(), &[MsgId(7)..MsgId(13)]);
+        assert!(buffer.live);
+        assert!(!buffer.complete);
+
+        let page = pager.fetch_previous(&mut manager, BufferId(1), 3).unwrap();
+        respond(&mut manager, -1, 7, 3, &[2, 4]);
+        assert_eq!(ids(&page.wait()), vec![2, 4]);
+
+        let buffer = pager.buffer(BufferId(1)).unwrap();
+        assert_eq!(buffer.known.ranges(), &[MsgId(0)..MsgId(13)]);
+        assert!(buffer.complete);
+        assert!(pager.fetch_previous(&mut manager, BufferId(1), 3).is_none());
+        assert_eq!(manager.pending_requests(), 0);
+    }
+
+    #[test]
+    fn backlogpager_fill_gaps() {
+        crate::message::init_test_sync_proxy();
+
+        let mut manager = BacklogManager::default();
+        let pager = BacklogPager::new();
+
+        let page = pager.fetch_before(&mut manager, BufferId(1), None, 2);
+        respond(&mut manager, -1, -1, 2, &[10, 11]);
+        page.wait();
+
+        pager.record(&message(1, 15));
+        pager.disconnected();
+        pager.record(&message(1, 30));
+        assert_eq!(
+            pager.buffer(BufferId(1)).unwrap().known.ranges(),
+            &[MsgId(10)..MsgId(16), MsgId(30)..MsgId(31)]
+        );
+
+        // The buffer is live again since the message after the reconnect
+        let pages = pager.fill_gaps(&mut manager, &HashMap::new(), 2);
+        assert_eq!(pages.len(), 1);
+        respond(&mut manager, 16, 30, 2, &[20, 25]);
+
+        let messages: Vec<Vec<Message>> = pages.into_iter().map(BacklogPage::wait).collect();
+        assert_eq!(ids(&messages[0]), vec![20, 25]);
+
+        // The gap was bigger than the limit and is only partially filled
+        let buffer = pager.buffer(BufferId(1)).unwrap();
+        assert_eq!(
+            buffer.known.ranges(),
+            &[MsgId(10)..MsgId(16), MsgId(20)..MsgId(31)]
+        );
+        assert_eq!(buffer.known.gaps(), vec![MsgId(16)..MsgId(20)]);
+        assert!(buffer.live);
+
+        let pages = pager.fill_gaps(&mut manager, &HashMap::new(), 2);
+        assert_eq!(pages.len(), 1);
+        respond(&mut manager, 16, 20, 2, &[]);
+        pages.into_iter().for_each(|page| {
+            page.wait();
+        });
+
+        let buffer = pager.buffer(BufferId(1)).unwrap();
+        assert_eq!(buffer.known.ranges(), &[MsgId(10)..MsgId(31)]);
+        assert!(pager.fill_gaps(&mut manager, &HashMap::new(), 2).is_empty());
+    }
+
+    #[test]
+    fn backlogpager_reconnect() {
+        crate::message::init_test_sync_proxy();
+
+        let mut manager = BacklogManager::default();
+        let pager = BacklogPager::new();
+
+        let page = pager.fetch_before(&mut manager, BufferId(1), None, 2);
+        respond(&mut manager, -1, -1, 2, &[10, 11]);
+        page.wait();
+        pager.record(&message(1, 15));
+
+        pager.disconnected();
+        pager.reconnected(&mut manager, &HashMap::new(), 2);
+        assert_eq!(pager.filling(), 1);
+
+        // More messages than the limit were sent while disconnected
+        respond(&mut manager, 16, -1, 2, &[25, 30]);
+        pager.poll(&mut manager);
+        assert_eq!(pager.filling(), 1);
+
+        respond(&mut manager, 16, 25, 2, &[20]);
+        pager.poll(&mut manager);
+        assert_eq!(pager.filling(), 0);
+        assert_eq!(manager.pending_requests(), 0);
+
+        pager.record(&message(1, 40));
+        pager.record(&message(1, 41));
+
+        let buffer = pager.buffer(BufferId(1)).unwrap();
+        assert!(buffer.live);
+        assert_eq!(buffer.known.ranges(), &[MsgId(10)..MsgId(42)]);
+    }
+
+    #[test]
+    fn backlogpager_reconnect_after_live_traffic() {
+        crate::message::init_test_sync_proxy();
+
+        let mut manager = BacklogManager::default();
+        let pager = BacklogPager::new();
+
+        // Message ids are global, the messages of one buffer are rarely adjacent
+        for (buffer_id, msg_id) in [(1, 10), (2, 11), (1, 12), (2, 13), (1, 14)] {
+            pager.record(&message(buffer_id, msg_id));
+        }
+        assert_eq!(
+            pager.buffer(BufferId(1)).unwrap().known.ranges(),
+            &[MsgId(10)..MsgId(15)]
+        );
+        assert_eq!(
+            pager.buffer(BufferId(2)).unwrap().known.ranges(),
+            &[MsgId(11)..MsgId(14)]
+        );
+
+        pager.disconnected();
+
+        // Buffer 3 was never seen by the pager, it is filled from its last seen message
+        let last_seen = vec![(1, 12), (3, 8)].into_iter().collect();
+        let pages = pager.fill_gaps(&mut manager, &last_seen, 10);
+        assert_eq!(pages.len(), 3);
+        assert_eq!(manager.pending_requests(), 3);
+
+        respond_buffer(&mut manager, 1, 15, -1, 10, &[20]);
+        respond_buffer(&mut manager, 2, 14, -1, 10, &[]);
+        respond_buffer(&mut manager, 3, 8, -1, 10, &[8, 16]);
+        pages.into_iter().for_each(|page| {
+            page.wait();
+        });
+
+        let buffer = pager.buffer(BufferId(1)).unwrap();
+        assert!(buffer.live);
+        assert_eq!(buffer.known.ranges(), &[MsgId(10)..MsgId(21)]);
+
+        let buffer = pager.buffer(BufferId(3)).unwrap();
+        assert!(buffer.live);
+        assert_eq!(buffer.known.ranges(), &[MsgId(8)..MsgId(17)]);
+
+        assert!(pager.fill_gaps(&mut manager, &last_seen, 10).is_empty());
+    }
+
+    #[test]
+    fn session_fills_gaps_after_reconnect() {
+        use crate::message::objects::{BufferSyncer, Types};
+        use crate::message::{DisplayMessage, InitData, RpcCall, SessionInit};
+        use crate::session::{Session, SessionManager};
+
+        crate::message::init_test_sync_proxy();
+
+        let mut session = Session::default();
+        session.rpc(RpcCall::DisplayMessage(DisplayMessage {
+            message: message(1, 15),
+        }));
+        assert!(session
+            .backlog_pager
+            .buffer(BufferId(1))
+            .unwrap()
+            .known
+            .contains(MsgId(15)));
+
+        session.session_init(SessionInit {
+            identities: vec![],
+            buffers: vec![],
+            network_ids: vec![],
+        });
+        session.init(InitData {
+            class_name: s!("BufferSyncer"),
+            object_name: s!(""),
+            init_data: Types::BufferSyncer(BufferSyncer {
+                last_seen_msg: vec![(2, 5)].into_iter().collect(),
+                ..Default::default()
+            }),
+        });
+        assert_eq!(session.backlog_pager.filling(), 2);
+        assert_eq!(session.backlog_manager.pending_requests(), 2);
+
+        respond(&mut session.backlog_manager, 16, -1, GAP_FILL_LIMIT, &[20]);
+        respond_buffer(&mut session.backlog_manager, 2, 5, -1, GAP_FILL_LIMIT, &[5]);
+        session.backlog_pager.poll(&mut session.backlog_manager);
+        session.rpc(RpcCall::DisplayMessage(DisplayMessage {
+            message: message(1, 25),
+        }));
+
+        let buffer = session.backlog_pager.buffer(BufferId(1)).unwrap();
+        assert_eq!(session.backlog_pager.filling(), 0);
+        assert_eq!(buffer.known.ranges(), &[MsgId(15)..MsgId(26)]);
+    }
+}
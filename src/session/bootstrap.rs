@@ -0,0 +1,294 @@
+//! Requesting the InitData of all session objects after the [`SessionInit`]
+//!
+//! The [`SessionBootstrap`] sends the InitRequests a client has to make after login,
+//! applies the received InitData to the session and reports the progress as [`SessionEvent`]s.
+
+use std::collections::BTreeSet;
+
+use crate::message::objects::Types;
+use crate::message::{Class, InitData, SessionInit};
+
+use super::{SessionEvent, SessionManager};
+
+/// Objects that exist exactly once per session
+const SINGLETONS: [Class; 6] = [
+    Class::AliasManager,
+    Class::BufferSyncer,
+    Class::BufferViewManager,
+    Class::CoreInfo,
+    Class::HighlightRuleManager,
+    Class::IgnoreListManager,
+];
+
+/// Requests the InitData of all objects of a session and tracks which have been received
+///
+/// Some objects can only be requested once others are known:
+///  - A `CertManager` exists per identity and is named by the identity id
+///  - A `Network` is requested for every id in `network_ids` and includes its users and channels
+///  - The `BufferViewConfig`s are requested by the [`BufferViewManager`] once its ids arrive
+///
+/// Every received object emits a [`SessionEvent::InitProgress`], the last one is followed
+/// by [`SessionEvent::FullySynced`].
+///
+/// [`BufferViewManager`]: crate::message::objects::BufferViewManager
+#[derive(Clone, Debug, Default)]
+pub struct SessionBootstrap {
+    pending: BTreeSet<(Class, String)>,
+    received: usize,
+    synced: bool,
+}
+
+impl SessionBootstrap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Initialize the session and request the InitData of all its objects
+    pub fn start<S: SessionManager>(&mut self, session: &mut S, data: SessionInit) {
+        let identity_ids: Vec<i32> = data
+            .identities
+            .iter()
+            .map(|identity| identity.identity_id)
+            .collect();
+        let network_ids = data.network_ids.clone();
+
+        session.session_init(data);
+
+        for class in SINGLETONS {
+            self.request(class, String::new());
+        }
+
+        for id in identity_ids {
+            self.request(Class::CertManager, id.to_string());
+        }
+
+        for id in network_ids {
+            self.request(Class::Network, id.to_string());
+        }
+    }
+
+    /// Apply received InitData to the session and update the progress
+    pub fn init<S: SessionManager>(&mut self, session: &mut S, data: InitData) {
+        let key = (
+            Class::from(data.class_name.as_str()),
+            data.object_name.clone(),
+        );
+
+        // The BufferViewManager requests the configs itself when its data is applied
+        if let Types::BufferViewManager(manager) = &data.init_data {
+            for id in manager.buffer_view_configs.keys() {
+                if session
+                    .buffer_view_manager()
+                    .buffer_view_config(*id)
+                    .is_none()
+                {
+                    self.pending
+                        .insert((Class::BufferViewConfig, id.to_string()));
+                }
+            }
+        }
+
+        session.init(data);
+
+        if self.pending.remove(&key) {
+            self.received += 1;
+
+            let (received, total) = self.progress();
+            session.emit(SessionEvent::InitProgress { received, total });
+
+            if self.pending.is_empty() && !self.synced {
+                self.synced = true;
+                session.emit(SessionEvent::FullySynced);
+            }
+        }
+    }
+
+    /// The number of received objects and the number of all requested objects
+    pub fn progress(&self) -> (usize, usize) {
+        (self.received, self.received + self.pending.len())
+    }
+
+    /// All requested objects have been received
+    pub fn is_synced(&self) -> bool {
+        self.synced
+    }
+
+    /// Objects that were requested but not received yet
+    pub fn pending(&self) -> impl Iterator<Item = (&Class, &str)> {
+        self.pending
+            .iter()
+            .map(|(class, object_name)| (class, object_name.as_str()))
+    }
+
+    fn request(&mut self, class: Class, object_name: String) {
+        crate::message::SYNC_PROXY
+            .get()
+            .unwrap()
+            .init_request(class.clone(), &object_name);
+
+        self.pending.insert((class, object_name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::message::objects::{
+        AliasManager, BufferSyncer, BufferViewConfig, BufferViewManager, CertManager,
+        HighlightRuleManager, Identity, IgnoreListManager, Network,
+    };
+    use crate::message::InitRequest;
+    use crate::primitive::IdentityId;
+    use crate::session::Session;
+
+    fn init_data(class_name: &str, object_name: &str, init_data: Types) -> InitData {
+        InitData {
+            class_name: class_name.to_owned(),
+            object_name: object_name.to_owned(),
+            init_data,
+        }
+    }
+
+    #[test]
+    fn bootstrap_session() {
+        crate::message::init_test_sync_proxy();
+
+        let mut session = Session::default();
+        let events = session.subscribe();
+        let mut bootstrap = SessionBootstrap::new();
+
+        let identity = |identity_id| Identity {
+            identity_id,
+            ..Default::default()
+        };
+
+        bootstrap.start(
+            &mut session,
+            SessionInit {
+                identities: vec![identity(7), identity(8)],
+                buffers: vec![],
+                network_ids: vec![1, 2],
+            },
+        );
+        assert_eq!(bootstrap.progress(), (0, 10));
+
+        // One CertManager is requested per identity, named by the identity id
+        let requests = crate::message::test_init_requests();
+        for object_name in ["7", "8"] {
+            assert!(requests.contains(&InitRequest {
+                class_name: s!("CertManager"),
+                object_name: s!(object_name),
+            }));
+        }
+
+        bootstrap.init(
+            &mut session,
+            init_data(
+                "BufferViewManager",
+                "",
+                Types::BufferViewManager(BufferViewManager {
+                    buffer_view_configs: vec![(1, None)].into_iter().collect(),
+                }),
+            ),
+        );
+        assert_eq!(bootstrap.progress(), (1, 11));
+
+        let data = vec![
+            init_data(
+                "AliasManager",
+                "",
+                Types::AliasManager(AliasManager::default()),
+            ),
+            init_data(
+                "BufferSyncer",
+                "",
+                Types::BufferSyncer(BufferSyncer::default()),
+            ),
+            init_data("CoreInfo", "", Types::Unknown(vec![])),
+            init_data(
+                "HighlightRuleManager",
+                "",
+                Types::HighlightRuleManager(HighlightRuleManager::default()),
+            ),
+            init_data(
+                "IgnoreListManager",
+                "",
+                Types::IgnoreListManager(IgnoreListManager::default()),
+            ),
+            init_data(
+                "CertManager",
+                "7",
+                Types::CertManager(CertManager {
+                    ssl_key: s!("key"),
+                    ..Default::default()
+                }),
+            ),
+            init_data(
+                "CertManager",
+                "8",
+                Types::CertManager(CertManager::default()),
+            ),
+            init_data("Network", "1", Types::Network(Network::default())),
+            init_data("Network", "2", Types::Network(Network::default())),
+        ];
+        for data in data {
+            bootstrap.init(&mut session, data);
+        }
+
+        assert!(!bootstrap.is_synced());
+        assert_eq!(
+            bootstrap.pending().collect::<Vec<_>>(),
+            vec![(&Class::BufferViewConfig, "1")]
+        );
+
+        // Objects that were not requested don't count
+        bootstrap.init(
+            &mut session,
+            init_data("Network", "3", Types::Network(Network::default())),
+        );
+        bootstrap.init(
+            &mut session,
+            init_data(
+                "BufferViewConfig",
+                "1",
+                Types::BufferViewConfig(BufferViewConfig {
+                    buffer_view_id: 1,
+                    ..Default::default()
+                }),
+            ),
+        );
+
+        assert!(bootstrap.is_synced());
+        assert_eq!(bootstrap.progress(), (11, 11));
+        assert!(session.buffer_view_manager.buffer_view_config(1).is_some());
+        assert_eq!(session.networks.len(), 3);
+        assert_eq!(session.cert_managers.len(), 2);
+        assert_eq!(session.cert_managers[&IdentityId(7)].ssl_key, "key");
+        assert_eq!(
+            session.cert_managers[&IdentityId(8)].identity_id,
+            IdentityId(8)
+        );
+
+        let events: Vec<SessionEvent> = events
+            .try_iter()
+            .filter(|event| {
+                matches!(
+                    event,
+                    SessionEvent::InitProgress { .. } | SessionEvent::FullySynced
+                )
+            })
+            .collect();
+        assert_eq!(events.len(), 12);
+        assert_eq!(
+            events[10..],
+            [
+                SessionEvent::InitProgress {
+                    received: 11,
+                    total: 11
+                },
+                SessionEvent::FullySynced
+            ]
+        );
+    }
+}
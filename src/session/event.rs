@@ -0,0 +1,543 @@
+use num_traits::FromPrimitive;
+
+use crate::message::objects::ConnectionState;
+use crate::message::{Class, InitData, RpcCall, SyncMessage};
+use crate::primitive::{BufferId, IdentityId, Message, MessageType, MsgId, NetworkId, Variant};
+
+/// A change applied to the session by [`SessionManager`]
+///
+/// Events are emitted through [`SessionManager::emit`] for every applied
+/// SyncMessage, InitData and RpcCall so frontends can update incrementally.
+///
+/// [`SessionManager`]: super::SessionManager
+/// [`SessionManager::emit`]: super::SessionManager::emit
+#[derive(Clone, Debug, PartialEq)]
+pub enum SessionEvent {
+    /// The session was initialized from the `SessionInit`
+    SessionInitialized,
+    /// A message was received through a `DisplayMessage` RPC
+    MessageReceived(Message),
+    BufferActivityChanged {
+        buffer_id: BufferId,
+        activity: MessageType,
+    },
+    HighlightCountChanged {
+        buffer_id: BufferId,
+        count: i32,
+    },
+    LastSeenMsgChanged {
+        buffer_id: BufferId,
+        msg_id: MsgId,
+    },
+    MarkerLineChanged {
+        buffer_id: BufferId,
+        msg_id: MsgId,
+    },
+    BufferMarkedAsRead {
+        buffer_id: BufferId,
+    },
+    BufferRenamed {
+        buffer_id: BufferId,
+        name: String,
+    },
+    BufferRemoved {
+        buffer_id: BufferId,
+    },
+    /// The `source` buffer was merged into the `target` buffer
+    BuffersMerged {
+        source: BufferId,
+        target: BufferId,
+    },
+    NickChanged {
+        network_id: NetworkId,
+        old_nick: String,
+        new_nick: String,
+    },
+    /// A user joined a channel with the given channel user modes
+    UserJoined {
+        network_id: NetworkId,
+        channel: String,
+        nick: String,
+        modes: String,
+    },
+    UserParted {
+        network_id: NetworkId,
+        channel: String,
+        nick: String,
+    },
+    UserQuit {
+        network_id: NetworkId,
+        nick: String,
+    },
+    /// The channel user modes of a user changed, `modes` are all modes the user now has
+    /// if `added` is `None`, otherwise the modes that were added or removed
+    UserModesChanged {
+        network_id: NetworkId,
+        channel: String,
+        nick: String,
+        modes: String,
+        added: Option<bool>,
+    },
+    TopicChanged {
+        network_id: NetworkId,
+        channel: String,
+        topic: String,
+    },
+    /// A channel mode was set or unset, with its parameter if it has one
+    ChannelModeChanged {
+        network_id: NetworkId,
+        channel: String,
+        mode: char,
+        value: Option<String>,
+        added: bool,
+    },
+    NetworkConnectionStateChanged {
+        network_id: NetworkId,
+        state: ConnectionState,
+    },
+    NetworkConnectedChanged {
+        network_id: NetworkId,
+        connected: bool,
+    },
+    NetworkNickChanged {
+        network_id: NetworkId,
+        nick: String,
+    },
+    IdentityUpdated {
+        identity_id: IdentityId,
+    },
+    IdentityCreated {
+        identity_id: IdentityId,
+    },
+    IdentityRemoved {
+        identity_id: IdentityId,
+    },
+    NetworkCreated {
+        network_id: NetworkId,
+    },
+    NetworkRemoved {
+        network_id: NetworkId,
+    },
+    /// Another requested object was initialized by the [`SessionBootstrap`]
+    ///
+    /// [`SessionBootstrap`]: super::SessionBootstrap
+    InitProgress {
+        received: usize,
+        total: usize,
+    },
+    /// All objects requested by the [`SessionBootstrap`] have been initialized
+    ///
+    /// [`SessionBootstrap`]: super::SessionBootstrap
+    FullySynced,
+    /// An object was initialized from InitData
+    ObjectInitialized {
+        class: Class,
+        object_name: String,
+    },
+    /// A SyncMessage without a more specific event was applied
+    ObjectSynced {
+        class: Class,
+        object_name: String,
+        slot_name: String,
+    },
+}
+
+impl SessionEvent {
+    /// The events caused by applying a SyncMessage
+    ///
+    /// Has to be called before the message is applied, for example renames
+    /// are only known by the name of the object before the change.
+    pub fn from_sync(msg: &SyncMessage) -> Vec<SessionEvent> {
+        let (network_id, name) = split_object_name(&msg.object_name);
+        let name = name.to_string();
+
+        let events = match (&msg.class_name, msg.slot_name.as_str()) {
+            (Class::BufferSyncer, slot) => buffer_syncer_event(msg, slot).into_iter().collect(),
+            (Class::IrcUser, "setNick") => param(msg, 0)
+                .map(|new_nick| SessionEvent::NickChanged {
+                    network_id,
+                    old_nick: name,
+                    new_nick,
+                })
+                .into_iter()
+                .collect(),
+            (Class::IrcUser, "quit") => vec![SessionEvent::UserQuit {
+                network_id,
+                nick: name,
+            }],
+            (Class::IrcChannel, "joinIrcUsers") => {
+                let nicks: Vec<String> = param(msg, 0).unwrap_or_default();
+                let modes: Vec<String> = param(msg, 1).unwrap_or_default();
+
+                nicks
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, nick)| SessionEvent::UserJoined {
+                        network_id,
+                        channel: name.clone(),
+                        nick,
+                        modes: modes.get(i).cloned().unwrap_or_default(),
+                    })
+                    .collect()
+            }
+            (Class::IrcChannel, "part") => param(msg, 0)
+                .map(|nick| SessionEvent::UserParted {
+                    network_id,
+                    channel: name,
+                    nick,
+                })
+                .into_iter()
+                .collect(),
+            (Class::IrcChannel, "setTopic") => param(msg, 0)
+                .map(|topic| SessionEvent::TopicChanged {
+                    network_id,
+                    channel: name,
+                    topic,
+                })
+                .into_iter()
+                .collect(),
+            (Class::IrcChannel, slot @ ("addChannelMode" | "removeChannelMode")) => param(msg, 0)
+                .map(|mode| SessionEvent::ChannelModeChanged {
+                    network_id,
+                    channel: name,
+                    mode,
+                    value: param(msg, 1).filter(|value: &String| !value.is_empty()),
+                    added: slot == "addChannelMode",
+                })
+                .into_iter()
+                .collect(),
+            (Class::IrcChannel, slot @ ("addUserMode" | "removeUserMode" | "setUserModes")) => {
+                match (param(msg, 0), param(msg, 1)) {
+                    (Some(nick), Some(modes)) => vec![SessionEvent::UserModesChanged {
+                        network_id,
+                        channel: name,
+                        nick,
+                        modes,
+                        added: match slot {
+                            "addUserMode" => Some(true),
+                            "removeUserMode" => Some(false),
+                            _ => None,
+                        },
+                    }],
+                    _ => Vec::new(),
+                }
+            }
+            (Class::Network, "setConnectionState") => param(msg, 0)
+                .and_then(ConnectionState::from_i32)
+                .map(|state| SessionEvent::NetworkConnectionStateChanged { network_id, state })
+                .into_iter()
+                .collect(),
+            (Class::Network, "setConnected") => param(msg, 0)
+                .map(|connected| SessionEvent::NetworkConnectedChanged {
+                    network_id,
+                    connected,
+                })
+                .into_iter()
+                .collect(),
+            (Class::Network, "setMyNick") => param(msg, 0)
+                .map(|nick| SessionEvent::NetworkNickChanged { network_id, nick })
+                .into_iter()
+                .collect(),
+            (Class::Identity, _) => msg
+                .object_name
+                .parse()
+                .map(|identity_id| SessionEvent::IdentityUpdated {
+                    identity_id: IdentityId(identity_id),
+                })
+                .into_iter()
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        if events.is_empty() {
+            vec![SessionEvent::ObjectSynced {
+                class: msg.class_name.clone(),
+                object_name: msg.object_name.clone(),
+                slot_name: msg.slot_name.clone(),
+            }]
+        } else {
+            events
+        }
+    }
+
+    /// The event caused by applying InitData
+    pub fn from_init(data: &InitData) -> SessionEvent {
+        SessionEvent::ObjectInitialized {
+            class: Class::from(data.class_name.as_str()),
+            object_name: data.object_name.clone(),
+        }
+    }
+
+    /// The event caused by an RpcCall, if it affects the session
+    pub fn from_rpc(rpc: &RpcCall) -> Option<SessionEvent> {
+        match rpc {
+            RpcCall::DisplayMessage(msg) => {
+                Some(SessionEvent::MessageReceived(msg.message.clone()))
+            }
+            RpcCall::IdentityCreated(created) => Some(SessionEvent::IdentityCreated {
+                identity_id: IdentityId(created.identity.identity_id),
+            }),
+            RpcCall::IdentityRemoved(removed) => Some(SessionEvent::IdentityRemoved {
+                identity_id: removed.identity_id,
+            }),
+            RpcCall::NetworkCreated(created) => Some(SessionEvent::NetworkCreated {
+                network_id: created.network_id,
+            }),
+            RpcCall::NetworkRemoved(removed) => Some(SessionEvent::NetworkRemoved {
+                network_id: removed.network_id,
+            }),
+            RpcCall::CreateIdentity(_)
+            | RpcCall::RemoveIdentity(_)
+            | RpcCall::CreateNetwork(_)
+            | RpcCall::RemoveNetwork(_)
+            | RpcCall::KickClient(_)
+            | RpcCall::NotImplemented => None,
+        }
+    }
+}
+
+fn buffer_syncer_event(msg: &SyncMessage, slot: &str) -> Option<SessionEvent> {
+    let buffer_id = param(msg, 0)?;
+
+    Some(match slot {
+        "setBufferActivity" => SessionEvent::BufferActivityChanged {
+            buffer_id,
+            activity: MessageType::from_bits_truncate(param(msg, 1)?),
+        },
+        "setHighlightCount" => SessionEvent::HighlightCountChanged {
+            buffer_id,
+            count: param(msg, 1)?,
+        },
+        "setLastSeenMsg" => SessionEvent::LastSeenMsgChanged {
+            buffer_id,
+            msg_id: param(msg, 1)?,
+        },
+        "setMarkerLine" => SessionEvent::MarkerLineChanged {
+            buffer_id,
+            msg_id: param(msg, 1)?,
+        },
+        "markBufferAsRead" => SessionEvent::BufferMarkedAsRead { buffer_id },
+        "renameBuffer" => SessionEvent::BufferRenamed {
+            buffer_id,
+            name: param(msg, 1)?,
+        },
+        "removeBuffer" => SessionEvent::BufferRemoved { buffer_id },
+        "mergeBuffersPermanently" => SessionEvent::BuffersMerged {
+            target: buffer_id,
+            source: param(msg, 1)?,
+        },
+        _ => return None,
+    })
+}
+
+/// Get a parameter of the message without removing it
+fn param<T: TryFrom<Variant>>(msg: &SyncMessage, index: usize) -> Option<T> {
+    msg.params.get(index).cloned()?.try_into().ok()
+}
+
+/// Split an object name like `1/#channel` into the network id and the name
+///
+/// Objects without a network, like `Network` itself, get their whole name parsed as id.
+fn split_object_name(object_name: &str) -> (NetworkId, &str) {
+    let (network_id, name) = object_name.split_once('/').unwrap_or((object_name, ""));
+
+    (NetworkId(network_id.parse().unwrap_or_default()), name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sync(
+        class_name: Class,
+        object_name: &str,
+        slot_name: &str,
+        params: Vec<Variant>,
+    ) -> SyncMessage {
+        SyncMessage {
+            class_name,
+            object_name: object_name.to_string(),
+            slot_name: slot_name.to_string(),
+            params,
+        }
+    }
+
+    #[test]
+    fn event_from_sync() {
+        assert_eq!(
+            SessionEvent::from_sync(&sync(
+                Class::IrcUser,
+                "1/audron",
+                "setNick",
+                vec![Variant::String(s!("audron_"))]
+            )),
+            vec![SessionEvent::NickChanged {
+                network_id: NetworkId(1),
+                old_nick: s!("audron"),
+                new_nick: s!("audron_"),
+            }]
+        );
+
+        assert_eq!(
+            SessionEvent::from_sync(&sync(
+                Class::IrcChannel,
+                "1/#test",
+                "joinIrcUsers",
+                vec![
+                    Variant::StringList(vec![s!("a"), s!("b")]),
+                    Variant::StringList(vec![s!("o"), s!("")]),
+                ]
+            )),
+            vec![
+                SessionEvent::UserJoined {
+                    network_id: NetworkId(1),
+                    channel: s!("#test"),
+                    nick: s!("a"),
+                    modes: s!("o"),
+                },
+                SessionEvent::UserJoined {
+                    network_id: NetworkId(1),
+                    channel: s!("#test"),
+                    nick: s!("b"),
+                    modes: s!(""),
+                },
+            ]
+        );
+
+        assert_eq!(
+            SessionEvent::from_sync(&sync(
+                Class::IrcChannel,
+                "2/#test",
+                "addChannelMode",
+                vec![Variant::char('k'), Variant::String(s!("secret"))]
+            )),
+            vec![SessionEvent::ChannelModeChanged {
+                network_id: NetworkId(2),
+                channel: s!("#test"),
+                mode: 'k',
+                value: Some(s!("secret")),
+                added: true,
+            }]
+        );
+
+        assert_eq!(
+            SessionEvent::from_sync(&sync(
+                Class::Network,
+                "3",
+                "setConnectionState",
+                vec![Variant::i32(3)]
+            )),
+            vec![SessionEvent::NetworkConnectionStateChanged {
+                network_id: NetworkId(3),
+                state: ConnectionState::Initialized,
+            }]
+        );
+    }
+
+    #[test]
+    fn event_from_buffer_syncer() {
+        assert_eq!(
+            SessionEvent::from_sync(&sync(
+                Class::BufferSyncer,
+                "",
+                "setBufferActivity",
+                vec![Variant::i32(5), Variant::i32(MessageType::PLAIN.bits())]
+            )),
+            vec![SessionEvent::BufferActivityChanged {
+                buffer_id: BufferId(5),
+                activity: MessageType::PLAIN,
+            }]
+        );
+
+        assert_eq!(
+            SessionEvent::from_sync(&sync(
+                Class::BufferSyncer,
+                "",
+                "setLastSeenMsg",
+                vec![Variant::i32(5), Variant::i64(1337)]
+            )),
+            vec![SessionEvent::LastSeenMsgChanged {
+                buffer_id: BufferId(5),
+                msg_id: MsgId(1337),
+            }]
+        );
+
+        // Unknown slots and broken parameters fall back to the generic event
+        assert_eq!(
+            SessionEvent::from_sync(&sync(Class::BufferSyncer, "", "setMarkerLine", vec![])),
+            vec![SessionEvent::ObjectSynced {
+                class: Class::BufferSyncer,
+                object_name: s!(""),
+                slot_name: s!("setMarkerLine"),
+            }]
+        );
+    }
+
+    #[test]
+    fn session_emits_events() {
+        use crate::message::{DisplayMessage, SessionInit};
+        use crate::session::{Session, SessionManager};
+
+        let mut session = Session::default();
+        let events = session.subscribe();
+        let dropped = session.subscribe();
+        drop(dropped);
+
+        session.session_init(SessionInit {
+            identities: vec![],
+            buffers: vec![],
+            network_ids: vec![],
+        });
+        session.sync(sync(
+            Class::BufferSyncer,
+            "",
+            "setHighlightCount",
+            vec![Variant::i32(1), Variant::i32(2)],
+        ));
+
+        // Nothing is emitted for changes to objects the session does not know
+        session.networks.insert(1, Default::default());
+        session.sync(sync(
+            Class::Network,
+            "2",
+            "setConnectionState",
+            vec![Variant::i32(3)],
+        ));
+        session.sync(sync(
+            Class::IrcChannel,
+            "1/#quassel",
+            "setTopic",
+            vec![Variant::String(s!("topic"))],
+        ));
+        session.sync(sync(
+            Class::IrcUser,
+            "1/Kitty",
+            "setNick",
+            vec![Variant::String(s!("Cat"))],
+        ));
+        session.sync(sync(
+            Class::BufferViewConfig,
+            "0",
+            "setBufferViewName",
+            vec![],
+        ));
+
+        let message = Message::test(1, "hi");
+        session.rpc(RpcCall::DisplayMessage(DisplayMessage {
+            message: message.clone(),
+        }));
+
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            vec![
+                SessionEvent::SessionInitialized,
+                SessionEvent::HighlightCountChanged {
+                    buffer_id: BufferId(1),
+                    count: 2,
+                },
+                SessionEvent::MessageReceived(message),
+            ]
+        );
+        assert_eq!(session.buffer_syncer.highlight_counts.get(&1), Some(&2));
+    }
+}
@@ -2,27 +2,37 @@ use std::collections::HashMap;
 
 use crate::message::StatefulSyncableServer;
 
+mod backlogpager;
+mod bootstrap;
+mod event;
+pub use backlogpager::*;
+pub use bootstrap::*;
+pub use event::*;
+
 use log::{debug, warn};
 
 use crate::message::{
     objects::{Types, *},
-    Class, InitData, SessionInit, StatefulSyncableClient, SyncMessage, Syncable,
+    Class, InitData, RpcCall, SessionInit, StatefulSyncableClient, SyncMessage, Syncable,
 };
-
-// TODO implement nested types init and sync like BufferViewConfig in BufferViewManager
+use crate::primitive::{IdentityId, NetworkId};
 
 #[derive(Default, Debug)]
 pub struct Session {
+    events: Vec<crossbeam_channel::Sender<SessionEvent>>,
     pub alias_manager: AliasManager,
     pub buffer_syncer: BufferSyncer,
     pub backlog_manager: BacklogManager,
+    pub backlog_pager: BacklogPager,
     pub buffer_view_manager: BufferViewManager,
-    pub cert_manager: CertManager,
+    pub cert_managers: HashMap<IdentityId, CertManager>,
     pub core_info: CoreInfo,
     pub highlight_rule_manager: HighlightRuleManager,
-    pub identities: Vec<Identity>,
+    pub identities: HashMap<IdentityId, Identity>,
     pub ignore_list_manager: IgnoreListManager,
+    pub irc_list_helper: IrcListHelper,
     pub networks: HashMap<i32, Network>,
+    pub network_config: NetworkConfig,
 }
 
 /// The Session Trait is the main point of entry and implements the basic logic
@@ -31,42 +41,169 @@ pub trait SessionManager {
     fn buffer_syncer(&mut self) -> &mut BufferSyncer;
     fn backlog_manager(&mut self) -> &mut BacklogManager;
     fn buffer_view_manager(&mut self) -> &mut BufferViewManager;
-    fn cert_manager(&mut self) -> &mut CertManager;
+    fn cert_managers(&mut self) -> &mut HashMap<IdentityId, CertManager>;
+    fn cert_manager(&mut self, id: IdentityId) -> Option<&mut CertManager>;
     fn core_info(&mut self) -> &mut CoreInfo;
     fn highlight_rule_manager(&mut self) -> &mut HighlightRuleManager;
-    fn identities(&mut self) -> &mut Vec<Identity>;
-    fn identity(&mut self, id: usize) -> Option<&mut Identity>;
+    fn identities(&mut self) -> &mut HashMap<IdentityId, Identity>;
+    fn identity(&mut self, id: IdentityId) -> Option<&mut Identity>;
     fn ignore_list_manager(&mut self) -> &mut IgnoreListManager;
+    fn irc_list_helper(&mut self) -> &mut IrcListHelper;
     fn networks(&mut self) -> &mut HashMap<i32, Network>;
     fn network(&mut self, id: i32) -> Option<&mut Network>;
+    fn network_config(&mut self) -> &mut NetworkConfig;
+
+    /// The pager that fills gaps in the backlog after a reconnect, none by default
+    fn backlog_pager(&mut self) -> Option<&mut BacklogPager> {
+        None
+    }
+
+    /// Called with every change applied to the session, does nothing by default
+    fn emit(&mut self, _event: SessionEvent) {}
 
     fn sync(&mut self, msg: SyncMessage)
     where
         Self: Sized,
     {
-        match msg.class_name {
-            Class::AliasManager => self.alias_manager().sync(msg),
-            Class::BufferSyncer => self.buffer_syncer().sync(msg),
-            Class::BufferViewConfig => (),
-            Class::BufferViewManager => self.buffer_view_manager().sync(msg),
-            Class::CoreInfo => self.core_info().sync(msg),
-            Class::CoreData => (),
-            Class::HighlightRuleManager => self.highlight_rule_manager().sync(msg),
-            Class::Identity => (),
-            Class::IgnoreListManager => self.ignore_list_manager().sync(msg),
-            Class::CertManager => self.cert_manager().sync(msg),
+        // Renames are only known by the object name before the change,
+        // but the events are only emitted if the message was applied
+        let events = SessionEvent::from_sync(&msg);
+
+        let applied = match msg.class_name {
+            Class::AliasManager => {
+                self.alias_manager().sync(msg);
+                true
+            }
+            Class::BacklogManager => {
+                self.backlog_manager().sync(msg);
+
+                if let Some(pager) = self.backlog_pager().cloned() {
+                    pager.poll(self.backlog_manager());
+                }
+
+                true
+            }
+            Class::BufferSyncer => {
+                self.buffer_syncer().sync(msg);
+                true
+            }
+            Class::BufferViewConfig => {
+                let id: i32 = match msg.object_name.parse() {
+                    Ok(id) => id,
+                    Err(_) => {
+                        warn!("Invalid BufferViewConfig object name {}", msg.object_name);
+                        return;
+                    }
+                };
+                match self.buffer_view_manager().buffer_view_configs.get_mut(&id) {
+                    Some(Some(config)) => {
+                        config.sync(msg);
+                        true
+                    }
+                    Some(None) => {
+                        warn!("BufferViewConfig {} is not initialized yet", id);
+                        false
+                    }
+                    None => {
+                        warn!("Could not find BufferViewConfig {}", id);
+                        false
+                    }
+                }
+            }
+            Class::BufferViewManager => {
+                self.buffer_view_manager().sync(msg);
+                true
+            }
+            Class::CoreInfo => {
+                self.core_info().sync(msg);
+                true
+            }
+            Class::CoreData => false,
+            Class::HighlightRuleManager => {
+                self.highlight_rule_manager().sync(msg);
+                true
+            }
+            Class::Identity => {
+                let id = match msg.object_name.parse() {
+                    Ok(id) => IdentityId(id),
+                    Err(_) => {
+                        warn!("Invalid Identity object name {}", msg.object_name);
+                        return;
+                    }
+                };
+                match self.identity(id) {
+                    Some(identity) => {
+                        identity.sync(msg);
+                        true
+                    }
+                    None => {
+                        warn!("Could not find Identity {:?}", id);
+                        false
+                    }
+                }
+            }
+            Class::IgnoreListManager => {
+                self.ignore_list_manager().sync(msg);
+                true
+            }
+            Class::IrcListHelper => {
+                self.irc_list_helper().sync(msg);
+                true
+            }
+            Class::CertManager => match msg.object_name.parse() {
+                Ok(id) => match self.cert_manager(IdentityId(id)) {
+                    Some(cert_manager) => {
+                        cert_manager.sync(msg);
+                        true
+                    }
+                    None => {
+                        warn!("Could not find CertManager {}", id);
+                        false
+                    }
+                },
+                Err(_) => {
+                    warn!("invalid CertManager object name {}", msg.object_name);
+                    false
+                }
+            },
             Class::Network => {
-                let id: i32 = msg.object_name.parse().unwrap();
-                if let Some(network) = self.network(id) {
-                    // network.sync()
+                let id: i32 = match msg.object_name.parse() {
+                    Ok(id) => id,
+                    Err(_) => {
+                        warn!("Invalid Network object name {}", msg.object_name);
+                        return;
+                    }
+                };
+                match self.network(id) {
+                    Some(network) => {
+                        network.sync(msg);
+                        true
+                    }
+                    None => {
+                        warn!("Could not find Network {:?}", id);
+                        false
+                    }
                 }
             }
-            Class::NetworkInfo => (),
-            Class::NetworkConfig => (),
+            Class::NetworkInfo => false,
+            Class::NetworkConfig => {
+                self.network_config().sync(msg);
+                true
+            }
             Class::IrcChannel => {
-                let mut object_name = msg.object_name.split('/');
-                let network_id: i32 = object_name.next().unwrap().parse().unwrap();
-                let channel = object_name.next().unwrap();
+                let (network_id, channel) = match msg.object_name.split_once('/') {
+                    Some((network_id, channel)) => match network_id.parse::<i32>() {
+                        Ok(network_id) => (network_id, channel),
+                        Err(_) => {
+                            warn!("Invalid IrcChannel object name {}", msg.object_name);
+                            return;
+                        }
+                    },
+                    None => {
+                        warn!("Invalid IrcChannel object name {}", msg.object_name);
+                        return;
+                    }
+                };
 
                 debug!("Syncing IrcChannel {} in Network {:?}", channel, network_id);
 
@@ -75,7 +212,8 @@ pub trait SessionManager {
                         warn!(
                             "Could not find IrcChannel {} in Network {:?}",
                             channel, network_id
-                        )
+                        );
+                        false
                     } else {
                         match msg.slot_name.as_str() {
                             "addChannelMode" => {
@@ -102,48 +240,256 @@ pub trait SessionManager {
                                     .unwrap()
                                     .remove_channel_mode(mode_type, mode, get_param!(msg));
                             }
+                            "joinIrcUsers" => {
+                                if msg.params.len() < 2 {
+                                    warn!("Missing parameters for joinIrcUsers in {}", channel);
+                                    return;
+                                }
+
+                                let mut msg = msg.clone();
+                                network.join_irc_users(channel, get_param!(msg), get_param!(msg));
+                            }
+                            "part" => {
+                                if msg.params.is_empty() {
+                                    warn!("Missing parameters for part in {}", channel);
+                                    return;
+                                }
+
+                                let mut msg = msg.clone();
+                                let nick: String = get_param!(msg);
+                                network.part_irc_channel(&nick, channel);
+                            }
                             _ => network
                                 .irc_channels
                                 .get_mut(channel)
                                 .unwrap()
                                 .sync(msg.clone()),
                         }
+
+                        true
                     }
                 } else {
-                    warn!("Could not find Network {:?}", network_id)
+                    warn!("Could not find Network {:?}", network_id);
+                    false
+                }
+            }
+            Class::IrcUser => {
+                let object_name = msg.object_name.clone();
+
+                match object_name.split_once('/') {
+                    Some((network_id, nick)) => {
+                        let network_id: i32 = match network_id.parse() {
+                            Ok(network_id) => network_id,
+                            Err(_) => {
+                                warn!("Invalid IrcUser object name {}", object_name);
+                                return;
+                            }
+                        };
+
+                        debug!("Syncing IrcUser {} in Network {:?}", nick, network_id);
+
+                        match self.network(network_id) {
+                            Some(network) => network.sync_irc_user(nick, msg),
+                            None => {
+                                warn!("Could not find Network {:?}", network_id);
+                                false
+                            }
+                        }
+                    }
+                    None => {
+                        warn!("Invalid IrcUser object name {}", object_name);
+                        false
+                    }
                 }
             }
-            Class::IrcUser => (),
-            Class::Unknown => (),
+            Class::Unknown => false,
+        };
+
+        if applied {
+            for event in events {
+                self.emit(event);
+            }
         }
     }
 
     fn session_init(&mut self, data: SessionInit) {
-        *self.identities() = data.identities;
+        *self.identities() = data
+            .identities
+            .into_iter()
+            .map(|identity| (IdentityId(identity.identity_id), identity))
+            .collect();
+
+        // Everything known about the backlog is from a previous connection
+        if let Some(pager) = self.backlog_pager() {
+            pager.disconnected();
+        }
+
+        self.emit(SessionEvent::SessionInitialized);
+    }
+
+    fn rpc(&mut self, rpc: RpcCall) {
+        match &rpc {
+            RpcCall::DisplayMessage(msg) => {
+                if let Some(pager) = self.backlog_pager() {
+                    pager.record(&msg.message);
+                }
+            }
+            RpcCall::IdentityCreated(created) => {
+                let identity = created.identity.clone();
+                self.identities()
+                    .insert(IdentityId(identity.identity_id), identity);
+            }
+            RpcCall::IdentityRemoved(removed) => {
+                self.identities().remove(&removed.identity_id);
+                self.cert_managers().remove(&removed.identity_id);
+            }
+            // The network itself is filled by the InitData requested afterwards
+            RpcCall::NetworkCreated(created) => {
+                let id = created.network_id;
+                self.networks().entry(id.0).or_insert_with(|| Network {
+                    network_info: NetworkInfo {
+                        network_id: id,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                });
+            }
+            RpcCall::NetworkRemoved(removed) => {
+                self.networks().remove(&removed.network_id.0);
+            }
+            _ => (),
+        }
+
+        if let Some(event) = SessionEvent::from_rpc(&rpc) {
+            self.emit(event);
+        }
     }
 
     fn init(&mut self, data: InitData) {
+        let event = SessionEvent::from_init(&data);
+
         match data.init_data {
             Types::AliasManager(data) => self.alias_manager().init(data),
-            Types::BufferSyncer(data) => self.buffer_syncer().init(data),
+            Types::BufferSyncer(data) => {
+                self.buffer_syncer().init(data);
+
+                // The BufferSyncer is synced once per connection, fill what was missed since the last one
+                if let Some(pager) = self.backlog_pager().cloned() {
+                    let last_seen = self.buffer_syncer().last_seen_msg.clone();
+                    pager.reconnected(self.backlog_manager(), &last_seen, GAP_FILL_LIMIT);
+                }
+            }
             Types::BufferViewConfig(data) => self.buffer_view_manager().init_buffer_view_config(data),
             Types::BufferViewManager(data) => self.buffer_view_manager().init(data),
+            Types::CoreInfo(data) => self.core_info().init(data),
             Types::CoreData(data) => self.core_info().set_core_data(data),
             Types::HighlightRuleManager(data) => self.highlight_rule_manager().init(data),
+            Types::Identity(identity) => {
+                let id: i32 = match data.object_name.parse() {
+                    Ok(id) => id,
+                    Err(_) => {
+                        warn!("Invalid Identity object name {}", data.object_name);
+                        return;
+                    }
+                };
+                self.identities().insert(
+                    IdentityId(id),
+                    Identity {
+                        identity_id: id,
+                        ..identity
+                    },
+                );
+            }
             Types::IgnoreListManager(data) => self.ignore_list_manager().init(data),
-            Types::CertManager(data) => self.cert_manager().init(data),
-            Types::Network(network) => {
-                let id: i32 = data.object_name.parse().unwrap();
+            // Only keeps track of running lists, which the InitData would drop
+            Types::IrcListHelper(_) => (),
+            Types::CertManager(cert_manager) => match data.object_name.parse() {
+                Ok(id) => {
+                    self.cert_managers().insert(
+                        IdentityId(id),
+                        CertManager {
+                            identity_id: IdentityId(id),
+                            ..cert_manager
+                        },
+                    );
+                }
+                Err(_) => warn!("invalid CertManager object name {}", data.object_name),
+            },
+            Types::Network(mut network) => {
+                let id: i32 = match data.object_name.parse() {
+                    Ok(id) => id,
+                    Err(_) => {
+                        warn!("Invalid Network object name {}", data.object_name);
+                        return;
+                    }
+                };
+                network.network_info.network_id = NetworkId(id);
                 self.networks().insert(id, network);
             }
-            Types::NetworkInfo(_) => (),
-            Types::NetworkConfig(_) => (),
+            Types::NetworkInfo(info) => {
+                let id: i32 = match data.object_name.parse() {
+                    Ok(id) => id,
+                    Err(_) => {
+                        warn!("Invalid NetworkInfo object name {}", data.object_name);
+                        return;
+                    }
+                };
+                match self.network(id) {
+                    Some(network) => {
+                        network.network_info = NetworkInfo {
+                            network_id: NetworkId(id),
+                            ..info
+                        }
+                    }
+                    None => warn!("Could not find Network {:?}", id),
+                }
+            }
+            Types::NetworkConfig(config) => *self.network_config() = config,
+            Types::IrcUser(user) => match data.object_name.split_once('/') {
+                Some((network_id, nick)) => match network_id.parse() {
+                    Ok(network_id) => match self.network(network_id) {
+                        Some(network) => network.init_irc_user(nick, user),
+                        None => warn!("Could not find Network {:?}", network_id),
+                    },
+                    Err(_) => warn!("Invalid IrcUser object name {}", data.object_name),
+                },
+                None => warn!("Invalid IrcUser object name {}", data.object_name),
+            },
+            Types::IrcChannel(channel) => match data.object_name.split_once('/') {
+                Some((network_id, name)) => match network_id.parse() {
+                    Ok(network_id) => match self.network(network_id) {
+                        Some(network) => network.init_irc_channel(name, channel),
+                        None => warn!("Could not find Network {:?}", network_id),
+                    },
+                    Err(_) => warn!("Invalid IrcChannel object name {}", data.object_name),
+                },
+                None => warn!("Invalid IrcChannel object name {}", data.object_name),
+            },
             Types::Unknown(_) => (),
         }
+
+        self.emit(event);
+    }
+}
+
+impl Session {
+    /// Receive the [`SessionEvent`]s of this session
+    ///
+    /// Every subscriber gets all events emitted after subscribing,
+    /// subscribers that dropped their receiver are removed.
+    pub fn subscribe(&mut self) -> crossbeam_channel::Receiver<SessionEvent> {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        self.events.push(sender);
+        receiver
     }
 }
 
 impl SessionManager for Session {
+    fn emit(&mut self, event: SessionEvent) {
+        self.events
+            .retain(|sender| sender.send(event.clone()).is_ok());
+    }
+
     fn alias_manager(&mut self) -> &mut AliasManager {
         &mut self.alias_manager
     }
@@ -160,8 +506,12 @@ impl SessionManager for Session {
         &mut self.buffer_view_manager
     }
 
-    fn cert_manager(&mut self) -> &mut CertManager {
-        &mut self.cert_manager
+    fn cert_managers(&mut self) -> &mut HashMap<IdentityId, CertManager> {
+        &mut self.cert_managers
+    }
+
+    fn cert_manager(&mut self, id: IdentityId) -> Option<&mut CertManager> {
+        self.cert_managers.get_mut(&id)
     }
 
     fn core_info(&mut self) -> &mut CoreInfo {
@@ -172,18 +522,22 @@ impl SessionManager for Session {
         &mut self.highlight_rule_manager
     }
 
-    fn identities(&mut self) -> &mut Vec<Identity> {
+    fn identities(&mut self) -> &mut HashMap<IdentityId, Identity> {
         &mut self.identities
     }
 
-    fn identity(&mut self, id: usize) -> Option<&mut Identity> {
-        self.identities.get_mut(id)
+    fn identity(&mut self, id: IdentityId) -> Option<&mut Identity> {
+        self.identities.get_mut(&id)
     }
 
     fn ignore_list_manager(&mut self) -> &mut IgnoreListManager {
         &mut self.ignore_list_manager
     }
 
+    fn irc_list_helper(&mut self) -> &mut IrcListHelper {
+        &mut self.irc_list_helper
+    }
+
     fn networks(&mut self) -> &mut HashMap<i32, Network> {
         &mut self.networks
     }
@@ -191,4 +545,323 @@ impl SessionManager for Session {
     fn network(&mut self, id: i32) -> Option<&mut Network> {
         self.networks.get_mut(&id)
     }
+
+    fn network_config(&mut self) -> &mut NetworkConfig {
+        &mut self.network_config
+    }
+
+    fn backlog_pager(&mut self) -> Option<&mut BacklogPager> {
+        Some(&mut self.backlog_pager)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::primitive::Variant;
+
+    fn sync(
+        class_name: Class,
+        object_name: &str,
+        slot_name: &str,
+        params: Vec<Variant>,
+    ) -> SyncMessage {
+        SyncMessage {
+            class_name,
+            object_name: object_name.to_string(),
+            slot_name: slot_name.to_string(),
+            params,
+        }
+    }
+
+    #[test]
+    fn session_identity_lifecycle() {
+        use crate::message::{IdentityCreated, IdentityRemoved, SessionInit};
+
+        let identity = |identity_id: i32| Identity {
+            identity_id,
+            identity_name: s!("audron"),
+            ..Default::default()
+        };
+
+        let mut session = Session::default();
+        session.session_init(SessionInit {
+            identities: vec![identity(2)],
+            buffers: vec![],
+            network_ids: vec![],
+        });
+        let events = session.subscribe();
+
+        session.rpc(RpcCall::IdentityCreated(IdentityCreated {
+            identity: identity(5),
+        }));
+        session.sync(sync(
+            Class::Identity,
+            "5",
+            "setRealName",
+            vec![Variant::String(s!("Audron"))],
+        ));
+        session.rpc(RpcCall::IdentityRemoved(IdentityRemoved {
+            identity_id: IdentityId(2),
+        }));
+
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            vec![
+                SessionEvent::IdentityCreated {
+                    identity_id: IdentityId(5)
+                },
+                SessionEvent::IdentityUpdated {
+                    identity_id: IdentityId(5)
+                },
+                SessionEvent::IdentityRemoved {
+                    identity_id: IdentityId(2)
+                },
+            ]
+        );
+        assert_eq!(session.identities.len(), 1);
+        assert_eq!(session.identities[&IdentityId(5)].real_name, "Audron");
+    }
+
+    #[test]
+    fn session_network_lifecycle() {
+        use crate::message::{NetworkCreated, NetworkRemoved};
+
+        let mut session = Session::default();
+        let events = session.subscribe();
+
+        session.rpc(RpcCall::NetworkCreated(NetworkCreated {
+            network_id: NetworkId(3),
+        }));
+        assert_eq!(session.networks[&3].network_info.network_id, NetworkId(3));
+
+        session.sync(sync(
+            Class::NetworkConfig,
+            "GlobalNetworkConfig",
+            "setAutoWhoDelay",
+            vec![Variant::i32(5)],
+        ));
+        session.rpc(RpcCall::NetworkRemoved(NetworkRemoved {
+            network_id: NetworkId(3),
+        }));
+
+        assert!(session.networks.is_empty());
+        assert_eq!(session.network_config.auto_who_delay, 5);
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            vec![
+                SessionEvent::NetworkCreated {
+                    network_id: NetworkId(3)
+                },
+                SessionEvent::ObjectSynced {
+                    class: Class::NetworkConfig,
+                    object_name: s!("GlobalNetworkConfig"),
+                    slot_name: s!("setAutoWhoDelay"),
+                },
+                SessionEvent::NetworkRemoved {
+                    network_id: NetworkId(3)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn session_buffer_view_configs() {
+        crate::message::init_test_sync_proxy();
+
+        let mut session = Session::default();
+
+        session.init(InitData {
+            class_name: s!("BufferViewManager"),
+            object_name: s!(""),
+            init_data: Types::BufferViewManager(BufferViewManager {
+                buffer_view_configs: vec![(1, None)].into_iter().collect(),
+            }),
+        });
+        session.sync(sync(
+            Class::BufferViewManager,
+            "",
+            "addBufferViewConfig",
+            vec![Variant::i32(2)],
+        ));
+        assert_eq!(
+            session.buffer_view_manager.buffer_view_configs.get(&2),
+            Some(&None)
+        );
+
+        // Not initialized yet, so the sync is dropped
+        session.sync(sync(
+            Class::BufferViewConfig,
+            "1",
+            "setBufferViewName",
+            vec![Variant::String(s!("Ignored"))],
+        ));
+
+        session.init(InitData {
+            class_name: s!("BufferViewConfig"),
+            object_name: s!("1"),
+            init_data: Types::BufferViewConfig(BufferViewConfig {
+                buffer_view_id: 1,
+                buffers: vec![1, 2],
+                ..Default::default()
+            }),
+        });
+        session.sync(sync(
+            Class::BufferViewConfig,
+            "1",
+            "setBufferViewName",
+            vec![Variant::String(s!("All Chats"))],
+        ));
+        session.sync(sync(
+            Class::BufferViewConfig,
+            "1",
+            "addBuffer",
+            vec![Variant::i32(3), Variant::i32(0)],
+        ));
+
+        let config = session.buffer_view_manager.buffer_view_config(1).unwrap();
+        assert_eq!(config.buffer_view_name, "All Chats");
+        assert_eq!(config.buffers, vec![3, 1, 2]);
+        assert!(session.buffer_view_manager.buffer_view_config(2).is_none());
+
+        // Configs deleted while disconnected are dropped on the next init
+        session.init(InitData {
+            class_name: s!("BufferViewManager"),
+            object_name: s!(""),
+            init_data: Types::BufferViewManager(BufferViewManager {
+                buffer_view_configs: vec![(1, None), (4, None)].into_iter().collect(),
+            }),
+        });
+
+        let mut ids: Vec<i32> = session
+            .buffer_view_manager
+            .buffer_view_configs
+            .keys()
+            .copied()
+            .collect();
+        ids.sort_unstable();
+        assert_eq!(ids, vec![1, 4]);
+        assert!(session.buffer_view_manager.buffer_view_config(1).is_some());
+    }
+
+    #[test]
+    fn session_init_irc_objects() {
+        use crate::message::NetworkList;
+
+        let mut session = Session::default();
+        session.networks.insert(1, Network::default());
+
+        let user = IrcUser {
+            nick: s!("Kitty"),
+            user: s!("kitty"),
+            ..Default::default()
+        };
+        let channel = IrcChannel {
+            name: s!("#quassel"),
+            user_modes: vec![(s!("Kitty"), s!("o"))].into_iter().collect(),
+            ..Default::default()
+        };
+        let identity = Identity {
+            identity_name: s!("Default"),
+            ..Default::default()
+        };
+
+        let objects = vec![
+            ("IrcUser", "1/Kitty", user.to_network_list()),
+            ("IrcChannel", "1/#quassel", channel.to_network_list()),
+            ("Identity", "2", identity.to_network_list()),
+        ];
+        for (class_name, object_name, mut list) in objects {
+            session.init(InitData {
+                class_name: class_name.to_owned(),
+                object_name: object_name.to_owned(),
+                init_data: Types::from_network(class_name, object_name, &mut list),
+            });
+        }
+
+        let network = &session.networks[&1];
+        assert_eq!(network.irc_users["Kitty"].user, "kitty");
+        assert_eq!(network.irc_users["Kitty"].channels, vec![s!("#quassel")]);
+        assert_eq!(network.irc_channels["#quassel"].user_modes["Kitty"], "o");
+        assert_eq!(session.identities[&IdentityId(2)].identity_id, 2);
+        assert_eq!(session.identities[&IdentityId(2)].identity_name, "Default");
+
+        session.sync(sync(
+            Class::IrcChannel,
+            "1/#quassel",
+            "joinIrcUsers",
+            vec![
+                Variant::StringList(vec![s!("kitty"), s!("Dog")]),
+                Variant::StringList(vec![s!(""), s!("v")]),
+            ],
+        ));
+        session.sync(sync(
+            Class::IrcChannel,
+            "1/#quassel",
+            "part",
+            vec![Variant::String(s!("Kitty"))],
+        ));
+
+        let network = &session.networks[&1];
+        assert!(!network.irc_users.contains_key("Kitty"));
+        assert_eq!(network.irc_users["Dog"].channels, vec![s!("#quassel")]);
+        assert_eq!(network.irc_channels["#quassel"].user_modes["Dog"], "v");
+    }
+
+    #[test]
+    fn session_ignores_invalid_objects() {
+        use crate::message::NetworkList;
+
+        let mut session = Session::default();
+        session.networks.insert(1, Network::default());
+
+        let objects = vec![
+            ("IrcUser", "one/Kitty", IrcUser::default().to_network_list()),
+            ("Identity", "Default", Identity::default().to_network_list()),
+        ];
+        for (class_name, object_name, mut list) in objects {
+            session.init(InitData {
+                class_name: class_name.to_owned(),
+                object_name: object_name.to_owned(),
+                init_data: Types::from_network(class_name, object_name, &mut list),
+            });
+        }
+
+        assert!(session.networks[&1].irc_users.is_empty());
+        assert!(session.identities.is_empty());
+
+        session.sync(sync(
+            Class::Identity,
+            "Default",
+            "setRealName",
+            vec![Variant::String(s!("Kitty"))],
+        ));
+        session.sync(sync(
+            Class::BufferViewConfig,
+            "",
+            "setBufferViewName",
+            vec![],
+        ));
+        session.sync(sync(Class::IrcChannel, "#quassel", "setTopic", vec![]));
+        session.sync(sync(Class::IrcChannel, "one/#quassel", "setTopic", vec![]));
+        session.sync(sync(Class::IrcUser, "one/Kitty", "setNick", vec![]));
+        session.sync(sync(Class::Network, "libera", "setNetworkName", vec![]));
+
+        // Messages missing their parameters are dropped as well
+        session
+            .networks
+            .get_mut(&1)
+            .unwrap()
+            .add_irc_channel(s!("#quassel"));
+        session.sync(sync(
+            Class::IrcChannel,
+            "1/#quassel",
+            "joinIrcUsers",
+            vec![],
+        ));
+        session.sync(sync(Class::IrcChannel, "1/#quassel", "part", vec![]));
+
+        assert!(session.networks[&1].irc_channels.contains_key("#quassel"));
+    }
 }
@@ -0,0 +1,521 @@
+//! Persistent local storage of received messages for clients
+//!
+//! The [`MessageStore`] keeps messages in append-only segment files inside a directory,
+//! so messages survive a restart of the client and can be read while offline.
+//!
+//! Every segment is a sequence of records, each a big endian u32 length followed by
+//! the serialized [`Message`]. Once a segment grows beyond [`SEGMENT_SIZE`] a new one is started.
+//! The buffer metadata from [`SessionInit`] is kept in a separate `buffers` file.
+//!
+//! Opening the store only builds an index of where each message is located,
+//! the messages themselves are read from the segments when they are requested.
+//!
+//! The files are only valid for the message features they were written with.
+//!
+//! [`SessionInit`]: crate::message::SessionInit
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    ops::RangeBounds,
+    path::{Path, PathBuf},
+};
+
+use failure::Error;
+
+use crate::util::{read_records, record};
+use crate::{deserialize::*, serialize::*};
+
+use crate::primitive::{BufferId, BufferInfo, Message, MsgId};
+
+/// Size in bytes after which a new segment file is started
+pub const SEGMENT_SIZE: u64 = 8 * 1024 * 1024;
+
+const BUFFERS_FILE: &str = "buffers";
+const SEGMENT_PREFIX: &str = "segment-";
+const SEGMENT_SUFFIX: &str = ".log";
+
+/// Messages stored on disk keyed by their buffer id and message id
+///
+/// Only the location of every message is kept in memory.
+/// Messages are deduplicated, so the same message arriving as a `DisplayMessage`
+/// and in a backlog reply is only stored once.
+#[derive(Debug)]
+pub struct MessageStore {
+    dir: PathBuf,
+    buffers: BTreeMap<BufferId, BufferInfo>,
+    messages: HashMap<BufferId, BTreeMap<MsgId, Location>>,
+    segment: File,
+    segment_index: u32,
+    segment_len: u64,
+}
+
+impl MessageStore {
+    /// Open the store in `dir`, creating the directory if it does not exist
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self, Error> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let mut buffers = BTreeMap::new();
+        let buffers_path = dir.join(BUFFERS_FILE);
+        if buffers_path.exists() {
+            let bytes = fs::read(&buffers_path)?;
+            let (records, _) = read_records(&bytes)?;
+            for record in records {
+                let (_, buffer) = BufferInfo::parse(record)?;
+                buffers.insert(BufferId(buffer.id), buffer);
+            }
+        }
+
+        let mut segments: Vec<u32> = fs::read_dir(&dir)?
+            .filter_map(|entry| {
+                let name = entry.ok()?.file_name().into_string().ok()?;
+                name.strip_prefix(SEGMENT_PREFIX)?
+                    .strip_suffix(SEGMENT_SUFFIX)?
+                    .parse()
+                    .ok()
+            })
+            .collect();
+        segments.sort_unstable();
+
+        let mut messages: HashMap<BufferId, BTreeMap<MsgId, Location>> = HashMap::new();
+        let mut segment_len = 0;
+        for (i, index) in segments.iter().enumerate() {
+            let bytes = fs::read(segment_path(&dir, *index))?;
+            let (records, len) = read_records(&bytes)?;
+
+            if len < bytes.len() && i + 1 < segments.len() {
+                log::warn!(
+                    "segment {} of the message store is truncated after {} bytes, the messages after it are lost",
+                    index,
+                    len
+                );
+            }
+
+            segment_len = len as u64;
+            let mut offset = 0;
+            for record in records {
+                let (buffer_id, msg_id) = parse_key(record)?;
+                let location = Location {
+                    segment: *index,
+                    offset: offset + 4,
+                    len: record.len() as u32,
+                };
+                offset += 4 + record.len() as u64;
+
+                messages
+                    .entry(buffer_id)
+                    .or_default()
+                    .insert(msg_id, location);
+            }
+        }
+
+        let segment_index = segments.last().copied().unwrap_or(0);
+        let segment = open_segment(&dir, segment_index)?;
+
+        // Cut off a truncated record so new records are appended after the last valid one
+        if segment.metadata()?.len() > segment_len {
+            segment.set_len(segment_len)?;
+        }
+
+        Ok(Self {
+            dir,
+            buffers,
+            messages,
+            segment,
+            segment_index,
+            segment_len,
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Store a message
+    ///
+    /// Returns false if the message was already stored.
+    pub fn insert(&mut self, message: &Message) -> Result<bool, Error> {
+        let buffer_id = BufferId(message.buffer.id);
+        let msg_id = MsgId::from(message.msg_id);
+
+        if self.contains(buffer_id, msg_id) {
+            return Ok(false);
+        }
+
+        if self.segment_len >= SEGMENT_SIZE {
+            self.segment_index += 1;
+            self.segment = open_segment(&self.dir, self.segment_index)?;
+            self.segment_len = 0;
+        }
+
+        let record = record(message.serialize()?)?;
+        self.segment.write_all(&record)?;
+
+        let location = Location {
+            segment: self.segment_index,
+            offset: self.segment_len + 4,
+            len: record.len() as u32 - 4,
+        };
+        self.segment_len += record.len() as u64;
+
+        self.messages
+            .entry(buffer_id)
+            .or_default()
+            .insert(msg_id, location);
+
+        Ok(true)
+    }
+
+    /// Store multiple messages like a backlog reply
+    ///
+    /// Returns the number of messages that were not stored before.
+    pub fn insert_all(&mut self, messages: &[Message]) -> Result<usize, Error> {
+        let mut inserted = 0;
+        for message in messages {
+            if self.insert(message)? {
+                inserted += 1;
+            }
+        }
+
+        self.flush()?;
+        Ok(inserted)
+    }
+
+    /// Flush written messages to disk
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.segment.flush()?;
+        Ok(())
+    }
+
+    pub fn contains(&self, buffer_id: BufferId, msg_id: MsgId) -> bool {
+        self.messages
+            .get(&buffer_id)
+            .is_some_and(|messages| messages.contains_key(&msg_id))
+    }
+
+    /// Read a single message from disk
+    pub fn get(&self, buffer_id: BufferId, msg_id: MsgId) -> Result<Option<Message>, Error> {
+        match self
+            .messages
+            .get(&buffer_id)
+            .and_then(|messages| messages.get(&msg_id))
+        {
+            Some(location) => Ok(self.read(std::iter::once(location))?.pop()),
+            None => Ok(None),
+        }
+    }
+
+    /// Read the messages of a buffer with ids in `range` from disk, oldest first
+    pub fn range<R: RangeBounds<MsgId>>(
+        &self,
+        buffer_id: BufferId,
+        range: R,
+    ) -> Result<Vec<Message>, Error> {
+        self.read(self.locations(buffer_id, range))
+    }
+
+    /// Read up to `limit` of the newest messages of a buffer older than `before`, oldest first
+    ///
+    /// Returns the newest messages of the buffer if `before` is `None`.
+    pub fn before(
+        &self,
+        buffer_id: BufferId,
+        before: Option<MsgId>,
+        limit: usize,
+    ) -> Result<Vec<Message>, Error> {
+        let mut locations: Vec<&Location> = match before {
+            Some(before) => self
+                .locations(buffer_id, ..before)
+                .rev()
+                .take(limit)
+                .collect(),
+            None => self.locations(buffer_id, ..).rev().take(limit).collect(),
+        };
+
+        locations.reverse();
+        self.read(locations)
+    }
+
+    /// Id of the newest stored message of a buffer
+    pub fn last_msg_id(&self, buffer_id: BufferId) -> Option<MsgId> {
+        self.messages.get(&buffer_id)?.keys().next_back().copied()
+    }
+
+    /// Number of messages stored for a buffer
+    pub fn len(&self, buffer_id: BufferId) -> usize {
+        self.messages.get(&buffer_id).map_or(0, BTreeMap::len)
+    }
+
+    /// Replace the buffer metadata, usually with the buffers of the `SessionInit`
+    pub fn set_buffers(&mut self, buffers: &[BufferInfo]) -> Result<(), Error> {
+        self.buffers = buffers
+            .iter()
+            .map(|buffer| (BufferId(buffer.id), buffer.clone()))
+            .collect();
+
+        self.write_buffers()
+    }
+
+    /// Add or update the metadata of a single buffer
+    pub fn update_buffer(&mut self, buffer: &BufferInfo) -> Result<(), Error> {
+        self.buffers.insert(BufferId(buffer.id), buffer.clone());
+        self.write_buffers()
+    }
+
+    pub fn buffer(&self, buffer_id: BufferId) -> Option<&BufferInfo> {
+        self.buffers.get(&buffer_id)
+    }
+
+    /// All known buffers ordered by their id
+    pub fn buffers(&self) -> impl Iterator<Item = &BufferInfo> {
+        self.buffers.values()
+    }
+
+    fn locations<R: RangeBounds<MsgId>>(
+        &self,
+        buffer_id: BufferId,
+        range: R,
+    ) -> impl DoubleEndedIterator<Item = &Location> {
+        self.messages
+            .get(&buffer_id)
+            .into_iter()
+            .flat_map(move |messages| messages.range((range.start_bound(), range.end_bound())))
+            .map(|(_, location)| location)
+    }
+
+    /// Read and parse the messages at `locations`, opening each segment only once in a row
+    fn read<'a>(
+        &self,
+        locations: impl IntoIterator<Item = &'a Location>,
+    ) -> Result<Vec<Message>, Error> {
+        let mut messages = Vec::new();
+        let mut file: Option<(u32, File)> = None;
+        let mut buf = Vec::new();
+
+        for location in locations {
+            let file = match file {
+                Some((index, ref mut file)) if index == location.segment => file,
+                _ => {
+                    let segment = File::open(segment_path(&self.dir, location.segment))?;
+                    &mut file.insert((location.segment, segment)).1
+                }
+            };
+
+            buf.resize(location.len as usize, 0);
+            file.seek(SeekFrom::Start(location.offset))?;
+            file.read_exact(&mut buf)?;
+
+            messages.push(Message::parse(&buf)?.1);
+        }
+
+        Ok(messages)
+    }
+
+    /// Write the buffer metadata to a temporary file and move it in place
+    fn write_buffers(&self) -> Result<(), Error> {
+        let mut bytes = Vec::new();
+        for buffer in self.buffers.values() {
+            bytes.append(&mut record(buffer.serialize()?)?);
+        }
+
+        let tmp = self.dir.join(format!("{}.tmp", BUFFERS_FILE));
+        fs::write(&tmp, bytes)?;
+        fs::rename(tmp, self.dir.join(BUFFERS_FILE))?;
+
+        Ok(())
+    }
+}
+
+/// Position of a serialized message inside a segment, without the length prefix of its record
+#[derive(Debug, Clone, Copy)]
+struct Location {
+    segment: u32,
+    offset: u64,
+    len: u32,
+}
+
+#[cfg(feature = "long-message-id")]
+type MsgIdRaw = i64;
+#[cfg(not(feature = "long-message-id"))]
+type MsgIdRaw = i32;
+
+#[cfg(feature = "long-time")]
+type TimeRaw = i64;
+#[cfg(not(feature = "long-time"))]
+type TimeRaw = i32;
+
+/// Parse only the buffer id and message id of a serialized message
+fn parse_key(b: &[u8]) -> Result<(BufferId, MsgId), Error> {
+    // The buffer id follows the message id, timestamp, type and flags
+    let pos = std::mem::size_of::<MsgIdRaw>() + std::mem::size_of::<TimeRaw>() + 4 + 1;
+    if b.len() < pos + 4 {
+        bail!("message store record of {} bytes is too short", b.len());
+    }
+
+    let (_, msg_id) = MsgIdRaw::parse(b)?;
+    let (_, buffer_id) = i32::parse(&b[pos..])?;
+
+    Ok((BufferId(buffer_id), MsgId::from(msg_id)))
+}
+
+fn segment_path(dir: &Path, index: u32) -> PathBuf {
+    dir.join(format!("{}{:08}{}", SEGMENT_PREFIX, index, SEGMENT_SUFFIX))
+}
+
+fn open_segment(dir: &Path, index: u32) -> Result<File, Error> {
+    Ok(OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(segment_path(dir, index))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::primitive::BufferType;
+
+    use super::*;
+
+    fn buffer(id: i32, name: &str) -> BufferInfo {
+        BufferInfo {
+            id,
+            network_id: 1,
+            buffer_type: BufferType::Channel,
+            name: name.to_string(),
+        }
+    }
+
+    fn message(buffer_id: i32, msg_id: i32) -> Message {
+        Message {
+            buffer: buffer(buffer_id, "#test"),
+            ..Message::test(msg_id, &format!("message {}", msg_id))
+        }
+    }
+
+    fn ids(messages: &[Message]) -> Vec<i64> {
+        messages
+            .iter()
+            .map(|message| MsgId::from(message.msg_id).0)
+            .collect()
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("libquassel-store-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn messagestore_dedup_and_range() {
+        let dir = temp_dir("range");
+        let mut store = MessageStore::open(&dir).unwrap();
+
+        assert!(store.insert(&message(1, 5)).unwrap());
+        assert!(!store.insert(&message(1, 5)).unwrap());
+        assert_eq!(
+            store
+                .insert_all(&[message(1, 3), message(1, 5), message(1, 8), message(2, 4)])
+                .unwrap(),
+            3
+        );
+
+        assert_eq!(store.len(BufferId(1)), 3);
+        assert_eq!(
+            ids(&store.range(BufferId(1), MsgId(4)..).unwrap()),
+            vec![5, 8]
+        );
+        assert_eq!(
+            ids(&store.before(BufferId(1), Some(MsgId(8)), 1).unwrap()),
+            vec![5]
+        );
+        assert_eq!(
+            ids(&store.before(BufferId(1), None, 2).unwrap()),
+            vec![5, 8]
+        );
+        assert_eq!(store.last_msg_id(BufferId(2)), Some(MsgId(4)));
+        assert!(store.range(BufferId(3), ..).unwrap().is_empty());
+        assert_eq!(
+            store.get(BufferId(2), MsgId(4)).unwrap(),
+            Some(message(2, 4))
+        );
+        assert_eq!(store.get(BufferId(2), MsgId(5)).unwrap(), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn messagestore_reopen() {
+        let dir = temp_dir("reopen");
+
+        {
+            let mut store = MessageStore::open(&dir).unwrap();
+            store
+                .set_buffers(&[buffer(1, "#test"), buffer(2, "#other")])
+                .unwrap();
+            store.insert_all(&[message(1, 1), message(2, 2)]).unwrap();
+        }
+
+        // Simulate a crash while writing a record
+        let mut segment = OpenOptions::new()
+            .append(true)
+            .open(segment_path(&dir, 0))
+            .unwrap();
+        segment.write_all(&[0, 0, 1, 0, 42]).unwrap();
+
+        let mut store = MessageStore::open(&dir).unwrap();
+        store.insert_all(&[message(1, 3)]).unwrap();
+
+        let mut store = MessageStore::open(&dir).unwrap();
+        assert_eq!(ids(&store.range(BufferId(1), ..).unwrap()), vec![1, 3]);
+        assert_eq!(store.buffer(BufferId(2)), Some(&buffer(2, "#other")));
+        assert_eq!(store.buffers().count(), 2);
+        assert_eq!(
+            store.get(BufferId(1), MsgId(1)).unwrap(),
+            Some(message(1, 1))
+        );
+        assert!(!store.insert(&message(2, 2)).unwrap());
+
+        store.update_buffer(&buffer(3, "#new")).unwrap();
+        let store = MessageStore::open(&dir).unwrap();
+        assert_eq!(store.buffer(BufferId(3)), Some(&buffer(3, "#new")));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn messagestore_truncated_segment() {
+        let dir = temp_dir("truncated");
+
+        {
+            let mut store = MessageStore::open(&dir).unwrap();
+            store.insert_all(&[message(1, 1), message(1, 2)]).unwrap();
+
+            store.segment_index = 1;
+            store.segment = open_segment(&dir, 1).unwrap();
+            store.segment_len = 0;
+            store.insert_all(&[message(1, 3), message(2, 4)]).unwrap();
+        }
+
+        // A truncated record in a segment that is not the last one
+        let mut segment = OpenOptions::new()
+            .append(true)
+            .open(segment_path(&dir, 0))
+            .unwrap();
+        segment.write_all(&[0, 0, 1, 0, 42]).unwrap();
+
+        let store = MessageStore::open(&dir).unwrap();
+        assert_eq!(store.segment_index, 1);
+        assert_eq!(ids(&store.range(BufferId(1), ..).unwrap()), vec![1, 2, 3]);
+        assert_eq!(ids(&store.before(BufferId(2), None, 5).unwrap()), vec![4]);
+
+        // The truncated segment is left alone instead of being cut
+        assert_eq!(
+            fs::metadata(segment_path(&dir, 0)).unwrap().len(),
+            2 * record(message(1, 1).serialize().unwrap()).unwrap().len() as u64 + 5
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
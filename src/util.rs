@@ -30,6 +30,50 @@ pub fn prepend_byte_len(buf: &mut Vec<u8>) {
     buf.insert(0, ulen[0]);
 }
 
+/// Prefix the bytes with their length as a big endian u32, forming a record
+///
+/// Used for the files of the message stores, read back with [`read_records`].
+pub(crate) fn record(mut bytes: Vec<u8>) -> Result<Vec<u8>, failure::Error> {
+    use crate::serialize::Serialize;
+
+    let mut res = (bytes.len() as u32).serialize()?;
+    res.append(&mut bytes);
+    Ok(res)
+}
+
+/// Split the bytes of a file into the records written with [`record`]
+///
+/// A truncated record at the end, as left behind by a crash while writing, is ignored.
+/// Returns the records and the number of bytes they take up,
+/// the file has to be cut to that length before appending new records.
+pub(crate) fn read_records(bytes: &[u8]) -> Result<(Vec<&[u8]>, usize), failure::Error> {
+    use crate::deserialize::Deserialize;
+
+    let mut records = Vec::new();
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let start = pos + 4;
+        let record = match bytes.get(pos..start) {
+            Some(len) => bytes.get(start..start + u32::parse(len)?.1 as usize),
+            None => None,
+        };
+
+        match record {
+            Some(record) => {
+                records.push(record);
+                pos = start + record.len();
+            }
+            None => {
+                log::warn!("ignoring truncated record at the end of a message store file");
+                break;
+            }
+        }
+    }
+
+    Ok((records, pos))
+}
+
 /// Insert a bytes `input` into `buf` at position `pos`
 pub fn insert_bytes(pos: usize, buf: &mut Vec<u8>, input: &mut [u8]) {
     input.reverse();